@@ -1,7 +1,9 @@
+#![allow(clippy::useless_vec, clippy::redundant_closure, clippy::needless_borrows_for_generic_args)]
+
 use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
 use claude_watch::*;
 use claude_watch::testing::*;
-use std::time::Duration;
+use clap::Parser;
 
 fn bench_activity_detection(c: &mut Criterion) {
     let test_cases = vec![
@@ -118,12 +120,12 @@ fn bench_args_parsing(c: &mut Criterion) {
 
 fn bench_llm_heuristics(c: &mut Criterion) {
     let test_cases = vec![
-        ("✅ All checks passed", llm::TaskStatus::Done),
-        ("Error: something went wrong", llm::TaskStatus::Stuck),
-        ("* Cogitating… (100s · ↑ 14.2k tokens · esc to interrupt)", llm::TaskStatus::Stuck),
-        ("Interrupted by user", llm::TaskStatus::Stuck),
-        ("Tool use: Reading file", llm::TaskStatus::Stuck),
-        ("Processing...", llm::TaskStatus::Stuck),
+        ("✅ All checks passed", ClaudeState::Done),
+        ("Error: something went wrong", ClaudeState::Errored),
+        ("* Cogitating… (100s · ↑ 14.2k tokens · esc to interrupt)", ClaudeState::Thinking),
+        ("Interrupted by user", ClaudeState::Interrupted),
+        ("Tool use: Reading file", ClaudeState::Working),
+        ("Processing...", ClaudeState::Working),
     ];
     
     let mut group = c.benchmark_group("llm_heuristics");
@@ -310,7 +312,7 @@ llm:
             let mut validator = TestValidator::new();
             validator.validate_number_range("interval", config.monitoring.interval, 1, 3600);
             validator.validate_number_range("stuck_sec", config.monitoring.stuck_sec, 5, 7200);
-            validator.validate_number_range("max_retry", config.monitoring.max_retry, 1, 100);
+            validator.validate_number_range("max_retry", config.monitoring.max_retry as u64, 1, 100);
             validator.validate_string_matches_regex("pane", &config.tmux.pane, r"^%\d+$");
             
             validator.is_valid()
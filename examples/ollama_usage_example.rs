@@ -1,3 +1,5 @@
+#![allow(clippy::single_component_path_imports)]
+
 use ollama_rs::{
     Ollama,
     generation::completion::request::GenerationRequest,
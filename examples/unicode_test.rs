@@ -1,3 +1,5 @@
+#![allow(clippy::useless_format)]
+
 //! 详细的Unicode测试
 
 use claude_watch::monitor::extract_execution_time;
@@ -1,3 +1,6 @@
+use crate::state::ClaudeState;
+use chrono::{DateTime, Duration, Local, TimeZone};
+
 /// 检测 Claude Code 特定的活动模式
 /// 
 /// 这是核心活动检测器，专注于 Claude Code 的特定输出格式
@@ -136,4 +139,290 @@ pub fn is_claude_active(text: &str) -> bool {
     }
     
     false
-}
\ No newline at end of file
+}
+
+/// 权限确认对话框中的一个选项
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptOption {
+    /// 选项编号，按下对应数字键即可选择
+    pub number: u8,
+    /// 选项文字
+    pub label: String,
+    /// 是否是当前高亮（❯）的选项
+    pub selected: bool,
+}
+
+/// 解析出的权限确认对话框
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionPrompt {
+    /// 请求使用的工具（对话框标题，如 "Bash"、"Edit"、"Create"）
+    pub tool: String,
+    /// 工具的具体内容，如要执行的命令或要编辑的文件；多行命令的各行用换行连接
+    pub command: Option<String>,
+    /// 对话框中的问题
+    pub question: String,
+    /// 可选项
+    pub options: Vec<PromptOption>,
+}
+
+impl PermissionPrompt {
+    /// 用于策略匹配和去重的文本：`<tool>: <command>`
+    pub fn subject(&self) -> String {
+        match &self.command {
+            Some(command) => format!("{}: {}", self.tool, command),
+            None => self.tool.clone(),
+        }
+    }
+
+    /// 第一个肯定选项（Yes）
+    pub fn yes_option(&self) -> Option<&PromptOption> {
+        self.options.iter().find(|o| o.label.starts_with("Yes"))
+    }
+
+    /// 第一个否定选项（No）
+    pub fn no_option(&self) -> Option<&PromptOption> {
+        self.options.iter().find(|o| o.label.starts_with("No"))
+    }
+}
+
+/// 解析屏幕末尾的权限确认 / 编号选择对话框
+///
+/// Claude Code 的对话框结构为：标题（工具）、内容（命令或文件）、
+/// "Do you want to ..." 问题，以及 `❯ 1. Yes` 形式的编号选项。
+/// 没有找到问题行或选项时返回 None
+pub fn parse_permission_prompt(text: &str) -> Option<PermissionPrompt> {
+    lazy_static::lazy_static! {
+        static ref OPTION_PATTERN: regex::Regex = regex::Regex::new(r"^(❯|>)?\s*(\d)\.\s+(.+)$").unwrap();
+    }
+
+    // 去掉对话框边框，只保留内容
+    let lines: Vec<String> = screen_tail(text, 40)
+        .lines()
+        .map(|line| line.trim().trim_matches(|c| c == '│' || c == '|').trim().to_string())
+        .collect();
+
+    let question_index = lines.iter().rposition(|line| line.starts_with("Do you want to"))?;
+    let question = lines[question_index].clone();
+
+    let options: Vec<PromptOption> = lines[question_index + 1..]
+        .iter()
+        .filter_map(|line| {
+            let caps = OPTION_PATTERN.captures(line)?;
+            Some(PromptOption {
+                number: caps[2].parse().ok()?,
+                label: caps[3].trim().to_string(),
+                selected: caps.get(1).is_some(),
+            })
+        })
+        .collect();
+    if options.is_empty() {
+        return None;
+    }
+
+    // 对话框顶部边框之后的第一行是标题，其后到分隔线或问题行之前是内容：
+    // 命令可能有多行，内容超过一行时最后一行是工具调用的说明
+    let box_start = lines[..question_index]
+        .iter()
+        .rposition(|line| line.starts_with('╭'))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let mut body: Vec<&str> = lines[box_start..question_index]
+        .iter()
+        .take_while(|line| !line.starts_with('─') && !line.starts_with('╌'))
+        .filter(|line| !line.is_empty())
+        .map(String::as_str)
+        .collect();
+    let title = if body.is_empty() { String::new() } else { body.remove(0).to_string() };
+    if body.len() > 1 {
+        body.pop();
+    }
+    let command = (!body.is_empty()).then(|| body.join("\n"));
+
+    Some(PermissionPrompt {
+        tool: normalize_tool_title(&title),
+        command,
+        question,
+        options,
+    })
+}
+
+/// 把对话框标题转换为工具名称，如 "Bash command" → "Bash"、"Edit file" → "Edit"
+fn normalize_tool_title(title: &str) -> String {
+    let title = title.trim();
+    for suffix in [" command", " file", " files"] {
+        if let Some(stripped) = title.strip_suffix(suffix) {
+            return stripped.to_string();
+        }
+    }
+    title.to_string()
+}
+
+/// 解析出的用量限制提示
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageLimit {
+    /// 提示所在的行，用于判断是否是已经处理过的旧提示
+    pub message: String,
+    /// 限制重置的时间，无法解析时为 None
+    pub resets_at: Option<DateTime<Local>>,
+}
+
+/// 解析屏幕末尾的用量限制提示及其重置时间
+///
+/// 支持两种格式：
+/// - `Claude AI usage limit reached|1760000000`（Unix 时间戳）
+/// - `5-hour limit reached ∙ resets 3pm`、`resets at 3:30pm`、`reset at 15:00`
+///
+/// 只有钟点的时间按本地时区解释；如果这个时间在 `now` 一小时之前，认为指的是第二天。
+/// 提示中附带的时区名称会被忽略
+pub fn parse_usage_limit(text: &str, now: DateTime<Local>) -> Option<UsageLimit> {
+    lazy_static::lazy_static! {
+        static ref EPOCH_PATTERN: regex::Regex = regex::Regex::new(r"limit reached\|(\d{9,})").unwrap();
+        static ref CLOCK_PATTERN: regex::Regex =
+            regex::Regex::new(r"(?i)\bresets?\s+(?:at\s+)?(\d{1,2})(?::(\d{2}))?\s*(am|pm)?\b").unwrap();
+    }
+
+    let tail = screen_tail(text, SCREEN_STATE_TAIL_LINES);
+    let lines: Vec<&str> = tail.lines().collect();
+    let index = lines
+        .iter()
+        .rposition(|line| RATE_LIMIT_PATTERNS.iter().any(|p| line.contains(p)))?;
+    let message = lines[index].trim().to_string();
+
+    // 重置时间通常和提示在同一行，个别版本会换到下一行
+    let nearby = lines[index..lines.len().min(index + 2)].join(" ");
+    let resets_at = if let Some(caps) = EPOCH_PATTERN.captures(&nearby) {
+        caps[1].parse::<i64>().ok().and_then(|secs| Local.timestamp_opt(secs, 0).single())
+    } else if let Some(caps) = CLOCK_PATTERN.captures(&nearby) {
+        let hour: u32 = caps[1].parse().ok()?;
+        let minute: u32 = caps.get(2).map_or(Some(0), |m| m.as_str().parse().ok())?;
+        let hour = match caps.get(3).map(|m| m.as_str().to_lowercase()) {
+            Some(ref meridiem) if (1..=12).contains(&hour) => hour % 12 + if meridiem == "pm" { 12 } else { 0 },
+            Some(_) => return Some(UsageLimit { message, resets_at: None }),
+            None => hour,
+        };
+        next_clock_time(now, hour, minute)
+    } else {
+        None
+    };
+
+    Some(UsageLimit { message, resets_at })
+}
+
+/// 计算 `now` 之后（允许一小时误差）最近的一个本地钟点时间
+fn next_clock_time(now: DateTime<Local>, hour: u32, minute: u32) -> Option<DateTime<Local>> {
+    let today = now.date_naive().and_hms_opt(hour, minute, 0)?;
+    let candidate = today.and_local_timezone(Local).earliest()?;
+    if candidate < now - Duration::hours(1) {
+        (today + Duration::days(1)).and_local_timezone(Local).earliest()
+    } else {
+        Some(candidate)
+    }
+}
+
+/// 只检查屏幕末尾的行数，避免滚动历史中的旧提示造成误判
+const SCREEN_STATE_TAIL_LINES: usize = 15;
+
+/// 深度思考状态关键词
+const THINKING_KEYWORDS: [&str; 8] = [
+    "Cogitating", "Thinking", "Herding", "Meandering",
+    "Reticulating", "Philosophising", "Contemplating", "Pondering",
+];
+
+/// 权限确认对话框的特征文本
+const PERMISSION_PATTERNS: [&str; 4] = [
+    "Do you want to proceed?",
+    "Do you want to make this edit",
+    "Do you want to create",
+    "Yes, and don't ask again",
+];
+
+/// 选择题形式提问的特征文本
+const QUESTION_PATTERNS: [&str; 2] = [
+    "Enter to select",
+    "to navigate · Esc to cancel",
+];
+
+/// 用量限制的特征文本
+const RATE_LIMIT_PATTERNS: [&str; 5] = [
+    "usage limit reached",
+    "Usage limit reached",
+    "limit reached ∙ resets",
+    "limit will reset",
+    "rate_limit_error",
+];
+
+/// 上下文已满的特征文本
+const CONTEXT_FULL_PATTERNS: [&str; 4] = [
+    "Prompt is too long",
+    "Context left until auto-compact: 0%",
+    "context window exceeded",
+    "Context low",
+];
+
+/// 中断状态的特征文本
+const INTERRUPTED_PATTERNS: [&str; 3] = [
+    "Interrupted by user",
+    "Aborted by user",
+    "Cancelled by user",
+];
+
+/// 从屏幕内容识别可以确定的 Claude Code 状态
+///
+/// 只识别有明确界面特征的状态：工作/思考、权限确认、提问、用量限制、上下文已满、中断。
+/// 无法确定时返回 None，由启发式完成/错误检查或 LLM 进一步判断
+pub fn detect_screen_state(text: &str) -> Option<ClaudeState> {
+    if is_claude_active(text) {
+        let thinking = text.lines().any(|line| {
+            line.contains("esc to interrupt")
+                && THINKING_KEYWORDS.iter().any(|keyword| line.contains(keyword))
+        });
+        return Some(if thinking { ClaudeState::Thinking } else { ClaudeState::Working });
+    }
+
+    let tail = screen_tail(text, SCREEN_STATE_TAIL_LINES);
+    let contains_any = |patterns: &[&str]| patterns.iter().any(|p| tail.contains(p));
+
+    if contains_any(&PERMISSION_PATTERNS) || parse_permission_prompt(text).is_some() {
+        return Some(ClaudeState::WaitingForPermission);
+    }
+    if contains_any(&RATE_LIMIT_PATTERNS) {
+        return Some(ClaudeState::RateLimited);
+    }
+    if contains_any(&CONTEXT_FULL_PATTERNS) {
+        return Some(ClaudeState::ContextFull);
+    }
+    if contains_any(&QUESTION_PATTERNS) || ends_with_question(&tail) {
+        return Some(ClaudeState::AskingUserQuestion);
+    }
+    if contains_any(&INTERRUPTED_PATTERNS) {
+        return Some(ClaudeState::Interrupted);
+    }
+
+    None
+}
+
+/// 取屏幕末尾的若干非空行
+fn screen_tail(text: &str, lines: usize) -> String {
+    let mut tail: Vec<&str> = text
+        .lines()
+        .rev()
+        .filter(|line| !line.trim().is_empty())
+        .take(lines)
+        .collect();
+    tail.reverse();
+    tail.join("\n")
+}
+
+/// Claude 最后一条消息（以 ● 开头，到输入框为止）是否以问号结尾
+fn ends_with_question(tail: &str) -> bool {
+    let lines: Vec<&str> = tail.lines().map(str::trim).collect();
+    let Some(start) = lines.iter().rposition(|line| line.starts_with('●')) else {
+        return false;
+    };
+    lines[start..]
+        .iter()
+        .take_while(|line| !line.starts_with('╭') && !line.starts_with('─') && !line.starts_with('>'))
+        .last()
+        .map(|line| line.ends_with('?') || line.ends_with('？'))
+        .unwrap_or(false)
+}
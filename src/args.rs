@@ -1,33 +1,127 @@
-use clap::Parser;
+use crate::terminal::Key;
+use clap::{Parser, Subcommand};
 
 /// 命令行参数配置
 /// 
 /// 简化实现：使用 clap 解析命令行参数，替代环境变量
 /// 支持配置文件路径、tmux 窗格 ID、LLM 后端等参数
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    /// 配置文件路径
-    #[arg(short, long, default_value = "config.yaml")]
-    pub config: String,
+    /// 配置文件路径，优先级高于系统、用户和项目配置 [默认: config.yaml，不存在时跳过]
+    #[arg(short, long, global = true)]
+    pub config: Option<String>,
 
     /// tmux pane ID (例如 %0 或 mysess:1.0)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub pane: Option<String>,
 
-    /// LLM 后端选择 [ollama, openai, openrouter, none]
-    #[arg(short, long)]
+    /// 自动发现所有 tmux 会话中的 Claude Code 窗格
+    #[arg(long, conflicts_with = "pane", global = true)]
+    pub discover: bool,
+
+    /// LLM 后端选择 [ollama, openai, openrouter, anthropic, none]
+    #[arg(short, long, global = true)]
     pub backend: Option<String>,
 
     /// 检查间隔(秒) [默认: 5]
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub interval: Option<u64>,
 
     /// 无变化多久算卡住(秒) [默认: 60]
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub stuck_sec: Option<u64>,
 
     /// 最大重试次数 [默认: 10]
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub max_retry: Option<usize>,
-}
\ No newline at end of file
+
+    /// 等同于 `status` 子命令，保留以兼容旧的脚本
+    #[arg(long)]
+    pub once: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// 没有指定 `--config` 时读取的配置文件
+pub const DEFAULT_CONFIG: &str = "config.yaml";
+
+impl Args {
+    /// `--config` 指定的配置文件，没有指定时为 `config.yaml`
+    pub fn config_file(&self) -> &str {
+        self.config.as_deref().unwrap_or(DEFAULT_CONFIG)
+    }
+
+    /// 要执行的子命令，不指定时为 `watch`，`--once` 等同于 `status`
+    pub fn command(&self) -> Command {
+        match &self.command {
+            Some(command) => command.clone(),
+            None if self.once => Command::Status,
+            None => Command::Watch,
+        }
+    }
+}
+
+/// 子命令，不指定时为 `watch`
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// 持续监控窗格，卡住时自动恢复
+    Watch,
+    /// 判断一次每个窗格（或 --pane 指定的窗格）的状态后退出
+    ///
+    /// 输出 `<pane>\t<状态>\t<判断依据>`，退出码 0=工作中/已完成 1=运行错误 2=等待处理 3=卡住/出错，
+    /// 多个窗格时取最严重的一个
+    Status,
+    /// 输出监控看到的窗格画面
+    ///
+    /// 默认输出发给 LLM 的画面：去掉界面装饰、按字符预算截断并隐藏敏感信息
+    Capture {
+        /// 输出未经处理的原始画面
+        #[arg(long)]
+        raw: bool,
+    },
+    /// 判断保存下来的画面文本，输出状态和判断依据
+    Classify {
+        /// 画面文本文件，`-` 表示从标准输入读取
+        file: String,
+        /// 只使用本地规则，不询问 LLM
+        #[arg(long)]
+        no_llm: bool,
+    },
+    /// 向窗格发送文本和按键，与恢复步骤使用同一条发送路径
+    Send {
+        /// 要发送的文本，发送后自动回车
+        #[arg(required_unless_present = "keys")]
+        text: Option<String>,
+        /// 在文本之后依次发送的按键，例如 Escape、C-c、Enter，可以重复
+        #[arg(short, long = "key", value_name = "KEY")]
+        keys: Vec<Key>,
+    },
+    /// 汇总最近几天的 LLM 用量和费用
+    Usage {
+        /// 统计的天数，包括今天
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+    },
+    /// 配置文件相关操作
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+/// `config` 的子命令
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigCommand {
+    /// 检查配置文件：未知配置项、缺少的后端配置、空的 API key、不合理的时间设置和找不到的窗格
+    Check,
+    /// 输出合并所有配置层后的有效配置，API key 会被隐藏
+    ///
+    /// 指定 --pane 时按该窗格的工作目录查找项目配置
+    Show {
+        /// 逐项列出每个值来自哪一层配置
+        #[arg(long)]
+        resolved: bool,
+    },
+}
@@ -0,0 +1,156 @@
+//! 一次性子命令：status、capture、classify、send
+//!
+//! 这些命令和监控循环使用相同的配置解析、状态判断和发送路径，
+//! 可以用来在不启动监控的情况下查看监控会怎样看待和处理一个窗格
+
+use crate::config::{Config, LlmConfig, RecoveryAction, RecoveryStep};
+use crate::discovery::discover_claude_panes;
+use crate::llm::{create_backend, simple_heuristic_check};
+use crate::monitor::{judge_screen, judge_screen_locally, pane_config};
+use crate::prompt::prepare_screen;
+use crate::recovery::deliver;
+use crate::redact::Redactor;
+use crate::state::Verdict;
+use crate::terminal::{Key, TerminalBackend};
+use std::io::{self, Read};
+
+/// `status`：判断每个窗格一次，输出 `<pane>\t<状态>\t<判断依据>` 并返回退出码
+///
+/// 多个窗格时取最严重的退出码，无法列出或捕获窗格时为 1
+pub async fn status(config: &Config, terminal: &dyn TerminalBackend) -> i32 {
+    let panes: Vec<String> = if config.tmux.discover {
+        match discover_claude_panes(terminal).await {
+            Ok(panes) => panes.into_iter().map(|info| info.id).collect(),
+            Err(e) => {
+                eprintln!("❌ 无法列出 tmux 窗格: {}", e);
+                return 1;
+            }
+        }
+    } else {
+        config.tmux.pane_list().into_iter().map(|pane| pane.id).collect()
+    };
+
+    let mut exit_code = 0;
+    for pane in panes {
+        let pane_config = pane_config(config, terminal, &pane).await;
+        let code = match terminal.capture(&pane).await {
+            Ok(text) => {
+                let verdict = judge_screen(&pane_config, &text).await;
+                println!("{}\t{}\t{}", pane, verdict.state, verdict.reason);
+                verdict.state.exit_code()
+            }
+            Err(e) => {
+                eprintln!("❌ [{}] 无法捕获窗格: {}", pane, e);
+                1
+            }
+        };
+        exit_code = exit_code.max(code);
+    }
+    exit_code
+}
+
+/// capture 和 send 操作的窗格：`--pane` 指定的或配置中唯一的窗格
+pub fn target_pane(config: &Config) -> Result<String, String> {
+    match config.tmux.pane_list().as_slice() {
+        [pane] => Ok(pane.id.clone()),
+        panes => Err(format!("配置了 {} 个窗格，请用 --pane 指定要操作的窗格", panes.len())),
+    }
+}
+
+/// `capture`：捕获目标窗格，`raw` 为 false 时整理成发给 LLM 的样子
+pub async fn capture(config: &Config, terminal: &dyn TerminalBackend, raw: bool) -> Result<String, String> {
+    let pane = target_pane(config)?;
+    let config = pane_config(config, terminal, &pane).await;
+    let text = terminal.capture(&pane).await?;
+    if raw {
+        return Ok(text);
+    }
+    let (screen, redacted) = screen_for_llm(&config.llm, &text);
+    if redacted > 0 {
+        eprintln!("🔒 已隐藏 {} 处敏感信息", redacted);
+    }
+    Ok(screen)
+}
+
+/// 降级链中第一个后端收到的画面和其中被隐藏的敏感信息数量
+///
+/// 与 `LlmChain` 一致：LLM 后端收到去掉界面装饰并按字符预算截断的画面，远程后端开启脱敏时再隐藏敏感信息；
+/// 启发式规则使用完整画面
+pub fn screen_for_llm(config: &LlmConfig, screen: &str) -> (String, usize) {
+    let Some(primary) = config.chain().into_iter().next() else {
+        return (screen.to_string(), 0);
+    };
+    let name = primary.name.as_str();
+    let backend = create_backend(name, config).ok();
+    if backend.as_ref().is_some_and(|backend| backend.is_heuristic()) {
+        return (screen.to_string(), 0);
+    }
+    let screen = prepare_screen(screen, config.screen.max_lines, config.max_chars_for(name));
+    // 无法创建的后端按远程处理，宁可多隐藏一些
+    let remote = backend.is_none_or(|backend| backend.is_remote());
+    if remote && config.redact_for(name) {
+        Redactor::new(&config.redaction).redact(&screen)
+    } else {
+        (screen, 0)
+    }
+}
+
+/// 读取 `classify` 的输入，`-` 表示标准输入
+pub fn read_screen(file: &str) -> Result<String, String> {
+    if file == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text).map_err(|e| format!("无法读取标准输入: {}", e))?;
+        Ok(text)
+    } else {
+        std::fs::read_to_string(file).map_err(|e| format!("无法读取 {}: {}", file, e))
+    }
+}
+
+/// `classify`：按监控循环的顺序判断一屏内容
+///
+/// `use_llm` 为 false 时只用本地规则，启发式得出的卡住/出错不再交给 LLM 确认
+pub async fn classify(config: &Config, text: &str, use_llm: bool) -> Verdict {
+    if use_llm {
+        return judge_screen(config, text).await;
+    }
+    judge_screen_locally(text)
+        .unwrap_or_else(|| Verdict::new(simple_heuristic_check(text), "启发式规则判断（未询问 LLM）"))
+}
+
+/// 输出 `classify` 的判断结果，第一行只有状态便于脚本读取
+pub fn print_verdict(verdict: &Verdict) {
+    println!("{}", verdict.state);
+    println!("判断依据: {}", if verdict.reason.is_empty() { "无" } else { &verdict.reason });
+    if let Some(confidence) = verdict.confidence {
+        println!("置信度: {:.2}", confidence);
+    }
+    if let Some(action) = &verdict.suggested_action {
+        println!("建议操作: {}", action);
+    }
+}
+
+/// `send`：向目标窗格依次发送文本和按键，返回执行的操作
+///
+/// 某一项发送失败时停止，不再发送后面的按键
+pub async fn send(
+    config: &Config,
+    terminal: &dyn TerminalBackend,
+    text: Option<&str>,
+    keys: &[Key],
+) -> Result<Vec<String>, String> {
+    let pane = target_pane(config)?;
+    if !terminal.pane_exists(&pane).await {
+        return Err(format!("窗格不存在: {}", pane));
+    }
+    let mut steps: Vec<RecoveryStep> = text
+        .map(|text| RecoveryStep::new(RecoveryAction::SendText { text: text.to_string() }))
+        .into_iter()
+        .collect();
+    steps.extend(keys.iter().map(|key| RecoveryStep::new(RecoveryAction::SendKey { key: *key })));
+
+    let mut sent = Vec::new();
+    for step in &steps {
+        sent.push(deliver(terminal, &pane, step).await?);
+    }
+    Ok(sent)
+}
@@ -1,6 +1,18 @@
+use crate::terminal::Key;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
 use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub mod layers;
+pub mod resolve;
+pub mod validate;
+
+pub use layers::{LayerStack, Source};
+pub use validate::{Diagnostic, Severity};
 
 /// Main configuration structure for claude-watch
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,12 +25,32 @@ pub struct Config {
     
     /// Tmux configuration
     pub tmux: TmuxConfig,
+    
+    /// Permission prompt policy
+    #[serde(default)]
+    pub permissions: PermissionConfig,
+    
+    /// Usage limit handling
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    
+    /// Recovery escalation ladder
+    #[serde(default)]
+    pub recovery: RecoveryConfig,
+
+    /// Prompt templates for judging and activation
+    #[serde(default)]
+    pub prompts: PromptConfig,
+
+    /// Layers this configuration was merged from; not part of the file format
+    #[serde(skip)]
+    pub layers: Option<Arc<LayerStack>>,
 }
 
 /// LLM backend configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LlmConfig {
-    /// Backend type: ollama, openrouter, openai, none
+    /// Backend type: ollama, openrouter, openai, anthropic, none
     pub backend: String,
     
     /// Ollama configuration
@@ -29,6 +61,339 @@ pub struct LlmConfig {
     
     /// OpenRouter configuration
     pub openrouter: Option<OpenRouterConfig>,
+    
+    /// Anthropic configuration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anthropic: Option<AnthropicConfig>,
+    
+    /// Ordered fallback chain, e.g. `[ollama, openrouter, none]`;
+    /// when empty only `backend` is used
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backends: Vec<BackendConfig>,
+    
+    /// Timeout for a single LLM call in seconds
+    #[serde(default = "default_llm_timeout")]
+    pub timeout: u64,
+    
+    /// Retries of a failed call before falling through to the next backend
+    #[serde(default = "default_llm_retries")]
+    pub retries: usize,
+    
+    /// Base delay between retries in milliseconds (doubled per retry, with jitter)
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    
+    /// Skipping of backends that keep failing
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    
+    /// Reuse of verdicts for screens already judged
+    #[serde(default)]
+    pub cache: CacheConfig,
+    
+    /// Redaction of secrets before screen content is sent to a backend
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    
+    /// Trimming of the screen sent for judging
+    #[serde(default)]
+    pub screen: ScreenConfig,
+    
+    /// Token usage, cost accounting and daily budget
+    #[serde(default)]
+    pub usage: UsageConfig,
+}
+
+impl LlmConfig {
+    /// Backends to try, in order
+    pub fn chain(&self) -> Vec<BackendConfig> {
+        if self.backends.is_empty() {
+            vec![BackendConfig::new(&self.backend)]
+        } else {
+            self.backends.clone()
+        }
+    }
+    
+    /// Chain entry for a backend, or one without overrides
+    pub fn backend_or_default(&self, name: &str) -> BackendConfig {
+        self.backends
+            .iter()
+            .find(|b| b.name == name)
+            .cloned()
+            .unwrap_or_else(|| BackendConfig::new(name))
+    }
+    
+    /// Effective timeout for a backend
+    pub fn timeout_for(&self, name: &str) -> Duration {
+        Duration::from_secs(self.backend_or_default(name).timeout.unwrap_or(self.timeout))
+    }
+    
+    /// Effective retry count for a backend
+    pub fn retries_for(&self, name: &str) -> usize {
+        self.backend_or_default(name).retries.unwrap_or(self.retries)
+    }
+    
+    /// Whether text sent to a backend is redacted
+    pub fn redact_for(&self, name: &str) -> bool {
+        self.backend_or_default(name).redact.unwrap_or(self.redaction.enabled)
+    }
+    
+    /// Effective screen character budget for a backend
+    pub fn max_chars_for(&self, name: &str) -> usize {
+        self.backend_or_default(name).max_chars.unwrap_or(self.screen.max_chars)
+    }
+}
+
+fn default_llm_timeout() -> u64 {
+    30
+}
+
+fn default_llm_retries() -> usize {
+    1
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    500
+}
+
+/// Entry of the LLM fallback chain
+///
+/// Accepts either a bare backend name (`"ollama"`) or a map with overrides
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "BackendEntry")]
+pub struct BackendConfig {
+    /// Backend name: ollama, openai, openrouter, anthropic, none
+    pub name: String,
+    
+    /// Timeout override in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    
+    /// Retry count override
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<usize>,
+    
+    /// Redaction override, e.g. `false` for a local Ollama
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redact: Option<bool>,
+    
+    /// Screen character budget override, e.g. smaller for a local model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_chars: Option<usize>,
+}
+
+/// Raw YAML form of a chain entry
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BackendEntry {
+    Name(String),
+    Full {
+        name: String,
+        #[serde(default)]
+        timeout: Option<u64>,
+        #[serde(default)]
+        retries: Option<usize>,
+        #[serde(default)]
+        redact: Option<bool>,
+        #[serde(default)]
+        max_chars: Option<usize>,
+    },
+}
+
+impl From<BackendEntry> for BackendConfig {
+    fn from(entry: BackendEntry) -> Self {
+        match entry {
+            BackendEntry::Name(name) => BackendConfig::new(&name),
+            BackendEntry::Full { name, timeout, retries, redact, max_chars } => {
+                BackendConfig { name, timeout, retries, redact, max_chars }
+            }
+        }
+    }
+}
+
+impl BackendConfig {
+    /// Create a chain entry without overrides
+    pub fn new(name: &str) -> Self {
+        BackendConfig {
+            name: name.to_string(),
+            timeout: None,
+            retries: None,
+            redact: None,
+            max_chars: None,
+        }
+    }
+}
+
+/// Circuit breaker configuration
+///
+/// A backend that fails `failures` calls in a row is skipped for
+/// `cooldown` seconds; the first call after that decides whether it stays skipped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failed calls before the backend is skipped
+    #[serde(default = "default_breaker_failures")]
+    pub failures: u32,
+    
+    /// Seconds to skip a tripped backend
+    #[serde(default = "default_breaker_cooldown")]
+    pub cooldown: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            failures: default_breaker_failures(),
+            cooldown: default_breaker_cooldown(),
+        }
+    }
+}
+
+fn default_breaker_failures() -> u32 {
+    3
+}
+
+fn default_breaker_cooldown() -> u64 {
+    300
+}
+
+/// Verdict cache configuration
+///
+/// Screens are keyed by their normalized content, so a pane sitting on the
+/// same stuck screen is judged by the LLM only once per `ttl`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// Seconds a verdict stays valid (0 disables the cache)
+    #[serde(default = "default_cache_ttl")]
+    pub ttl: u64,
+    
+    /// Maximum number of cached verdicts
+    #[serde(default = "default_cache_max_entries")]
+    pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            ttl: default_cache_ttl(),
+            max_entries: default_cache_max_entries(),
+        }
+    }
+}
+
+fn default_cache_ttl() -> u64 {
+    600
+}
+
+fn default_cache_max_entries() -> usize {
+    256
+}
+
+/// Redaction configuration
+///
+/// Built-in patterns cover common API key formats, JWTs, private keys,
+/// bearer tokens and secret-looking `NAME=value` assignments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    /// Redact text sent to backends unless the backend entry overrides it
+    #[serde(default = "default_redaction_enabled")]
+    pub enabled: bool,
+    
+    /// Extra regexes whose matches are redacted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        RedactionConfig {
+            enabled: default_redaction_enabled(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+fn default_redaction_enabled() -> bool {
+    true
+}
+
+/// Screen trimming configuration
+///
+/// UI chrome (banner, box borders, shortcut hints) is always removed; what is
+/// left is cut to the last `max_lines` non-empty lines and `max_chars` characters
+/// (roughly 4 characters per token for English, 1-2 for Chinese)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScreenConfig {
+    /// Non-empty lines kept from the bottom of the screen
+    #[serde(default = "default_screen_max_lines")]
+    pub max_lines: usize,
+    
+    /// Character budget, overridable per backend
+    #[serde(default = "default_screen_max_chars")]
+    pub max_chars: usize,
+}
+
+impl Default for ScreenConfig {
+    fn default() -> Self {
+        ScreenConfig {
+            max_lines: default_screen_max_lines(),
+            max_chars: default_screen_max_chars(),
+        }
+    }
+}
+
+fn default_screen_max_lines() -> usize {
+    80
+}
+
+fn default_screen_max_chars() -> usize {
+    8000
+}
+
+/// Usage accounting configuration
+///
+/// Every LLM call is appended to `file` with its token counts and the cost
+/// estimated from `prices`; once today's cost reaches `daily_budget` remote
+/// backends are skipped until midnight and the heuristic judge takes over
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageConfig {
+    /// Usage log (JSON Lines), default `$XDG_DATA_HOME/claude-watch/usage.jsonl`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    
+    /// Daily spend limit in USD
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_budget: Option<f64>,
+    
+    /// USD per million tokens, keyed by model name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub prices: HashMap<String, ModelPrice>,
+}
+
+impl UsageConfig {
+    /// Path of the usage log
+    pub fn path(&self) -> PathBuf {
+        if let Some(file) = &self.file {
+            return PathBuf::from(file);
+        }
+        let data_dir = env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+        match data_dir {
+            Some(dir) => dir.join("claude-watch").join("usage.jsonl"),
+            None => PathBuf::from("usage.jsonl"),
+        }
+    }
+}
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    /// Prompt (input) tokens
+    pub input: f64,
+    
+    /// Completion (output) tokens
+    pub output: f64,
 }
 
 /// Ollama configuration
@@ -45,8 +410,17 @@ pub struct OllamaConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenAiConfig {
     /// OpenAI API key
+    #[serde(default)]
     pub api_key: String,
     
+    /// File holding the API key, used when `api_key` is empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_file: Option<String>,
+    
+    /// Command printing the API key (e.g. `pass show openai`), used when `api_key` is empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_cmd: Option<String>,
+    
     /// OpenAI API base URL
     pub api_base: String,
     
@@ -58,12 +432,57 @@ pub struct OpenAiConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenRouterConfig {
     /// OpenRouter API key
+    #[serde(default)]
     pub api_key: String,
     
+    /// File holding the API key, used when `api_key` is empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_file: Option<String>,
+    
+    /// Like `OpenAiConfig::api_key_cmd`, e.g. `pass show openrouter`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_cmd: Option<String>,
+    
     /// Model to use
     pub model: String,
 }
 
+/// Anthropic configuration (Messages API)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnthropicConfig {
+    /// Anthropic API key, sent as `x-api-key`
+    #[serde(default)]
+    pub api_key: String,
+    
+    /// File holding the API key, used when `api_key` is empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_file: Option<String>,
+    
+    /// Shell command whose stdout is the key, e.g. `op read op://Private/Anthropic/credential`;
+    /// run once per config load when neither `api_key` nor `api_key_file` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_cmd: Option<String>,
+    
+    /// API base URL, without the `/v1/messages` path
+    #[serde(default = "default_anthropic_base_url")]
+    pub base_url: String,
+    
+    /// Model to use
+    pub model: String,
+    
+    /// Value of the `anthropic-version` header
+    #[serde(default = "default_anthropic_version")]
+    pub version: String,
+}
+
+fn default_anthropic_base_url() -> String {
+    "https://api.anthropic.com".to_string()
+}
+
+fn default_anthropic_version() -> String {
+    "2023-06-01".to_string()
+}
+
 /// Monitoring configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MonitoringConfig {
@@ -80,64 +499,400 @@ pub struct MonitoringConfig {
 /// Tmux configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TmuxConfig {
-    /// Tmux pane ID
+    /// Tmux pane ID (used when `panes` is empty)
+    #[serde(default = "default_pane")]
     pub pane: String,
+    
+    /// Panes watched concurrently, each with optional overrides
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub panes: Vec<PaneConfig>,
+    
+    /// Discover Claude Code panes across all tmux sessions instead of
+    /// watching a fixed list (entries in `panes` still provide overrides)
+    #[serde(default)]
+    pub discover: bool,
+    
+    /// Seconds between discovery scans
+    #[serde(default = "default_discover_interval")]
+    pub discover_interval: u64,
 }
 
-impl Config {
-    /// Load configuration from file or create default
-    pub fn load(config_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        if let Ok(content) = fs::read_to_string(config_path) {
-            let config: Config = serde_yaml::from_str(&content)?;
-            Ok(config)
+/// How permission prompts are answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionMode {
+    /// Never answer; leave the prompt and notify
+    #[default]
+    AlwaysAsk,
+    /// Answer according to the allow / deny lists, ask for everything else
+    Policy,
+}
+
+/// Permission prompt configuration
+///
+/// Patterns are regexes matched against `<tool>: <command>`,
+/// e.g. `Bash: cargo test --workspace` or `Edit: src/main.rs`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PermissionConfig {
+    /// Answering mode
+    #[serde(default)]
+    pub mode: PermissionMode,
+    
+    /// Requests whose whole `<tool>: <command>` matches one of these are approved
+    #[serde(default)]
+    pub allow: Vec<String>,
+    
+    /// Requests whose whole `<tool>: <command>` matches one of these are rejected (checked before `allow`)
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+/// Usage limit configuration
+///
+/// While a pane is rate limited no recovery action is taken; once the
+/// reset time has passed the continuation message is sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Message sent to the pane when the limit has reset
+    #[serde(default = "default_resume_message")]
+    pub resume_message: String,
+    
+    /// Extra seconds to wait after the reset time before resuming
+    #[serde(default = "default_resume_delay")]
+    pub resume_delay: u64,
+    
+    /// Seconds to wait when the reset time cannot be parsed from the screen
+    #[serde(default = "default_fallback_wait")]
+    pub fallback_wait: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            resume_message: default_resume_message(),
+            resume_delay: default_resume_delay(),
+            fallback_wait: default_fallback_wait(),
+        }
+    }
+}
+
+fn default_resume_message() -> String {
+    "continue".to_string()
+}
+
+fn default_resume_delay() -> u64 {
+    60
+}
+
+fn default_fallback_wait() -> u64 {
+    3600
+}
+
+/// Language of the built-in prompt templates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// Chinese
+    #[default]
+    Zh,
+    /// English
+    En,
+}
+
+/// Prompt template configuration
+///
+/// Templates may use `{{screen}}`, `{{screen_tail}}`, `{{pane}}`, `{{idle_seconds}}`,
+/// `{{state}}`, `{{last_action}}`, `{{failed_attempts}}` and `{{task_goal}}`
+///
+/// In the judge template `{{screen}}` only points at the user message, which carries the screen
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptConfig {
+    /// Language of the built-in templates: zh or en
+    #[serde(default)]
+    pub language: Language,
+
+    /// Judge template file (system prompt; the screen is sent as the user message)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub judge: Option<String>,
+
+    /// Activation template file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activation: Option<String>,
+
+    /// What the watched session is working on, available as `{{task_goal}}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_goal: Option<String>,
+}
+
+/// Recovery configuration
+///
+/// When a pane is stuck, one step of the ladder is executed per stuck
+/// detection. A step that fails `attempts` times escalates to the next one;
+/// any success resets the ladder. An empty list uses the built-in ladder
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecoveryConfig {
+    /// Escalation steps, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<RecoveryStep>,
+}
+
+/// A single recovery step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecoveryStep {
+    /// What to do
+    #[serde(flatten)]
+    pub action: RecoveryAction,
+    
+    /// Seconds to wait before checking the result (defaults to `monitoring.stuck_sec`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait: Option<u64>,
+    
+    /// How to decide whether the step worked
+    #[serde(default)]
+    pub check: SuccessCheck,
+    
+    /// Number of stuck detections this step is tried for before escalating
+    #[serde(default = "default_attempts")]
+    pub attempts: usize,
+}
+
+/// Recovery action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RecoveryAction {
+    /// Type a message and press Enter
+    SendText { text: String },
+    /// Press a key (e.g. `Escape`, `C-c`, `Enter`, `Up`)
+    SendKey { key: Key },
+    /// Ask the LLM for an activation message and send it
+    LlmMessage {
+        /// Text sent instead when the LLM is unavailable
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fallback: Option<String>,
+    },
+    /// Run a shell command (`sh -c`); `CLAUDE_WATCH_PANE` is set in its environment
+    ShellHook {
+        command: String,
+        /// Seconds before the command is killed (defaults to 60)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout: Option<u64>,
+    },
+    /// Print a notification; `{pane}` is replaced by the pane ID
+    Notify { message: String },
+}
+
+/// How a recovery step is judged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuccessCheck {
+    /// New substantial output appeared (not just a ticking timer)
+    #[default]
+    Progress,
+    /// The screen changed at all
+    Changed,
+    /// Claude Code shows its execution bar again
+    Active,
+    /// Never succeeds; always escalate to the next step
+    None,
+}
+
+fn default_attempts() -> usize {
+    1
+}
+
+impl RecoveryStep {
+    /// Step with default wait, check and attempts
+    pub fn new(action: RecoveryAction) -> Self {
+        RecoveryStep {
+            action,
+            wait: None,
+            check: SuccessCheck::default(),
+            attempts: default_attempts(),
+        }
+    }
+    
+    /// Short description used in logs
+    pub fn describe(&self) -> String {
+        match &self.action {
+            RecoveryAction::SendText { text } => format!("send_text \"{}\"", text),
+            RecoveryAction::SendKey { key } => format!("send_key {}", key),
+            RecoveryAction::LlmMessage { .. } => "llm_message".to_string(),
+            RecoveryAction::ShellHook { command, .. } => format!("shell_hook `{}`", command),
+            RecoveryAction::Notify { .. } => "notify".to_string(),
+        }
+    }
+}
+
+/// Per-pane watch configuration
+///
+/// Accepts either a bare pane id (`"%1"`) or a map with overrides
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "PaneEntry")]
+pub struct PaneConfig {
+    /// Tmux pane ID
+    pub id: String,
+    
+    /// Check interval override in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+    
+    /// Stuck timeout override in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stuck_sec: Option<u64>,
+    
+    /// LLM backend override
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+}
+
+/// Raw YAML form of a pane entry
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PaneEntry {
+    Id(String),
+    Full {
+        id: String,
+        #[serde(default)]
+        interval: Option<u64>,
+        #[serde(default)]
+        stuck_sec: Option<u64>,
+        #[serde(default)]
+        backend: Option<String>,
+    },
+}
+
+impl From<PaneEntry> for PaneConfig {
+    fn from(entry: PaneEntry) -> Self {
+        match entry {
+            PaneEntry::Id(id) => PaneConfig::new(&id),
+            PaneEntry::Full { id, interval, stuck_sec, backend } => PaneConfig {
+                id,
+                interval,
+                stuck_sec,
+                backend,
+            },
+        }
+    }
+}
+
+impl PaneConfig {
+    /// Create a pane entry without overrides
+    pub fn new(id: &str) -> Self {
+        PaneConfig {
+            id: id.to_string(),
+            interval: None,
+            stuck_sec: None,
+            backend: None,
+        }
+    }
+}
+
+fn default_pane() -> String {
+    "%0".to_string()
+}
+
+fn default_discover_interval() -> u64 {
+    30
+}
+
+impl TmuxConfig {
+    /// Panes to watch: `panes` if set, otherwise the single `pane`
+    pub fn pane_list(&self) -> Vec<PaneConfig> {
+        if self.panes.is_empty() {
+            vec![PaneConfig::new(&self.pane)]
         } else {
-            // Create default configuration
-            Ok(Config::default())
+            self.panes.clone()
         }
     }
     
-    /// Create configuration from command line arguments
-    pub fn from_args(args: &crate::args::Args) -> Self {
-        let backend = args.backend.clone().unwrap_or("ollama".to_string());
+    /// Configured entry for a pane id, or an entry without overrides
+    pub fn pane_or_default(&self, id: &str) -> PaneConfig {
+        self.panes
+            .iter()
+            .find(|p| p.id == id)
+            .cloned()
+            .unwrap_or_else(|| PaneConfig::new(id))
+    }
+}
+
+impl Config {
+    /// Effective configuration for a single pane
+    ///
+    /// Applies the pane's overrides and points `tmux.pane` at it, so the
+    /// monitor can keep reading everything from one `Config`
+    pub fn for_pane(&self, pane: &PaneConfig) -> Config {
+        let mut config = self.clone();
+        config.tmux.pane = pane.id.clone();
+        config.tmux.panes = Vec::new();
+        if let Some(interval) = pane.interval {
+            config.monitoring.interval = interval;
+        }
+        if let Some(stuck_sec) = pane.stuck_sec {
+            config.monitoring.stuck_sec = stuck_sec;
+        }
+        if let Some(backend) = &pane.backend {
+            // The override replaces the whole fallback chain
+            config.llm.backend = backend.clone();
+            config.llm.backends.clear();
+        }
+        config
+    }
+    
+    /// Recovery ladder in effect
+    ///
+    /// Falls back to the built-in ladder: an LLM activation message,
+    /// `max_retry` plain retries, then increasingly forceful nudges
+    pub fn recovery_steps(&self) -> Vec<RecoveryStep> {
+        if !self.recovery.steps.is_empty() {
+            return self.recovery.steps.clone();
+        }
         
-        Config {
-            llm: LlmConfig {
-                backend: backend.clone(),
-                ollama: if backend == "ollama" {
-                    Some(OllamaConfig {
-                        url: env::var("OLLAMA_URL").unwrap_or_else(|_| "http://localhost:11434".to_string()),
-                        model: "qwen2.5:3b".to_string(),
-                    })
-                } else {
-                    None
-                },
-                openai: if backend == "openai" {
-                    Some(OpenAiConfig {
-                        api_key: env::var("OPENAI_API_KEY").unwrap_or_default(),
-                        api_base: env::var("OPENAI_API_BASE").unwrap_or_else(|_| "https://api.openai.com/v1".to_string()),
-                        model: "gpt-4o".to_string(),
-                    })
-                } else {
-                    None
-                },
-                openrouter: if backend == "openrouter" {
-                    Some(OpenRouterConfig {
-                        api_key: env::var("OPENROUTER_KEY").unwrap_or_default(),
-                        model: env::var("OPENROUTER_MODEL").unwrap_or_else(|_| "qwen/qwen-2.5-7b-instruct".to_string()),
-                    })
-                } else {
-                    None
-                },
+        let send_text = |text: &str| RecoveryStep::new(RecoveryAction::SendText { text: text.to_string() });
+        vec![
+            RecoveryStep::new(RecoveryAction::LlmMessage { fallback: Some("Retry".to_string()) }),
+            RecoveryStep {
+                attempts: self.monitoring.max_retry.max(1),
+                ..send_text("Retry")
             },
-            monitoring: MonitoringConfig {
-                interval: args.interval.unwrap_or(5),
-                stuck_sec: args.stuck_sec.unwrap_or(60),
-                max_retry: args.max_retry.unwrap_or(10),
-            },
-            tmux: TmuxConfig {
-                pane: args.pane.clone().unwrap_or("%0".to_string()),
+            send_text("请继续你的工作"),
+            send_text("你好，看起来你可能卡住了，请继续处理任务"),
+            send_text("/compact"),
+            RecoveryStep::new(RecoveryAction::SendKey { key: Key::Escape }),
+            RecoveryStep::new(RecoveryAction::SendKey { key: Key::CtrlC }),
+            RecoveryStep {
+                check: SuccessCheck::None,
+                ..RecoveryStep::new(RecoveryAction::Notify {
+                    message: "所有恢复步骤都无效，{pane} 可能需要手动干预或重启 Claude Code".to_string(),
+                })
             },
+        ]
+    }
+    
+    /// Load configuration from a file
+    ///
+    /// Expands `${VAR}` references and resolves API keys. Fails when the file
+    /// cannot be read, does not parse, contains unknown keys or a secret cannot
+    /// be resolved; semantic checks are left to [`Config::validate`] so that command
+    /// line overrides can be applied first
+    pub fn load(config_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(config_path)
+            .map_err(|e| format!("无法读取配置文件 {}: {}", config_path, e))?;
+        let content = resolve::interpolate(&content).map_err(|d| d.render(config_path))?;
+        let (mut config, mut diagnostics) = validate::parse(&content).map_err(|d| d.render(config_path))?;
+        diagnostics.extend(config.resolve_secrets().into_iter().map(|d| d.locate_in(&content)));
+        if validate::has_errors(&diagnostics) {
+            let errors: Vec<String> = diagnostics.iter().map(|d| d.render(config_path)).collect();
+            return Err(errors.join("\n").into());
         }
+        Ok(config)
+    }
+    
+    /// Create configuration from command line arguments, without a config file
+    pub fn from_args(args: &crate::args::Args) -> Self {
+        let mut config = Config::from_env();
+        config.apply_args(args);
+        config.resolve_secrets();
+        config
     }
 }
 
@@ -152,13 +907,34 @@ impl Default for Config {
                 }),
                 openai: Some(OpenAiConfig {
                     api_key: "".to_string(),
+                    api_key_file: None,
+                    api_key_cmd: None,
                     api_base: "https://api.openai.com/v1".to_string(),
                     model: "gpt-4o".to_string(),
                 }),
                 openrouter: Some(OpenRouterConfig {
                     api_key: "".to_string(),
+                    api_key_file: None,
+                    api_key_cmd: None,
                     model: "qwen/qwen-2.5-7b-instruct".to_string(),
                 }),
+                anthropic: Some(AnthropicConfig {
+                    api_key: "".to_string(),
+                    api_key_file: None,
+                    api_key_cmd: None,
+                    base_url: default_anthropic_base_url(),
+                    model: "claude-3-5-haiku-latest".to_string(),
+                    version: default_anthropic_version(),
+                }),
+                backends: Vec::new(),
+                timeout: default_llm_timeout(),
+                retries: default_llm_retries(),
+                retry_backoff_ms: default_retry_backoff_ms(),
+                circuit_breaker: CircuitBreakerConfig::default(),
+                cache: CacheConfig::default(),
+                redaction: RedactionConfig::default(),
+                screen: ScreenConfig::default(),
+                usage: UsageConfig::default(),
             },
             monitoring: MonitoringConfig {
                 interval: 5,
@@ -167,7 +943,15 @@ impl Default for Config {
             },
             tmux: TmuxConfig {
                 pane: "%0".to_string(),
+                panes: Vec::new(),
+                discover: false,
+                discover_interval: default_discover_interval(),
             },
+            permissions: PermissionConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            recovery: RecoveryConfig::default(),
+            prompts: PromptConfig::default(),
+            layers: None,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,598 @@
+//! Layered configuration
+//!
+//! The effective configuration is merged from several layers, each one
+//! overriding the ones before it field by field:
+//!
+//! 1. built-in defaults
+//! 2. legacy environment variables (`OLLAMA_URL`, `OPENAI_API_BASE`, ...)
+//! 3. the system file `/etc/claude-watch/config.yaml`
+//! 4. the user file `$XDG_CONFIG_HOME/claude-watch/config.yaml` (`~/.config` by default)
+//! 5. `config.yaml` in the current directory, when `--config` is not given
+//! 6. the project file `.claude-watch.yaml`, found by walking up from the
+//!    pane's working directory
+//! 7. the file named by `--config`; unlike the other files it must exist
+//! 8. command line flags
+//!
+//! Mappings merge key by key; scalars and lists replace whatever is below
+//! them. Every layer keeps its own text, so a diagnostic points into the file
+//! that actually set the offending value.
+
+use super::resolve::SecretCache;
+use super::validate::{self, Diagnostic};
+use super::Config;
+use crate::args::Args;
+use crate::redact::REDACTED;
+use serde_yaml::{Mapping, Value};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// System-wide configuration file
+pub const SYSTEM_FILE: &str = "/etc/claude-watch/config.yaml";
+
+/// Project file looked up from a pane's working directory and its parents
+pub const PROJECT_FILE: &str = ".claude-watch.yaml";
+
+/// Keys that, when a layer sets them, drop related keys set by the layers below
+///
+/// A single backend or an API key source is one setting spread over several
+/// keys; without this a project file switching to `api_key_cmd` would clash
+/// with the `api_key` of the user file
+const REPLACES: [(&str, &[&str]); 4] = [
+    ("backend", &["backends"]),
+    ("api_key", &["api_key_file", "api_key_cmd"]),
+    ("api_key_file", &["api_key", "api_key_cmd"]),
+    ("api_key_cmd", &["api_key", "api_key_file"]),
+];
+
+/// Where a layer comes from, in order of increasing priority
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    Env,
+    System(PathBuf),
+    User(PathBuf),
+    /// The implicit `config.yaml` in the current directory
+    Local(PathBuf),
+    Project(PathBuf),
+    /// The file given with `--config`
+    File(PathBuf),
+    Cli,
+}
+
+impl Source {
+    fn rank(&self) -> u8 {
+        match self {
+            Source::Default => 0,
+            Source::Env => 1,
+            Source::System(_) => 2,
+            Source::User(_) => 3,
+            Source::Local(_) => 4,
+            Source::Project(_) => 5,
+            Source::File(_) => 6,
+            Source::Cli => 7,
+        }
+    }
+
+    /// The file behind the layer, if there is one
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Source::System(path)
+            | Source::User(path)
+            | Source::Local(path)
+            | Source::Project(path)
+            | Source::File(path) => Some(path),
+            Source::Default | Source::Env | Source::Cli => None,
+        }
+    }
+
+    /// Kind of layer followed by its file, e.g. `项目配置 /src/app/.claude-watch.yaml`
+    pub fn describe(&self) -> String {
+        let kind = match self {
+            Source::Default => "默认值",
+            Source::Env => "环境变量",
+            Source::System(_) => "系统配置",
+            Source::User(_) => "用户配置",
+            Source::Local(_) => "当前目录配置",
+            Source::Project(_) => "项目配置",
+            Source::File(_) => "配置文件",
+            Source::Cli => "命令行参数",
+        };
+        match self.path() {
+            Some(path) => format!("{} {}", kind, path.display()),
+            None => kind.to_string(),
+        }
+    }
+}
+
+/// The file name, or the kind of layer when there is no file; used as the
+/// location prefix of diagnostics
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path() {
+            Some(path) => write!(f, "{}", path.display()),
+            None => write!(f, "{}", self.describe()),
+        }
+    }
+}
+
+/// One layer of configuration
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub source: Source,
+    /// Interpolated YAML text; empty for layers that do not come from a file
+    pub content: String,
+    /// Always a mapping
+    pub value: Value,
+}
+
+impl Layer {
+    fn new(source: Source, value: Value) -> Self {
+        Layer { source, content: String::new(), value }
+    }
+
+    /// Read, interpolate and parse a file layer; a missing file is not an error
+    fn read(source: Source) -> Result<Option<Self>, Diagnostic> {
+        let path = source.path().expect("file layer").to_path_buf();
+        let file = path.display().to_string();
+        let raw = match fs::read_to_string(&path) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Diagnostic::error("", format!("无法读取配置文件: {}", e)).with_file(file)),
+        };
+        let content = super::resolve::interpolate(&raw).map_err(|d| d.with_file(file.as_str()))?;
+        let value = match serde_yaml::from_str(&content) {
+            Ok(Value::Null) => Value::Mapping(Mapping::new()),
+            Ok(value @ Value::Mapping(_)) => value,
+            Ok(_) => return Err(Diagnostic::error("", "配置文件的顶层必须是映射").with_file(file)),
+            Err(e) => return Err(validate::yaml_error(&e).with_file(file)),
+        };
+        Ok(Some(Layer { source, content, value }))
+    }
+
+    /// Attribute a diagnostic to this layer and locate it in the file
+    fn locate(&self, diagnostic: Diagnostic) -> Diagnostic {
+        diagnostic.with_file(self.source.to_string()).locate_in(&self.content)
+    }
+}
+
+/// Where to look for configuration files
+#[derive(Debug, Clone)]
+pub struct Locations {
+    pub system: PathBuf,
+    /// `None` when neither `XDG_CONFIG_HOME` nor `HOME` is set
+    pub user: Option<PathBuf>,
+    /// Directory the project file is looked up from
+    pub cwd: PathBuf,
+}
+
+impl Locations {
+    /// The standard locations, with the project file looked up from the current directory
+    pub fn from_env() -> Self {
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+        Locations {
+            system: PathBuf::from(SYSTEM_FILE),
+            user: config_home.map(|dir| dir.join("claude-watch").join("config.yaml")),
+            cwd: env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        }
+    }
+}
+
+/// The nearest project file in `dir` or one of its parents
+pub fn find_project_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().map(|dir| dir.join(PROJECT_FILE)).find(|path| path.is_file())
+}
+
+/// A leaf of the effective configuration and where it was set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedValue {
+    /// Dotted path, e.g. `monitoring.interval`
+    pub path: String,
+    /// The value in YAML flow style, with API keys redacted
+    pub value: String,
+    pub source: String,
+}
+
+impl fmt::Display for ResolvedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}  # {}", self.path, self.value, self.source)
+    }
+}
+
+/// The layers of a configuration, lowest priority first
+#[derive(Debug, Clone)]
+pub struct LayerStack {
+    layers: Vec<Layer>,
+    /// Warnings found while collecting the layers, reported by `resolve`
+    warnings: Vec<Diagnostic>,
+    /// Keys read by this stack, shared with the stacks `for_dir` derives from it
+    secrets: Arc<SecretCache>,
+}
+
+impl LayerStack {
+    /// Collect the layers for a run from the standard locations
+    pub fn discover(args: &Args) -> Result<Self, Diagnostic> {
+        Self::discover_in(args, &Locations::from_env())
+    }
+
+    /// Collect the layers for a run
+    ///
+    /// Missing system, user and project files are skipped. A missing
+    /// `--config` file is an error, and a missing implicit `config.yaml` a
+    /// warning, so a typo in the path does not silently fall back to the
+    /// other layers. Only an explicit `--config` outranks the project file
+    pub fn discover_in(args: &Args, locations: &Locations) -> Result<Self, Diagnostic> {
+        let defaults = serde_yaml::to_value(Config::default()).unwrap_or(Value::Null);
+        let from_env = serde_yaml::to_value(Config::from_env()).unwrap_or(Value::Null);
+        let env_layer = diff(&from_env, &defaults).unwrap_or(Value::Mapping(Mapping::new()));
+        let mut stack = LayerStack {
+            layers: vec![Layer::new(Source::Default, defaults), Layer::new(Source::Env, env_layer)],
+            warnings: Vec::new(),
+            secrets: Arc::default(),
+        };
+
+        let mut files = vec![Source::System(locations.system.clone())];
+        files.extend(locations.user.clone().map(Source::User));
+        files.extend(find_project_file(&locations.cwd).map(Source::Project));
+        for source in files {
+            stack.add_file(source)?;
+        }
+
+        let file = args.config_file();
+        if Path::new(file).exists() {
+            let path = PathBuf::from(file);
+            stack.add_file(if args.config.is_some() { Source::File(path) } else { Source::Local(path) })?;
+        } else if args.config.is_some() {
+            return Err(Diagnostic::error("", "配置文件不存在").with_file(file));
+        } else {
+            let warning = Diagnostic::warning("", "配置文件不存在，只使用系统、用户和项目配置");
+            stack.warnings.push(warning.with_file(file));
+        }
+        stack.insert(Layer::new(Source::Cli, cli_layer(args)));
+        Ok(stack)
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// The project file in use, if any
+    pub fn project_file(&self) -> Option<&Path> {
+        self.layers.iter().find_map(|layer| match &layer.source {
+            Source::Project(path) => Some(path.as_path()),
+            _ => None,
+        })
+    }
+
+    /// The same stack with the project file found from `dir` in place of the
+    /// current one, or `None` when that is the file already in use
+    ///
+    /// API keys already read by this stack are reused
+    pub fn for_dir(&self, dir: &Path) -> Result<Option<Self>, Diagnostic> {
+        let project = find_project_file(dir);
+        if project.as_deref() == self.project_file() {
+            return Ok(None);
+        }
+        let mut stack = self.clone();
+        stack.layers.retain(|layer| !matches!(layer.source, Source::Project(_)));
+        if let Some(project) = project {
+            stack.add_file(Source::Project(project))?;
+        }
+        Ok(Some(stack))
+    }
+
+    /// Add a file layer unless it does not exist or is already part of the stack
+    fn add_file(&mut self, source: Source) -> Result<(), Diagnostic> {
+        let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let path = canonical(source.path().expect("file layer"));
+        if self.layers.iter().filter_map(|layer| layer.source.path()).any(|other| canonical(other) == path) {
+            return Ok(());
+        }
+        if let Some(layer) = Layer::read(source)? {
+            self.insert(layer);
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, layer: Layer) {
+        let rank = layer.source.rank();
+        let index = self.layers.iter().position(|other| other.source.rank() > rank).unwrap_or(self.layers.len());
+        self.layers.insert(index, layer);
+    }
+
+    /// All layers merged into one YAML value
+    pub fn merged(&self) -> Value {
+        let mut merged = Value::Mapping(Mapping::new());
+        for layer in &self.layers {
+            merge(&mut merged, &layer.value);
+        }
+        merged
+    }
+
+    /// Merge the layers into a configuration, with secrets resolved and
+    /// diagnostics attributed to the layer that set each value
+    ///
+    /// Only unreadable or unparsable files are returned as `Err`; the caller
+    /// decides what to do with error diagnostics
+    pub fn resolve(self) -> Result<(Config, Vec<Diagnostic>), Diagnostic> {
+        let mut config: Config = serde_yaml::from_value(self.merged()).map_err(|e| self.blame(&e))?;
+
+        let mut diagnostics = Vec::new();
+        if self.layers.iter().all(|layer| layer.source.path().is_none()) {
+            diagnostics.push(Diagnostic::warning("", "没有找到配置文件，使用默认配置"));
+        } else {
+            diagnostics.extend(self.warnings.iter().cloned());
+        }
+        let known = serde_yaml::to_value(&config).unwrap_or(Value::Null);
+        for layer in self.layers.iter().filter(|layer| layer.source.path().is_some()) {
+            let mut unknown = Vec::new();
+            validate::unknown_keys(&layer.value, &known, "", &mut unknown);
+            diagnostics.extend(unknown.into_iter().map(|d| layer.locate(d)));
+        }
+        let mut checks = config.resolve_secrets_with(&self.secrets);
+        checks.extend(config.validate());
+        diagnostics.extend(checks.into_iter().map(|d| self.attribute(d)));
+
+        config.layers = Some(Arc::new(self));
+        Ok((config, diagnostics))
+    }
+
+    /// Blame a type error on the file that introduced it
+    ///
+    /// Each file is checked on top of the defaults alone; parsing its own text
+    /// then gives the line and column
+    fn blame(&self, error: &serde_yaml::Error) -> Diagnostic {
+        for layer in self.layers.iter().rev().filter(|layer| layer.source.path().is_some()) {
+            let mut alone = self.layers[0].value.clone();
+            merge(&mut alone, &layer.value);
+            if serde_yaml::from_value::<Config>(alone).is_ok() {
+                continue;
+            }
+            let diagnostic = match serde_yaml::from_str::<Config>(&layer.content) {
+                // A partial file misses fields that other layers provide
+                Err(e) if !e.to_string().contains("missing field") => validate::yaml_error(&e),
+                _ => validate::yaml_error(error),
+            };
+            return diagnostic.with_file(layer.source.to_string());
+        }
+        validate::yaml_error(error)
+    }
+
+    /// Attribute a diagnostic to the layer that set its path, or the
+    /// nearest ancestor of it, and locate it in that layer's file
+    pub fn attribute(&self, diagnostic: Diagnostic) -> Diagnostic {
+        if diagnostic.file.is_some() || diagnostic.path.is_empty() {
+            return diagnostic;
+        }
+        let mut path = Some(diagnostic.path.as_str());
+        while let Some(current) = path {
+            if let Some(layer) = self.origin(current) {
+                return layer.locate(diagnostic);
+            }
+            path = parent(current);
+        }
+        diagnostic
+    }
+
+    /// The highest layer that sets `path`
+    pub fn origin(&self, path: &str) -> Option<&Layer> {
+        self.layers.iter().rev().find(|layer| lookup(&layer.value, path).is_some())
+    }
+
+    /// Every leaf of `config` with the layer it came from
+    ///
+    /// API keys filled in from `api_key_file`, `api_key_cmd` or the environment
+    /// are attributed to that source; their values are redacted
+    pub fn describe(&self, config: &Config) -> Vec<ResolvedValue> {
+        let merged = self.merged();
+        let mut leaves = Vec::new();
+        flatten(&serde_yaml::to_value(config).unwrap_or(Value::Null), String::new(), &mut leaves);
+        leaves
+            .into_iter()
+            .map(|(path, value)| {
+                let is_key = path.ends_with(".api_key");
+                let source = if is_key && lookup(&merged, &path) != Some(&value) {
+                    self.key_source(parent(&path).unwrap_or(""))
+                } else {
+                    self.origin(&path).map_or_else(|| Source::Default.describe(), |layer| layer.source.describe())
+                };
+                let value = if is_key && value.as_str().is_some_and(|key| !key.is_empty()) {
+                    Value::String(REDACTED.to_string())
+                } else {
+                    value
+                };
+                ResolvedValue { path, value: serde_json::to_string(&value).unwrap_or_default(), source }
+            })
+            .collect()
+    }
+
+    /// Where the API key of an `llm.<section>` was read from
+    fn key_source(&self, section: &str) -> String {
+        for key in ["api_key_file", "api_key_cmd"] {
+            if let Some(layer) = self.origin(&format!("{}.{}", section, key)) {
+                return format!("{} ({})", key, layer.source.describe());
+            }
+        }
+        "环境变量".to_string()
+    }
+}
+
+/// The configuration as YAML with API keys redacted, for printing
+pub fn redacted_value(config: &Config) -> Value {
+    let mut value = serde_yaml::to_value(config).unwrap_or(Value::Null);
+    redact_keys(&mut value);
+    value
+}
+
+fn redact_keys(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(secret) if key.as_str() == Some("api_key") && !secret.is_empty() => {
+                        *secret = REDACTED.to_string();
+                    }
+                    value => redact_keys(value),
+                }
+            }
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(redact_keys),
+        _ => {}
+    }
+}
+
+impl Config {
+    /// The configuration as seen from `dir`: with the project file found from
+    /// there instead of the one found from the current directory
+    ///
+    /// Returns a copy of `self` when there is nothing to change, or when the
+    /// project file is invalid, after reporting why
+    pub fn in_dir(&self, dir: &Path) -> Config {
+        let Some(stack) = &self.layers else { return self.clone() };
+        let errors = match stack.for_dir(dir).and_then(|stack| stack.map(LayerStack::resolve).transpose()) {
+            Ok(None) => return self.clone(),
+            Ok(Some((config, diagnostics))) if !validate::has_errors(&diagnostics) => return config,
+            Ok(Some((_, diagnostics))) => {
+                diagnostics.into_iter().filter(|d| d.severity == validate::Severity::Error).collect()
+            }
+            Err(e) => vec![e],
+        };
+        for error in errors {
+            eprintln!("❌ {}", error.render(&dir.display().to_string()));
+        }
+        eprintln!("⚠️ {} 的项目配置无效，使用全局配置", dir.display());
+        self.clone()
+    }
+
+    /// Effective configuration for a pane whose working directory is `dir`:
+    /// its project file, then its entry in `tmux.panes`
+    pub fn for_pane_in(&self, id: &str, dir: Option<&Path>) -> Config {
+        let config = match dir {
+            Some(dir) => self.in_dir(dir),
+            None => self.clone(),
+        };
+        config.for_pane(&config.tmux.pane_or_default(id))
+    }
+}
+
+/// Command line flags as a layer, mirroring [`Config::apply_args`]
+fn cli_layer(args: &Args) -> Value {
+    let mut layer = Value::Mapping(Mapping::new());
+    if let Some(pane) = &args.pane {
+        // A pane given on the command line replaces the configured list
+        set(&mut layer, "tmux.pane", Value::from(pane.as_str()));
+        set(&mut layer, "tmux.panes", Value::Sequence(Vec::new()));
+    }
+    if args.discover {
+        set(&mut layer, "tmux.discover", Value::Bool(true));
+    }
+    if let Some(backend) = &args.backend {
+        // A backend given on the command line replaces the whole chain
+        set(&mut layer, "llm.backend", Value::from(backend.as_str()));
+        set(&mut layer, "llm.backends", Value::Sequence(Vec::new()));
+    }
+    if let Some(interval) = args.interval {
+        set(&mut layer, "monitoring.interval", Value::from(interval));
+    }
+    if let Some(stuck_sec) = args.stuck_sec {
+        set(&mut layer, "monitoring.stuck_sec", Value::from(stuck_sec));
+    }
+    if let Some(max_retry) = args.max_retry {
+        set(&mut layer, "monitoring.max_retry", Value::from(max_retry as u64));
+    }
+    layer
+}
+
+/// Set a dotted path of plain keys, creating mappings on the way
+fn set(value: &mut Value, path: &str, leaf: Value) {
+    let mut current = value;
+    for key in path.split('.') {
+        if !current.is_mapping() {
+            *current = Value::Mapping(Mapping::new());
+        }
+        let Value::Mapping(map) = current else { unreachable!() };
+        current = map.entry(Value::from(key)).or_insert(Value::Null);
+    }
+    *current = leaf;
+}
+
+/// Merge `overlay` into `base`: mappings key by key, anything else replaced
+fn merge(base: &mut Value, overlay: &Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for key in overlay.keys().filter_map(Value::as_str) {
+                for (name, replaced) in REPLACES {
+                    if key == name {
+                        replaced.iter().for_each(|replaced| drop(base.remove(*replaced)));
+                    }
+                }
+            }
+            for (key, value) in overlay {
+                match base.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
+
+/// The parts of `value` that differ from `base`
+fn diff(value: &Value, base: &Value) -> Option<Value> {
+    match (value, base) {
+        (Value::Mapping(value), Value::Mapping(base)) => {
+            let changed: Mapping = value
+                .iter()
+                .filter_map(|(key, value)| {
+                    let changed = match base.get(key) {
+                        Some(base) => diff(value, base),
+                        None => Some(value.clone()),
+                    };
+                    changed.map(|value| (key.clone(), value))
+                })
+                .collect();
+            (!changed.is_empty()).then_some(Value::Mapping(changed))
+        }
+        _ => (value != base).then(|| value.clone()),
+    }
+}
+
+/// The value at a dotted path such as `tmux.panes[1].interval`
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut current = value;
+    for segment in path.split('.') {
+        let (key, indexes) = segment.split_once('[').unwrap_or((segment, ""));
+        current = current.as_mapping()?.get(key)?;
+        for index in indexes.trim_end_matches(']').split("][").filter(|index| !index.is_empty()) {
+            current = current.as_sequence()?.get(index.parse::<usize>().ok()?)?;
+        }
+    }
+    Some(current)
+}
+
+/// `tmux.panes[1]` for `tmux.panes[1].interval`, `tmux.panes` for `tmux.panes[1]`
+fn parent(path: &str) -> Option<&str> {
+    path.rfind(['.', '[']).map(|index| &path[..index])
+}
+
+/// Leaves of a mapping; lists and empty mappings count as leaves
+fn flatten(value: &Value, path: String, leaves: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Mapping(map) if !map.is_empty() => {
+            for (key, value) in map {
+                let Some(key) = key.as_str() else { continue };
+                let child = if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+                flatten(value, child, leaves);
+            }
+        }
+        value => leaves.push((path, value.clone())),
+    }
+}
@@ -0,0 +1,290 @@
+//! Configuration value resolution
+//!
+//! Every configuration goes through the same steps before use: `${VAR}` and
+//! `${VAR:-default}` are expanded in the YAML text, then API keys are read
+//! from `api_key_file` / `api_key_cmd` or, as a last resort, from the legacy
+//! environment variables (`OPENAI_API_KEY`, `OPENROUTER_KEY`, `ANTHROPIC_API_KEY`).
+
+use super::layers::LayerStack;
+use super::validate::Diagnostic;
+use super::Config;
+use crate::args::Args;
+use std::env;
+use std::fs;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Mutex;
+
+/// Expand `${VAR}` and `${VAR:-default}` using the process environment
+pub fn interpolate(content: &str) -> Result<String, Diagnostic> {
+    interpolate_with(content, |name| env::var(name).ok())
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` with the given lookup
+///
+/// `$${...}` produces a literal `${...}`. Comment lines are left alone.
+/// An unset variable without a default is an error, so a missing secret does
+/// not silently become an empty string; `${VAR:-}` opts into that explicitly
+pub fn interpolate_with(content: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, Diagnostic> {
+    let mut output = String::with_capacity(content.len());
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        if line.trim_start().starts_with('#') {
+            output.push_str(line);
+            continue;
+        }
+        let mut rest = line;
+        while let Some(start) = rest.find('$') {
+            output.push_str(&rest[..start]);
+            let tail = &rest[start..];
+            if let Some(escaped) = tail.strip_prefix("$${") {
+                output.push_str("${");
+                rest = escaped;
+                continue;
+            }
+            let Some(expression) = tail.strip_prefix("${") else {
+                output.push('$');
+                rest = &tail[1..];
+                continue;
+            };
+            let column = line.len() - tail.len() + 1;
+            let located = |message: String| Diagnostic {
+                location: Some((index + 1, column)),
+                ..Diagnostic::error("", message)
+            };
+            let end = expression.find('}').ok_or_else(|| located("`${` 没有闭合的 `}`".to_string()))?;
+            let (name, default) = match expression[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&expression[..end], None),
+            };
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(located(format!("无效的环境变量名 `{}`", name)));
+            }
+            // Like the shell, `:-` also replaces a variable that is set but empty
+            match (lookup(name), default) {
+                (Some(value), Some(default)) if value.is_empty() => output.push_str(default),
+                (Some(value), _) => output.push_str(&value),
+                (None, Some(default)) => output.push_str(default),
+                (None, None) => {
+                    return Err(located(format!("环境变量 {} 未设置，可以用 `${{{}:-默认值}}` 提供默认值", name, name)))
+                }
+            }
+            rest = &expression[end + 1..];
+        }
+        output.push_str(rest);
+    }
+    Ok(output)
+}
+
+/// API keys read through `api_key_file` / `api_key_cmd`, keyed by the file or command
+///
+/// A layer stack shares one cache with the per-directory stacks derived from
+/// it, so a command such as `pass show` runs once per load instead of once per
+/// pane. Failures are kept as well, so a broken command is not retried either
+#[derive(Debug, Default)]
+pub struct SecretCache {
+    keys: Mutex<HashMap<String, Result<String, String>>>,
+}
+
+impl SecretCache {
+    fn get_or_read(&self, key: String, read: impl FnOnce() -> Result<String, String>) -> Result<String, String> {
+        // Holding the lock while reading keeps two panes from running the same command at once
+        self.keys.lock().unwrap().entry(key).or_insert_with(read).clone()
+    }
+}
+
+/// Where an API key comes from
+struct KeySource<'a> {
+    /// Section name under `llm`
+    section: &'static str,
+    /// Legacy environment variable used when nothing else is set
+    env: &'static str,
+    api_key: &'a mut String,
+    file: &'a Option<String>,
+    cmd: &'a Option<String>,
+}
+
+impl Config {
+    /// Built-in defaults, with the legacy environment variables applied
+    ///
+    /// Used when there is no configuration file
+    pub fn from_env() -> Self {
+        let mut config = Config::default();
+        if let (Some(ollama), Ok(url)) = (config.llm.ollama.as_mut(), env::var("OLLAMA_URL")) {
+            ollama.url = url;
+        }
+        if let (Some(openai), Ok(api_base)) = (config.llm.openai.as_mut(), env::var("OPENAI_API_BASE")) {
+            openai.api_base = api_base;
+        }
+        if let (Some(openrouter), Ok(model)) = (config.llm.openrouter.as_mut(), env::var("OPENROUTER_MODEL")) {
+            openrouter.model = model;
+        }
+        if let Some(anthropic) = config.llm.anthropic.as_mut() {
+            if let Ok(base_url) = env::var("ANTHROPIC_BASE_URL") {
+                anthropic.base_url = base_url;
+            }
+            if let Ok(model) = env::var("ANTHROPIC_MODEL") {
+                anthropic.model = model;
+            }
+        }
+        config
+    }
+
+    /// Resolve the configuration for a run from all layers: defaults, the
+    /// system, user and project files, the file named by `--config` and
+    /// command line flags, with secrets resolved and the result validated
+    ///
+    /// Diagnostics are located in the layer that set the value where possible.
+    /// Only unreadable or unparsable files are returned as `Err`; the caller
+    /// decides what to do with error diagnostics
+    pub fn resolve(args: &Args) -> Result<(Self, Vec<Diagnostic>), Diagnostic> {
+        LayerStack::discover(args)?.resolve()
+    }
+
+    /// Command line overrides
+    pub fn apply_args(&mut self, args: &Args) {
+        if let Some(pane) = &args.pane {
+            // A pane given on the command line replaces the configured list
+            self.tmux.pane = pane.clone();
+            self.tmux.panes.clear();
+        }
+        if args.discover {
+            self.tmux.discover = true;
+        }
+        if let Some(backend) = &args.backend {
+            // A backend given on the command line replaces the whole chain
+            self.llm.backend = backend.clone();
+            self.llm.backends.clear();
+        }
+        if let Some(interval) = args.interval {
+            self.monitoring.interval = interval;
+        }
+        if let Some(stuck_sec) = args.stuck_sec {
+            self.monitoring.stuck_sec = stuck_sec;
+        }
+        if let Some(max_retry) = args.max_retry {
+            self.monitoring.max_retry = max_retry;
+        }
+    }
+
+    /// Fill empty API keys of the backends in use from `api_key_file`,
+    /// `api_key_cmd` or the legacy environment variable, in that order
+    pub fn resolve_secrets(&mut self) -> Vec<Diagnostic> {
+        self.resolve_secrets_with(&SecretCache::default())
+    }
+
+    /// Like [`Config::resolve_secrets`], reusing the keys already in `cache`
+    pub fn resolve_secrets_with(&mut self, cache: &SecretCache) -> Vec<Diagnostic> {
+        let in_use = self.backends_in_use();
+        let mut sources = Vec::new();
+        if let Some(openai) = self.llm.openai.as_mut() {
+            sources.push(KeySource {
+                section: "openai",
+                env: "OPENAI_API_KEY",
+                api_key: &mut openai.api_key,
+                file: &openai.api_key_file,
+                cmd: &openai.api_key_cmd,
+            });
+        }
+        if let Some(openrouter) = self.llm.openrouter.as_mut() {
+            sources.push(KeySource {
+                section: "openrouter",
+                env: "OPENROUTER_KEY",
+                api_key: &mut openrouter.api_key,
+                file: &openrouter.api_key_file,
+                cmd: &openrouter.api_key_cmd,
+            });
+        }
+        if let Some(anthropic) = self.llm.anthropic.as_mut() {
+            sources.push(KeySource {
+                section: "anthropic",
+                env: "ANTHROPIC_API_KEY",
+                api_key: &mut anthropic.api_key,
+                file: &anthropic.api_key_file,
+                cmd: &anthropic.api_key_cmd,
+            });
+        }
+        sources
+            .into_iter()
+            .filter(|source| in_use.iter().any(|name| name == source.section))
+            .filter_map(|source| resolve_key(source, cache))
+            .collect()
+    }
+
+    /// Backends reachable from the fallback chain or a pane override
+    ///
+    /// Keys of other sections are not resolved, so an unused `api_key_cmd`
+    /// never runs
+    pub fn backends_in_use(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.llm.chain().into_iter().map(|entry| entry.name).collect();
+        names.extend(self.tmux.panes.iter().filter_map(|pane| pane.backend.clone()));
+        names
+    }
+}
+
+fn resolve_key(source: KeySource<'_>, cache: &SecretCache) -> Option<Diagnostic> {
+    let configured = [!source.api_key.is_empty(), source.file.is_some(), source.cmd.is_some()];
+    if configured.iter().filter(|set| **set).count() > 1 {
+        return Some(Diagnostic::error(
+            &format!("llm.{}", source.section),
+            "api_key、api_key_file 和 api_key_cmd 只能设置一个",
+        ));
+    }
+    let result = if let Some(file) = source.file {
+        cache
+            .get_or_read(format!("file:{}", file), || read_key_file(file))
+            .map_err(|e| (format!("llm.{}.api_key_file", source.section), e))
+    } else if let Some(cmd) = source.cmd {
+        cache
+            .get_or_read(format!("cmd:{}", cmd), || run_key_command(cmd))
+            .map_err(|e| (format!("llm.{}.api_key_cmd", source.section), e))
+    } else if source.api_key.is_empty() {
+        Ok(env::var(source.env).unwrap_or_default())
+    } else {
+        return None;
+    };
+    match result {
+        Ok(key) => {
+            *source.api_key = key;
+            None
+        }
+        Err((path, e)) => Some(Diagnostic::error(&path, e)),
+    }
+}
+
+/// Read the first line of a key file; `~/` is expanded to the home directory
+fn read_key_file(file: &str) -> Result<String, String> {
+    let path = match (file.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(file),
+    };
+    let content = fs::read_to_string(&path).map_err(|e| format!("无法读取 API key 文件 {}: {}", path.display(), e))?;
+    let key = content.lines().next().unwrap_or("").trim().to_string();
+    if key.is_empty() {
+        return Err(format!("API key 文件 {} 为空", path.display()));
+    }
+    Ok(key)
+}
+
+/// Run `sh -c <cmd>` and use the first line of its output, like `pass show` prints it
+fn run_key_command(cmd: &str) -> Result<String, String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .output()
+        .map_err(|e| format!("无法执行 `{}`: {}", cmd, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "`{}` 执行失败 ({}): {}",
+            cmd,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let key = stdout.lines().next().unwrap_or("").trim().to_string();
+    if key.is_empty() {
+        return Err(format!("`{}` 没有输出 API key", cmd));
+    }
+    Ok(key)
+}
@@ -0,0 +1,395 @@
+//! Configuration validation
+//!
+//! serde only rejects what it cannot parse; unknown keys are silently ignored
+//! and a missing section quietly becomes a default. This module reports those
+//! mistakes, plus settings that parse fine but cannot work, with the line and
+//! column in the YAML file where possible.
+
+use super::{Config, LlmConfig};
+use crate::terminal::TerminalBackend;
+use serde_yaml::Value;
+use std::fmt;
+
+/// Backends `create_backend` knows about
+pub const BACKENDS: [&str; 5] = ["ollama", "openai", "openrouter", "anthropic", "none"];
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The setting is probably not what was meant, but claude-watch can run
+    Warning,
+    /// claude-watch refuses to start with this configuration
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "警告"),
+            Severity::Error => write!(f, "错误"),
+        }
+    }
+}
+
+/// A single problem found in the configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Dotted path of the offending key, e.g. `tmux.panes[1].interval`; empty for syntax errors
+    pub path: String,
+    pub message: String,
+    /// 1-based line and column in the YAML file
+    pub location: Option<(usize, usize)>,
+    /// Where the offending value came from, when it is not the file being checked:
+    /// another configuration layer or the command line
+    pub file: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(path: &str, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            path: path.to_string(),
+            message: message.into(),
+            location: None,
+            file: None,
+        }
+    }
+
+    pub fn warning(path: &str, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(path, message)
+        }
+    }
+
+    /// Name the file or layer the diagnostic belongs to, unless it already has one
+    pub fn with_file(mut self, file: impl Into<String>) -> Self {
+        self.file.get_or_insert_with(|| file.into());
+        self
+    }
+
+    /// Fill in the location of `path` in `content` if it is not known yet
+    pub fn locate_in(mut self, content: &str) -> Self {
+        if self.location.is_none() && !self.path.is_empty() {
+            self.location = locate(content, &self.path);
+        }
+        self
+    }
+
+    /// Format as `file:line:column: severity: path: message`
+    ///
+    /// `file` is used unless the diagnostic names its own
+    pub fn render(&self, file: &str) -> String {
+        let file = self.file.as_deref().unwrap_or(file);
+        let location = match self.location {
+            Some((line, column)) => format!("{}:{}:{}", file, line, column),
+            None => file.to_string(),
+        };
+        format!("{}: {}", location, self)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}: {}", self.severity, self.message)
+        } else {
+            write!(f, "{}: {}: {}", self.severity, self.path, self.message)
+        }
+    }
+}
+
+/// Whether any of the diagnostics prevents claude-watch from running
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+/// Parse a YAML configuration, reporting unknown keys alongside the result
+///
+/// Syntax and type errors are returned as `Err` with serde_yaml's line and column
+pub fn parse(content: &str) -> Result<(Config, Vec<Diagnostic>), Diagnostic> {
+    let config: Config = serde_yaml::from_str(content).map_err(|e| yaml_error(&e))?;
+
+    let mut diagnostics = Vec::new();
+    let original: Value = serde_yaml::from_str(content).unwrap_or(Value::Null);
+    let known = serde_yaml::to_value(&config).unwrap_or(Value::Null);
+    unknown_keys(&original, &known, "", &mut diagnostics);
+    Ok((config, diagnostics.into_iter().map(|d| d.locate_in(content)).collect()))
+}
+
+/// A serde_yaml error, with its position moved from the message into `location`
+pub(crate) fn yaml_error(e: &serde_yaml::Error) -> Diagnostic {
+    let message = e.to_string();
+    let message = message.split_once(" at line ").map_or(message.as_str(), |(message, _)| message);
+    Diagnostic {
+        location: e.location().map(|location| (location.line(), location.column())),
+        ..Diagnostic::error("", message)
+    }
+}
+
+/// Keys present in the file but dropped by serde
+///
+/// Whatever the parsed config serializes back to is known; a key missing from
+/// the round trip was ignored, unless it only held an empty value that is
+/// skipped when serializing
+pub(crate) fn unknown_keys(original: &Value, known: &Value, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+    match (original, known) {
+        (Value::Mapping(original), Value::Mapping(known)) => {
+            for (key, value) in original {
+                let Some(name) = key.as_str() else { continue };
+                let child = if path.is_empty() { name.to_string() } else { format!("{}.{}", path, name) };
+                match known.get(key) {
+                    Some(known_value) => unknown_keys(value, known_value, &child, diagnostics),
+                    None if is_empty(value) => {}
+                    None => {
+                        let suggestion = known
+                            .keys()
+                            .filter_map(Value::as_str)
+                            .map(|candidate| (strsim::jaro_winkler(name, candidate), candidate))
+                            .filter(|(score, _)| *score > 0.8)
+                            .max_by(|a, b| a.0.total_cmp(&b.0))
+                            .map(|(_, candidate)| format!("，是否是 `{}`？", candidate))
+                            .unwrap_or_default();
+                        diagnostics.push(Diagnostic::error(&child, format!("未知的配置项{}", suggestion)));
+                    }
+                }
+            }
+        }
+        (Value::Sequence(original), Value::Sequence(known)) => {
+            for (index, (value, known_value)) in original.iter().zip(known).enumerate() {
+                unknown_keys(value, known_value, &format!("{}[{}]", path, index), diagnostics);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Sequence(items) => items.is_empty(),
+        Value::Mapping(map) => map.is_empty(),
+        _ => false,
+    }
+}
+
+/// Line and column (1-based) of a dotted path such as `tmux.panes[1].interval`,
+/// or of its deepest ancestor found in the file
+///
+/// A plain text search following block indentation; flow style (`{a: 1}`) is not supported
+pub fn locate(content: &str, path: &str) -> Option<(usize, usize)> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut start = 0;
+    let mut parent: isize = -1;
+    let mut on_item = false;
+    let mut found = None;
+    for segment in path.split('.') {
+        let (key, indexes) = match segment.split_once('[') {
+            Some((key, rest)) => (key, rest.trim_end_matches(']').split("][").filter_map(|i| i.parse().ok()).collect()),
+            None => (segment, Vec::new()),
+        };
+        // Stop at the deepest key that was found
+        let Some((line, column)) = find_key(&lines, start, parent, on_item, key) else { break };
+        found = Some((line + 1, column + 1));
+        start = line + 1;
+        parent = column as isize;
+        on_item = false;
+        for index in indexes {
+            let Some((line, column)) = find_item(&lines, start, parent, index) else { return found };
+            found = Some((line + 1, column + 1));
+            start = line;
+            parent = column as isize;
+            on_item = true;
+        }
+    }
+    found
+}
+
+/// Indentation and the text after it, with a leading `- ` counted as indentation
+fn split_line(line: &str) -> (usize, usize, &str) {
+    let text = line.trim_start();
+    let indent = line.len() - text.len();
+    let content = text.strip_prefix("- ").map(str::trim_start).unwrap_or(text);
+    (indent, line.len() - content.len(), content)
+}
+
+fn is_blank(line: &str) -> bool {
+    let text = line.trim();
+    text.is_empty() || text.starts_with('#')
+}
+
+/// Find `key:` nested under the block whose key sits at column `parent`
+///
+/// When `on_item` is set, `start` is the `- ` line of a sequence item, whose
+/// own first key shares the dash's line
+fn find_key(lines: &[&str], start: usize, parent: isize, on_item: bool, key: &str) -> Option<(usize, usize)> {
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        if is_blank(line) {
+            continue;
+        }
+        let (indent, column, content) = split_line(line);
+        if (indent as isize) <= parent && !(on_item && i == start) {
+            return None;
+        }
+        let name = content.split(':').next().unwrap_or("").trim().trim_matches(|c| c == '"' || c == '\'');
+        let is_key = content.contains(':') && name == key;
+        if is_key && column as isize > parent {
+            return Some((i, column));
+        }
+    }
+    None
+}
+
+/// Find the `index`-th `- ` item of the sequence under the key at column `parent`
+fn find_item(lines: &[&str], start: usize, parent: isize, index: usize) -> Option<(usize, usize)> {
+    let mut dash_indent = None;
+    let mut seen = 0;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        if is_blank(line) {
+            continue;
+        }
+        let (indent, _, _) = split_line(line);
+        let text = line.trim_start();
+        let is_item = text == "-" || text.starts_with("- ");
+        // Sequences may sit at the same indentation as their key
+        if (indent as isize) < parent || ((indent as isize) == parent && !is_item) {
+            return None;
+        }
+        if is_item && *dash_indent.get_or_insert(indent) == indent {
+            if seen == index {
+                return Some((i, indent));
+            }
+            seen += 1;
+        }
+    }
+    None
+}
+
+impl Config {
+    /// Settings that parse fine but cannot work as written
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        if self.llm.backends.is_empty() {
+            check_backend(&self.llm, &self.llm.backend, "llm.backend", &mut diagnostics);
+        } else {
+            for (index, entry) in self.llm.backends.iter().enumerate() {
+                check_backend(&self.llm, &entry.name, &format!("llm.backends[{}]", index), &mut diagnostics);
+            }
+        }
+        check_timing(
+            self.monitoring.interval,
+            self.monitoring.stuck_sec,
+            "monitoring.interval",
+            &mut diagnostics,
+        );
+        if self.tmux.discover && self.tmux.discover_interval == 0 {
+            diagnostics.push(Diagnostic::error("tmux.discover_interval", "发现间隔必须大于 0"));
+        }
+        for (index, pane) in self.tmux.panes.iter().enumerate() {
+            let path = format!("tmux.panes[{}]", index);
+            if pane.id.trim().is_empty() {
+                diagnostics.push(Diagnostic::error(&format!("{}.id", path), "窗格 ID 为空"));
+            }
+            if pane.interval.is_some() || pane.stuck_sec.is_some() {
+                let effective = self.for_pane(pane);
+                check_timing(
+                    effective.monitoring.interval,
+                    effective.monitoring.stuck_sec,
+                    &format!("{}.{}", path, if pane.interval.is_some() { "interval" } else { "stuck_sec" }),
+                    &mut diagnostics,
+                );
+            }
+            if let Some(backend) = &pane.backend {
+                check_backend(&self.llm, backend, &format!("{}.backend", path), &mut diagnostics);
+            }
+        }
+
+        for (index, step) in self.recovery.steps.iter().enumerate() {
+            if step.attempts == 0 {
+                diagnostics.push(Diagnostic::error(
+                    &format!("recovery.steps[{}].attempts", index),
+                    "尝试次数至少为 1",
+                ));
+            }
+        }
+        for (name, file) in [("judge", &self.prompts.judge), ("activation", &self.prompts.activation)] {
+            if let Some(file) = file {
+                if !std::path::Path::new(file).is_file() {
+                    diagnostics.push(Diagnostic::warning(
+                        &format!("prompts.{}", name),
+                        format!("模板文件 {} 不存在，将使用内置模板", file),
+                    ));
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+/// A backend named in the chain must exist and have a usable section
+fn check_backend(llm: &LlmConfig, name: &str, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let api_key = match name {
+        "ollama" | "none" => return,
+        "openai" => llm.openai.as_ref().map(|openai| openai.api_key.as_str()),
+        "openrouter" => llm.openrouter.as_ref().map(|openrouter| openrouter.api_key.as_str()),
+        "anthropic" => llm.anthropic.as_ref().map(|anthropic| anthropic.api_key.as_str()),
+        _ => {
+            diagnostics.push(Diagnostic::error(
+                path,
+                format!("不支持的 LLM 后端 `{}`，可选: {}", name, BACKENDS.join(", ")),
+            ));
+            return;
+        }
+    };
+    match api_key {
+        None => diagnostics.push(Diagnostic::error(
+            path,
+            format!("使用了后端 `{}`，但没有 `llm.{}` 配置段", name, name),
+        )),
+        Some(key) if key.trim().is_empty() => diagnostics.push(Diagnostic::error(
+            &format!("llm.{}.api_key", name),
+            format!("后端 `{}` 的 API key 为空", name),
+        )),
+        Some(_) => {}
+    }
+}
+
+fn check_timing(interval: u64, stuck_sec: u64, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+    if interval == 0 {
+        diagnostics.push(Diagnostic::error(path, "检查间隔必须大于 0"));
+    } else if interval > stuck_sec {
+        diagnostics.push(Diagnostic::error(
+            path,
+            format!("检查间隔 {} 秒大于卡住判定时间 stuck_sec {} 秒", interval, stuck_sec),
+        ));
+    }
+}
+
+/// Panes in the configuration that tmux does not know about
+///
+/// Skipped in discovery mode, where the pane list only provides overrides
+pub async fn check_panes(config: &Config, terminal: &dyn TerminalBackend) -> Vec<Diagnostic> {
+    if config.tmux.discover {
+        return Vec::new();
+    }
+    let mut diagnostics = Vec::new();
+    let panes: Vec<(String, String)> = if config.tmux.panes.is_empty() {
+        vec![("tmux.pane".to_string(), config.tmux.pane.clone())]
+    } else {
+        config
+            .tmux
+            .panes
+            .iter()
+            .enumerate()
+            .map(|(index, pane)| (format!("tmux.panes[{}]", index), pane.id.clone()))
+            .collect()
+    };
+    for (path, id) in panes {
+        if !terminal.pane_exists(&id).await {
+            diagnostics.push(Diagnostic::error(&path, format!("tmux 中找不到窗格 {}", id)));
+        }
+    }
+    diagnostics
+}
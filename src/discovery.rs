@@ -0,0 +1,159 @@
+//! Claude Code 窗格自动发现
+//!
+//! 通过 `TerminalBackend::list_panes` 枚举所有会话中的窗格，
+//! 结合前台进程名称和屏幕内容识别 Claude Code，并为每个识别出的窗格启动监控任务
+
+use crate::activity::is_claude_active;
+use crate::monitor::{pane_config, run_monitoring_loop, PaneState};
+use crate::reload::ConfigUpdates;
+use crate::shutdown::Shutdown;
+use crate::terminal::{PaneInfo, TerminalBackend};
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Claude Code 自身的进程名称
+const CLAUDE_COMMANDS: [&str; 2] = ["claude", "claude-code"];
+
+/// Claude Code 界面特有的文本，只要出现一个即认为是 Claude Code 屏幕
+const CLAUDE_SCREEN_MARKERS: [&str; 6] = [
+    "Welcome to Claude Code",
+    "? for shortcuts",
+    "esc to interrupt",
+    "Bypassing Permissions",
+    "bypass permissions on",
+    "auto-accept edits on",
+];
+
+/// 检查进程名称是否是 Claude Code
+pub fn is_claude_command(command: &str) -> bool {
+    let command = command.trim().to_lowercase();
+    CLAUDE_COMMANDS.iter().any(|name| command == *name)
+}
+
+/// 根据屏幕内容判断是否是 Claude Code 界面
+///
+/// 除了界面标记外，复用 `is_claude_active` 的执行条检测
+pub fn looks_like_claude_screen(text: &str) -> bool {
+    CLAUDE_SCREEN_MARKERS.iter().any(|marker| text.contains(marker)) || is_claude_active(text)
+}
+
+/// 判断窗格是否运行着 Claude Code
+///
+/// 进程名称直接匹配时无需查看屏幕；否则（例如通过 node 启动）根据屏幕指纹判断
+pub fn is_claude_pane(info: &PaneInfo, screen: &str) -> bool {
+    is_claude_command(&info.current_command) || looks_like_claude_screen(screen)
+}
+
+/// 枚举所有窗格并返回识别为 Claude Code 的窗格
+pub async fn discover_claude_panes(terminal: &dyn TerminalBackend) -> Result<Vec<PaneInfo>, String> {
+    let panes = terminal.list_panes().await?;
+    Ok(select_claude_panes(terminal, panes).await)
+}
+
+/// 从窗格列表中筛选出 Claude Code 窗格，只在进程名称无法确定时才捕获屏幕
+async fn select_claude_panes(terminal: &dyn TerminalBackend, panes: Vec<PaneInfo>) -> Vec<PaneInfo> {
+    let mut selected = Vec::new();
+    for info in panes {
+        let is_claude = is_claude_command(&info.current_command)
+            || terminal
+                .capture(&info.id)
+                .await
+                .map(|screen| looks_like_claude_screen(&screen))
+                .unwrap_or(false);
+        if is_claude {
+            selected.push(info);
+        }
+    }
+    selected
+}
+
+/// 运行自动发现循环
+///
+/// 每隔 `tmux.discover_interval` 秒扫描一次：为新出现的 Claude Code 窗格启动监控任务，
+/// 回收已经结束（窗格关闭）的任务，并中止已从 tmux 中消失的窗格的任务。
+/// 已经在监控的窗格即使屏幕暂时不像 Claude Code 也会继续监控，直到窗格关闭。
+/// 收到新配置时从下一次扫描开始使用，已经在监控的窗格由各自的任务应用新配置。
+/// 收到退出通知后停止扫描，并等待所有监控任务结束
+pub async fn run_discovery_loop(
+    mut updates: ConfigUpdates,
+    terminal: Arc<dyn TerminalBackend>,
+    shutdown: Shutdown,
+) -> io::Result<()> {
+    let mut watchers: HashMap<String, JoinHandle<io::Result<()>>> = HashMap::new();
+    let mut config = updates.current();
+    let mut interval = Duration::from_secs(config.tmux.discover_interval.max(1));
+
+    println!("🔍 自动发现模式：每 {} 秒扫描一次 tmux 窗格", interval.as_secs());
+
+    loop {
+        if let Some(updated) = updates.take() {
+            config = updated;
+            interval = Duration::from_secs(config.tmux.discover_interval.max(1));
+        }
+
+        // 回收自行结束的监控任务
+        watchers.retain(|pane, handle| {
+            if handle.is_finished() {
+                println!("👋 [{}] 监控任务已结束", pane);
+                false
+            } else {
+                true
+            }
+        });
+
+        let all_panes = match terminal.list_panes().await {
+            Ok(panes) => panes,
+            Err(e) => {
+                eprintln!("⚠️ 无法列出 tmux 窗格: {}", e);
+                if !shutdown.sleep(interval).await {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        // 中止已经不存在的窗格的监控任务
+        watchers.retain(|pane, handle| {
+            if all_panes.iter().any(|info| &info.id == pane) {
+                true
+            } else {
+                println!("👋 [{}] tmux pane 已消失，停止监控", pane);
+                handle.abort();
+                false
+            }
+        });
+
+        // 只检查尚未监控的窗格
+        let candidates = all_panes
+            .into_iter()
+            .filter(|info| !watchers.contains_key(&info.id))
+            .collect();
+        for info in select_claude_panes(terminal.as_ref(), candidates).await {
+            let pane_config = pane_config(&config, terminal.as_ref(), &info.id).await;
+            println!(
+                "✨ 发现 Claude Code 窗格 {} (会话: {}, 进程: {})，开始监控",
+                info.id, info.session, info.current_command
+            );
+            let terminal = Arc::clone(&terminal);
+            let shutdown = shutdown.clone();
+            let updates = updates.clone();
+            let handle = tokio::spawn(async move {
+                let mut state = PaneState::with_updates(shutdown, updates);
+                run_monitoring_loop(&pane_config, terminal.as_ref(), &mut state).await
+            });
+            watchers.insert(info.id, handle);
+        }
+
+        if !shutdown.sleep(interval).await {
+            break;
+        }
+    }
+
+    for (_, handle) in watchers {
+        let _ = handle.await;
+    }
+    Ok(())
+}
@@ -1,14 +1,27 @@
 pub mod activity;
+pub mod commands;
 pub mod config;
+pub mod discovery;
 pub mod monitor;
+pub mod permission;
+pub mod prompt;
+pub mod recovery;
+pub mod redact;
+pub mod reload;
+pub mod shutdown;
+pub mod state;
 pub mod llm;
+pub mod terminal;
 pub mod tmux;
 pub mod args;
 pub mod testing;
 
 // 重新导出主要的公共接口
 pub use activity::is_claude_active;
-pub use monitor::{has_substantial_progress, is_just_time_counter, check_if_should_skip_llm_call, extract_execution_time, is_time_increasing, PaneStatus};
+pub use monitor::{has_substantial_progress, is_just_time_counter, check_if_should_skip_llm_call, extract_execution_time, is_time_increasing, PaneState};
 
-pub use llm::{ask_llm_for_activation, ask_llm_final_status, TaskStatus};
-pub use config::{Config, LlmConfig, OpenAiConfig, OpenRouterConfig, OllamaConfig};
\ No newline at end of file
+pub use llm::{ask_llm_for_activation, ask_llm_final_status, create_backend, LlmBackend};
+pub use state::{ClaudeState, Verdict};
+pub use config::{Config, LlmConfig, BackendConfig, OpenAiConfig, OpenRouterConfig, OllamaConfig, AnthropicConfig};
+pub use terminal::{Key, TerminalBackend};
+pub use tmux::TmuxBackend;
\ No newline at end of file
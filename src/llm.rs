@@ -1,148 +1,262 @@
-use crate::config::{Config, OpenAiConfig, OpenRouterConfig};
-use serde_json::{json, Value};
-// tokio imported implicitly through async functions
-
-/// 任务状态枚举
-#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
-pub enum TaskStatus {
-    Done,
-    Stuck,
+//! LLM 后端
+//!
+//! 每个服务商实现一次 `LlmBackend::complete`，状态判断和激活消息生成都建立在它之上，
+//! 监控逻辑只通过 `create_backend` 拿到的 trait 对象调用 LLM
+
+pub mod anthropic;
+pub mod cache;
+pub mod chain;
+pub mod ollama;
+pub mod openai;
+pub mod openrouter;
+pub mod usage;
+pub mod verdict;
+
+use crate::activity::detect_screen_state;
+use crate::config::{Config, LlmConfig};
+use crate::prompt::{self, PromptContext};
+use crate::redact::Redactor;
+use crate::state::{ClaudeState, Verdict};
+use async_trait::async_trait;
+
+pub use anthropic::AnthropicBackend;
+pub use cache::{CacheStats, VerdictCache};
+pub use chain::LlmChain;
+pub use ollama::{parse_ollama_url, OllamaBackend};
+pub use openai::OpenAiBackend;
+pub use openrouter::OpenRouterBackend;
+pub use usage::{Usage, UsageLedger, UsageRecord};
+pub use verdict::parse_verdict;
+
+/// 状态判断时允许 LLM 输出的最大 token 数，需要容纳包含理由的 JSON
+const VERDICT_MAX_TOKENS: u32 = 256;
+
+/// 激活消息允许的最大 token 数，需要容纳点明具体问题的一两句话
+const ACTIVATION_MAX_TOKENS: u32 = 100;
+
+/// 一次补全请求
+#[derive(Debug, Clone, Copy)]
+pub struct CompletionRequest<'a> {
+    /// 系统指令
+    pub system: &'a str,
+    /// 用户内容
+    pub prompt: &'a str,
+    /// 最大输出 token 数
+    pub max_tokens: u32,
+    /// 采样温度
+    pub temperature: f32,
+    /// 要求输出 JSON 对象（JSON 模式或工具调用，取决于服务商支持）
+    pub json: bool,
 }
 
-/// 解析 Ollama URL 为主机和端口
-fn parse_ollama_url(url: &str) -> (String, u16) {
-    // 移除协议前缀
-    let url = url.trim_start_matches("http://").trim_start_matches("https://");
-    
-    // 分割主机和端口
-    let parts: Vec<&str> = url.split(':').collect();
-    match parts.as_slice() {
-        [host, port_str] => {
-            let port = port_str.parse::<u16>().unwrap_or(11434);
-            (host.to_string(), port)
-        }
-        [host] => {
-            // 默认端口 11434
-            (host.to_string(), 11434)
-        }
-        _ => {
-            // 默认值
-            ("localhost".to_string(), 11434)
-        }
+/// 一次补全的结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Completion {
+    /// 模型的原始回答
+    pub text: String,
+    /// 服务商返回的 token 用量，没有返回时为 None
+    pub usage: Option<Usage>,
+}
+
+impl Completion {
+    pub fn new(text: impl Into<String>, usage: Option<Usage>) -> Self {
+        Completion { text: text.into(), usage }
     }
 }
 
-/// 使用 ollama-rs 库调用 Ollama API
-async fn ask_ollama_with_ollama_rs(prompt_text: &str, model: &str, url: &str) -> Result<String, String> {
-    // 解析 URL 获取主机和端口
-    let (host, port) = parse_ollama_url(url);
-    
-    // 初始化 Ollama 客户端（支持自定义服务器地址和端口）
-    let ollama = ollama_rs::Ollama::new(&host, port);
-    
-    // 设置模型选项以提高稳定性和一致性
-    let options = ollama_rs::models::ModelOptions::default()
-        .temperature(0.0)  // 确保确定性输出
-        .num_predict(4);   // 限制输出长度
-    
-    // 构建生成请求
-    let request = ollama_rs::generation::completion::request::GenerationRequest::new(
-        model.to_string(),
-        prompt_text.to_string(),
-    ).options(options);
-    
-    // 发送请求并处理响应
-    match ollama.generate(request).await {
-        Ok(response) => {
-            Ok(response.response)
+/// LLM 服务商
+///
+/// 新的服务商只需要实现 `complete`，`classify` 和 `generate` 自动可用
+#[async_trait]
+pub trait LlmBackend: Send + Sync {
+    /// 后端名称，与配置中的 `llm.backend` 对应
+    fn name(&self) -> &str;
+
+    /// 使用的模型，用于用量统计和价格表
+    fn model(&self) -> &str {
+        ""
+    }
+
+    /// 是否需要访问网络服务；远程后端的调用受每日预算限制，发送前按配置脱敏
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    /// 是否是本地的启发式规则：不会临时失败，降级链不重试也不熔断，判断时使用完整画面
+    fn is_heuristic(&self) -> bool {
+        false
+    }
+
+    /// 发送一次补全请求，返回模型的原始回答和用量
+    async fn complete(&self, request: CompletionRequest<'_>) -> Result<Completion, String>;
+
+    /// 按判断提示词 `instructions` 判断画面对应的 Claude Code 状态
+    async fn classify(&self, instructions: &str, screen: &str) -> Result<Verdict, String> {
+        let response = self
+            .complete(CompletionRequest {
+                system: instructions,
+                prompt: screen,
+                max_tokens: VERDICT_MAX_TOKENS,
+                temperature: 0.0,
+                json: true,
+            })
+            .await?
+            .text;
+        if response.trim().is_empty() {
+            // 推理模型可能只输出推理过程，content 为空
+            // 因为画面已经停止变化，默认认为卡住
+            return Ok(Verdict::new(ClaudeState::Stuck, "LLM 没有返回内容"));
         }
-        Err(e) => {
-            Err(format!("Ollama 调用失败: {}", e))
+        parse_verdict(&response)
+    }
+
+    /// 根据提示生成一句激活消息
+    async fn generate(&self, system: &str, prompt: &str) -> Result<String, String> {
+        let response = self
+            .complete(CompletionRequest {
+                system,
+                prompt,
+                max_tokens: ACTIVATION_MAX_TOKENS,
+                temperature: 0.1,
+                json: false,
+            })
+            .await?
+            .text;
+        let message = response.trim();
+        if message.is_empty() {
+            return Err(format!("{} 返回了空的激活消息", self.name()));
         }
+        Ok(message.to_string())
     }
 }
 
-/// 手搓 HTTP 请求调用 OpenAI 兼容的 API
-fn ask_openai(system_prompt: &str, user_content: &str, config: &crate::config::OpenAiConfig) -> Result<String, String> {
-    use serde_json::{json, Value};
-    
-    // 检查 API key 是否为空
-    if config.api_key.is_empty() {
-        return Err("OpenAI API key 未设置".to_string());
+/// 不使用 LLM：状态判断使用启发式规则，无法生成激活消息
+#[derive(Debug, Default, Clone)]
+pub struct HeuristicBackend;
+
+#[async_trait]
+impl LlmBackend for HeuristicBackend {
+    fn name(&self) -> &str {
+        "none"
     }
-    
-    // 创建请求体
-    let request_body = json!({
-        "model": config.model,
-        "messages": [
-            {
-                "role": "system",
-                "content": system_prompt
-            },
-            {
-                "role": "user",
-                "content": user_content
-            }
-        ],
-        "max_tokens": 4,
-        "temperature": 0.0
-    });
-    
-    // 构建完整的 URL
-    let url = if config.api_base.ends_with('/') {
-        format!("{}chat/completions", config.api_base)
-    } else {
-        format!("{}/chat/completions", config.api_base)
-    };
-    
-    // 发送 HTTP 请求
-    let response = match ureq::post(&url)
-        .set("Authorization", &format!("Bearer {}", config.api_key))
-        .set("Content-Type", "application/json")
-        .send_json(request_body) 
-    {
-        Ok(resp) => resp,
-        Err(e) => return Err(format!("HTTP 请求失败: {}", e)),
-    };
-    
-    // 检查响应状态
-    let status = response.status();
-    if status != 200 {
-        let error_text = response.into_string().unwrap_or_else(|_| "无法获取错误信息".to_string());
-        return Err(format!("API 请求失败，状态码: {}, 错误: {}", status, error_text));
+
+    fn is_remote(&self) -> bool {
+        false
     }
-    
-    // 解析响应 JSON
-    let response_text = response
-        .into_string()
-        .map_err(|e| format!("读取响应失败: {}", e))?;
-    
-    let json_response: Value = serde_json::from_str(&response_text)
-        .map_err(|e| format!("解析 JSON 失败: {}, 响应: {}", e, response_text))?;
-    
-    // 提取结果
-    if let Some(choices) = json_response.get("choices").and_then(|v| v.as_array()) {
-        if let Some(first_choice) = choices.first() {
-            if let Some(message) = first_choice.get("message").and_then(|v| v.as_object()) {
-                // 只从 content 字段判断，忽略推理过程
-                if let Some(content) = message.get("content").and_then(|v| v.as_str()) {
-                    if !content.is_empty() {
-                        return Ok(content.to_string());
-                    }
-                }
-                
-                // 如果 content 为空，忽略推理过程，直接返回 STUCK
-                // 因为画面已经停止变化，默认认为卡住
-                return Ok("STUCK".to_string());
-            }
+
+    fn is_heuristic(&self) -> bool {
+        true
+    }
+
+    async fn complete(&self, _request: CompletionRequest<'_>) -> Result<Completion, String> {
+        Err("未配置 LLM 后端".to_string())
+    }
+
+    async fn classify(&self, _instructions: &str, screen: &str) -> Result<Verdict, String> {
+        Ok(Verdict::new(simple_heuristic_check(screen), "启发式规则判断"))
+    }
+}
+
+/// 发送前先脱敏的后端
+///
+/// 包装远程后端，系统提示词和用户内容都经过 `Redactor` 处理后再发出
+pub struct RedactingBackend {
+    inner: Box<dyn LlmBackend>,
+    redactor: Redactor,
+}
+
+impl RedactingBackend {
+    pub fn new(inner: Box<dyn LlmBackend>, redactor: Redactor) -> Self {
+        RedactingBackend { inner, redactor }
+    }
+}
+
+#[async_trait]
+impl LlmBackend for RedactingBackend {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn is_remote(&self) -> bool {
+        self.inner.is_remote()
+    }
+
+    fn is_heuristic(&self) -> bool {
+        self.inner.is_heuristic()
+    }
+
+    async fn complete(&self, request: CompletionRequest<'_>) -> Result<Completion, String> {
+        let (system, system_count) = self.redactor.redact(request.system);
+        let (prompt, prompt_count) = self.redactor.redact(request.prompt);
+        if system_count + prompt_count > 0 {
+            println!("🔒 发送给 {} 前脱敏了 {} 处敏感信息", self.name(), system_count + prompt_count);
         }
+        self.inner
+            .complete(CompletionRequest {
+                system: &system,
+                prompt: &prompt,
+                ..request
+            })
+            .await
+    }
+}
+
+/// 根据后端名称创建 LLM 后端
+///
+/// - "ollama": 使用 Ollama 服务，未配置时连接本地默认地址
+/// - "openai": 使用 OpenAI 或兼容服务
+/// - "openrouter": 使用 OpenRouter 服务
+/// - "anthropic": 使用 Anthropic Messages API
+/// - "none": 使用简单的启发式判断
+///
+/// HTTP 请求的超时时间取自 `LlmConfig::timeout_for`；
+/// 远程后端按 `LlmConfig::redact_for` 包装为 `RedactingBackend`
+pub fn create_backend(name: &str, config: &LlmConfig) -> Result<Box<dyn LlmBackend>, String> {
+    let backend = create_raw_backend(name, config)?;
+    if backend.is_remote() && config.redact_for(name) {
+        Ok(Box::new(RedactingBackend::new(backend, Redactor::new(&config.redaction))))
+    } else {
+        Ok(backend)
+    }
+}
+
+fn create_raw_backend(name: &str, config: &LlmConfig) -> Result<Box<dyn LlmBackend>, String> {
+    let timeout = config.timeout_for(name);
+    match name {
+        "ollama" => Ok(Box::new(OllamaBackend::from_config(config.ollama.as_ref())?)),
+        "openai" => config
+            .openai
+            .clone()
+            .map(|openai| Box::new(OpenAiBackend::new(openai, timeout)) as Box<dyn LlmBackend>)
+            .ok_or_else(|| "OpenAI 配置未找到".to_string()),
+        "openrouter" => config
+            .openrouter
+            .clone()
+            .map(|openrouter| Box::new(OpenRouterBackend::new(openrouter, timeout)) as Box<dyn LlmBackend>)
+            .ok_or_else(|| "OpenRouter 配置未找到".to_string()),
+        "anthropic" => config
+            .anthropic
+            .clone()
+            .map(|anthropic| Box::new(AnthropicBackend::new(anthropic, timeout)) as Box<dyn LlmBackend>)
+            .ok_or_else(|| "Anthropic 配置未找到".to_string()),
+        "none" => Ok(Box::new(HeuristicBackend)),
+        _ => Err(format!("不支持的LLM后端: {}", name)),
     }
-    
-    Err("无法解析 API 响应".to_string())
 }
 
 /// 简化的启发式检查（仅在 LLM 不可用时使用）
-pub fn simple_heuristic_check(text: &str) -> TaskStatus {
+///
+/// 先识别有明确界面特征的状态（见 `activity::detect_screen_state`），
+/// 再依次检查完成、错误和处理中的文本特征，都不匹配时认为卡住
+pub fn simple_heuristic_check(text: &str) -> ClaudeState {
+    if let Some(state) = detect_screen_state(text) {
+        return state;
+    }
+    
     // 检查明显的完成标志
     // 注意：移除了"Done"，因为它只是agent执行完成，不表示整体任务完成
     let done_patterns = [
@@ -174,7 +288,7 @@ pub fn simple_heuristic_check(text: &str) -> TaskStatus {
     ];
     
     if done_patterns.iter().any(|&pattern| text.contains(pattern)) {
-        return TaskStatus::Done;
+        return ClaudeState::Done;
     }
     
     // 检查明显的错误标志
@@ -198,7 +312,7 @@ pub fn simple_heuristic_check(text: &str) -> TaskStatus {
     ];
     
     if error_patterns.iter().any(|&pattern| text.contains(pattern)) {
-        return TaskStatus::Stuck;
+        return ClaudeState::Errored;
     }
     
     // 检查可能仍在处理中的状态（避免误判为卡住）
@@ -224,7 +338,7 @@ pub fn simple_heuristic_check(text: &str) -> TaskStatus {
         "Uploading",
         "Checking",
         "Testing",
-        "Retry",
+        "Retrying",
         "Escaping",
         "Interrupting",
         "...",
@@ -232,254 +346,54 @@ pub fn simple_heuristic_check(text: &str) -> TaskStatus {
         "◦◦◦",
     ];
     
-    // 如果检测到处理中状态，不轻易判断为卡住
-    // 这里返回一个特殊状态，让监控逻辑继续等待
-    if processing_patterns.iter().any(|&pattern| text.contains(pattern)) {
-        // 简化起见，我们仍然返回 Stuck，但在监控逻辑中需要处理这种情况
-        // 更好的做法是增加一个 Processing 状态，但现在先这样处理
-        return TaskStatus::Stuck;
-    }
-    
-    // 检查是否有未完成的命令或程序输出
+    // 只在最后几行中查找处理中状态，避免滚动历史中的旧输出造成误判
     let lines: Vec<&str> = text.lines().collect();
     let last_few_lines: Vec<&str> = lines.iter().rev().take(5).cloned().collect();
     let last_content = last_few_lines.join("\n");
     
-    // 这个逻辑移到了monitor.rs中的check_if_should_skip_llm_call函数中
-    // 这里只处理启发式检查，不跳过LLM调用判断
-    
-    // 如果最后几行看起来像是程序输出的一部分，可能是正常的处理状态
-    if last_content.contains('$') || last_content.contains('>') || last_content.contains('#') {
-        // 如果有命令提示符，可能是在等待输入，不算卡住
-        return TaskStatus::Stuck;
-    }
-    
-    // 如果文本看起来不完整或者有未闭合的结构，可能是执行中
-    if text.ends_with("...") || text.ends_with("•") || text.ends_with("▪") {
-        // 仍在处理中，不立即判断为卡住
-        return TaskStatus::Stuck;
+    // 如果检测到处理中状态，不轻易判断为卡住
+    if processing_patterns.iter().any(|&pattern| last_content.contains(pattern)) {
+        return ClaudeState::Working;
     }
     
     // 默认认为卡住（因为画面已经停止变化了）
-    // 但这个逻辑现在更加谨慎，只有在确认没有其他状态时才判断为卡住
-    TaskStatus::Stuck
+    ClaudeState::Stuck
 }
 
 /// 使用 LLM 生成激活消息
 /// 
-/// 这是智能激活功能，让LLM生成一句话来激活卡住的Claude Code
-pub fn ask_llm_for_activation(prompt: &str, backend: &str, config: &Config) -> Result<String, String> {
-    match backend {
-        "openai" => {
-            if let Some(openai_config) = &config.llm.openai {
-                ask_openai_for_activation(prompt, openai_config)
-            } else {
-                Err("OpenAI配置未找到".to_string())
-            }
-        },
-        "openrouter" => {
-            if let Some(openrouter_config) = &config.llm.openrouter {
-                ask_openrouter_for_activation(prompt, openrouter_config)
-            } else {
-                Err("OpenRouter配置未找到".to_string())
-            }
-        },
-        "ollama" => {
-            if let Some(_ollama_config) = &config.llm.ollama {
-                // 这里需要保持async，因为ollama-rs是异步的
-                // 为了简化，我们暂时返回错误，实际使用中可能需要tokio::block_on
-                Err("Ollama激活功能暂时不可用".to_string())
-            } else {
-                Err("Ollama配置未找到".to_string())
-            }
-        },
-        _ => {
-            Err(format!("不支持的LLM后端: {}", backend))
-        }
-    }
-}
-
-/// 使用OpenAI生成激活消息
-fn ask_openai_for_activation(prompt: &str, config: &OpenAiConfig) -> Result<String, String> {
-    // 构建正确的URL - 添加 chat/completions 路径
-    let url = if config.api_base.ends_with('/') {
-        format!("{}chat/completions", config.api_base)
-    } else {
-        format!("{}/chat/completions", config.api_base)
-    };
-    
-    let request_body = serde_json::json!({
-        "model": &config.model,
-        "messages": [
-            {
-                "role": "system",
-                "content": "你是一个Claude Code激活助手。当Claude Code卡住时，你需要生成一句简短、有效的话来激活它。"
-            },
-            {
-                "role": "user", 
-                "content": prompt
-            }
-        ],
-        "max_tokens": 50,
-        "temperature": 0.1
-    });
-    
-    match ureq::post(&url)
-        .set("Authorization", &format!("Bearer {}", &config.api_key))
-        .set("Content-Type", "application/json")
-        .send_json(request_body) 
-    {
-        Ok(resp) => {
-            if let Ok(text) = resp.into_string() {
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) {
-                    if let Some(content) = json["choices"][0]["message"]["content"].as_str() {
-                        return Ok(content.trim().to_string());
-                    } else {
-                        return Err("OpenAI响应中缺少content字段".to_string());
-                    }
-                } else {
-                    return Err("OpenAI响应解析失败".to_string());
-                }
-            } else {
-                return Err("OpenAI响应读取失败".to_string());
-            }
-        },
-        Err(e) => {
-            Err(format!("OpenAI请求失败: {}", e))
-        }
-    }
-}
-
-/// 使用OpenRouter生成激活消息
-fn ask_openrouter_for_activation(prompt: &str, config: &OpenRouterConfig) -> Result<String, String> {
-    let request_body = serde_json::json!({
-        "model": &config.model,
-        "messages": [
-            {
-                "role": "system",
-                "content": "你是一个Claude Code激活助手。当Claude Code卡住时，你需要生成一句简短、有效的话来激活它。"
-            },
-            {
-                "role": "user", 
-                "content": prompt
-            }
-        ],
-        "max_tokens": 50,
-        "temperature": 0.1
-    });
-    
-    match ureq::post("https://openrouter.ai/api/v1/chat/completions")
-        .set("Authorization", &format!("Bearer {}", &config.api_key))
-        .set("Content-Type", "application/json")
-        .send_json(request_body) 
-    {
-        Ok(resp) => {
-            if let Ok(text) = resp.into_string() {
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) {
-                    if let Some(content) = json["choices"][0]["message"]["content"].as_str() {
-                        return Ok(content.trim().to_string());
-                    }
-                }
-            }
-            Err("OpenRouter响应解析失败".to_string())
-        },
-        Err(e) => {
-            Err(format!("OpenRouter请求失败: {}", e))
-        }
-    }
+/// 这是智能激活功能，让LLM生成一句话来激活卡住的Claude Code。
+/// 提示词来自 `prompts.activation` 模板，按降级链依次尝试，所有后端都失败时返回错误
+pub async fn ask_llm_for_activation(context: &PromptContext, config: &Config) -> Result<String, String> {
+    let system = prompt::activation_system_prompt(config.prompts.language);
+    let user_prompt = prompt::activation_prompt(&config.prompts, context);
+    LlmChain::global().generate(&context.pane, system, &user_prompt, &config.llm).await
 }
 
 /// 使用 LLM 判断 Claude Code 最终状态
 /// 
-/// 这是最关键的状态判断函数，仅在画面长时间无变化时调用
-/// 根据配置的 LLM 后端类型进行判断：
-/// - "ollama": 使用 Ollama 服务
-/// - "openai": 使用 OpenAI 或兼容服务
-/// - "openrouter": 使用 OpenRouter 服务
-/// - "none": 使用简单的启发式判断
-pub async fn ask_llm_final_status(text: &str, backend: &str, config: &Config) -> Result<TaskStatus, String> {
-    if backend == "none" {
-        // 如果禁用 LLM，使用简单的启发式判断
-        return Ok(simple_heuristic_check(text));
+/// 这是最关键的状态判断函数，仅在画面长时间无变化时调用。
+/// 判断提示词来自 `prompts.judge` 模板，屏幕内容作为用户消息发送
+/// LLM 返回包含状态、置信度、理由和建议操作的 JSON，见 `verdict::parse_verdict`。
+/// 按降级链依次尝试，所有后端都失败时使用 `simple_heuristic_check`。
+/// 相同画面的判断结果在 `llm.cache.ttl` 秒内直接复用，启发式规则的结果不缓存
+pub async fn ask_llm_final_status(context: &PromptContext, config: &Config) -> Verdict {
+    let cache = VerdictCache::global();
+    if let Some(verdict) = cache.get(&context.screen, &config.llm.cache) {
+        println!("💾 画面与之前判断过的相同，复用缓存结果 ({})", cache.stats());
+        return verdict;
     }
-    
-    // 只读取一次 system prompt
-    let system_prompt = include_str!("../prompt_final.md");
-
-    match backend.as_ref() {
-        "ollama" => {
-            let model = config.llm.ollama.as_ref().map(|o| o.model.clone()).unwrap_or("qwen2.5:3b".to_string());
-            let url = config.llm.ollama.as_ref().map(|o| o.url.clone()).unwrap_or("http://localhost:11434".to_string());
-            
-            // 对于 Ollama，我们需要将 system 和 user 内容以合适的格式传递
-            let ollama_prompt = format!("### 系统指令\n{}\n\n### 用户内容\n{}", system_prompt, text);
-            
-            match ask_ollama_with_ollama_rs(&ollama_prompt, &model, &url).await {
-                Ok(response) => {
-                    let response = response.trim();
-                    match response {
-                        "DONE" => Ok(TaskStatus::Done),
-                        "STUCK" => Ok(TaskStatus::Stuck),
-                        _ => Err(format!("LLM 返回未知状态: {}", response)),
-                    }
-                }
-                Err(e) => Err(e),
-            }
-        }
-        "openai" => {
-            if let Some(openai_config) = &config.llm.openai {
-                match ask_openai(&system_prompt, &text, &openai_config) {
-                    Ok(response) => {
-                        let response = response.trim();
-                        match response {
-                            "DONE" => Ok(TaskStatus::Done),
-                            "STUCK" => Ok(TaskStatus::Stuck),
-                            _ => Err(format!("LLM 返回未知状态: {}", response)),
-                        }
-                    }
-                    Err(e) => Err(e),
-                }
-            } else {
-                Err("OpenAI 配置未找到".to_string())
-            }
-        }
-        "openrouter" => {
-            let url = "https://openrouter.ai/api/v1/chat/completions";
-            let model = config.llm.openrouter.as_ref().map(|o| o.model.clone()).unwrap_or("qwen/qwen-2.5-7b-instruct".to_string());
-            
-            if let Some(openrouter_config) = &config.llm.openrouter {
-                let body = json!({
-                    "model": model,
-                    "messages": [
-                        {"role": "system", "content": system_prompt},
-                        {"role": "user", "content": text}
-                    ],
-                    "max_tokens": 4,
-                    "temperature": 0.0
-                });
-                
-                match ureq::post(&url)
-                    .set("Authorization", &format!("Bearer {}", openrouter_config.api_key))
-                    .send_json(body) 
-                {
-                    Ok(resp) => {
-                        let json: Value = resp.into_json().map_err(|e| e.to_string())?;
-                        let response = json["choices"][0]["message"]["content"]
-                            .as_str()
-                            .unwrap_or("[空响应]")
-                            .trim();
-                        match response {
-                            "DONE" => Ok(TaskStatus::Done),
-                            "STUCK" => Ok(TaskStatus::Stuck),
-                            _ => Err(format!("LLM 返回未知状态: {}", response)),
-                        }
-                    }
-                    Err(e) => Err(format!("OpenRouter 调用失败: {}", e)),
-                }
-            } else {
-                Err("OpenRouter 配置未找到".to_string())
+
+    let instructions = prompt::judge_prompt(&config.prompts, context);
+    match LlmChain::global().try_classify(&context.pane, &instructions, &context.screen, &config.llm).await {
+        Ok((backend, verdict)) => {
+            // 降级链中的 none 给出的是启发式结果，LLM 恢复后应该重新判断
+            if config.llm.cache.ttl > 0 && backend != HeuristicBackend.name() {
+                cache.insert(&context.screen, &verdict, &config.llm.cache);
+                println!("💾 判断结果已缓存 ({})", cache.stats());
             }
+            verdict
         }
-        _ => Err("未知的 LLM_BACKEND".to_string()),
+        Err(e) => chain::heuristic_verdict(&context.screen, &e),
     }
-}
\ No newline at end of file
+}
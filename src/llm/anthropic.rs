@@ -0,0 +1,121 @@
+//! Anthropic 后端（Messages API）
+
+use super::verdict::verdict_schema;
+use super::{Completion, CompletionRequest, LlmBackend, Usage};
+use crate::config::AnthropicConfig;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// 状态判断时强制调用的工具名
+const VERDICT_TOOL: &str = "report_verdict";
+
+/// Anthropic Claude 模型
+pub struct AnthropicBackend {
+    config: AnthropicConfig,
+    client: Client,
+    timeout: Duration,
+}
+
+impl AnthropicBackend {
+    pub fn new(config: AnthropicConfig, timeout: Duration) -> Self {
+        AnthropicBackend { config, client: Client::new(), timeout }
+    }
+}
+
+#[async_trait]
+impl LlmBackend for AnthropicBackend {
+    fn name(&self) -> &str {
+        "anthropic"
+    }
+
+    fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    async fn complete(&self, request: CompletionRequest<'_>) -> Result<Completion, String> {
+        if self.config.api_key.is_empty() {
+            return Err("Anthropic API key 未设置".to_string());
+        }
+        let url = format!("{}/v1/messages", self.config.base_url.trim_end_matches('/'));
+        let mut body = json!({
+            "model": self.config.model,
+            "system": request.system,
+            "messages": [
+                {"role": "user", "content": request.prompt}
+            ],
+            "max_tokens": request.max_tokens,
+            "temperature": request.temperature
+        });
+        if request.json {
+            // Messages API 没有 JSON 模式，强制调用一个参数就是判断结果的工具
+            body["tools"] = json!([{
+                "name": VERDICT_TOOL,
+                "description": "Report the detected Claude Code state",
+                "input_schema": verdict_schema()
+            }]);
+            body["tool_choice"] = json!({"type": "tool", "name": VERDICT_TOOL});
+        }
+        send_message(&self.client, &url, &self.config, &body, self.timeout).await
+    }
+}
+
+/// 发送 Messages 请求并取出回答
+///
+/// 有工具调用时返回工具参数的 JSON，否则拼接所有文本块。
+/// 用量取自 `usage.input_tokens` 和 `usage.output_tokens`
+async fn send_message(
+    client: &Client,
+    url: &str,
+    config: &AnthropicConfig,
+    body: &Value,
+    timeout: Duration,
+) -> Result<Completion, String> {
+    let response = client
+        .post(url)
+        .timeout(timeout)
+        .header("x-api-key", &config.api_key)
+        .header("anthropic-version", &config.version)
+        .json(body)
+        .send()
+        .await
+        .map_err(|e| format!("HTTP 请求失败: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        // 错误响应形如 {"type": "error", "error": {"type": "...", "message": "..."}}
+        let error_text = response.text().await.unwrap_or_else(|_| "无法获取错误信息".to_string());
+        let message = serde_json::from_str::<Value>(&error_text)
+            .ok()
+            .and_then(|json| json["error"]["message"].as_str().map(str::to_string))
+            .unwrap_or(error_text);
+        return Err(format!("Anthropic API 请求失败，状态码: {}, 错误: {}", status.as_u16(), message));
+    }
+
+    let response_text = response
+        .text()
+        .await
+        .map_err(|e| format!("读取响应失败: {}", e))?;
+    let json_response: Value = serde_json::from_str(&response_text)
+        .map_err(|e| format!("解析 JSON 失败: {}, 响应: {}", e, response_text))?;
+
+    let blocks = json_response["content"]
+        .as_array()
+        .ok_or_else(|| "无法解析 Anthropic 响应".to_string())?;
+    let usage = json_response["usage"].as_object().map(|usage| {
+        Usage::new(
+            usage.get("input_tokens").and_then(Value::as_u64).unwrap_or(0),
+            usage.get("output_tokens").and_then(Value::as_u64).unwrap_or(0),
+        )
+    });
+    if let Some(block) = blocks.iter().find(|block| block["type"] == "tool_use") {
+        return Ok(Completion::new(block["input"].to_string(), usage));
+    }
+    let text: String = blocks
+        .iter()
+        .filter(|block| block["type"] == "text")
+        .filter_map(|block| block["text"].as_str())
+        .collect();
+    Ok(Completion::new(text, usage))
+}
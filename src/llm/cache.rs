@@ -0,0 +1,131 @@
+//! 状态判断缓存
+//!
+//! 窗格停在同一个卡住画面时，每轮检查都会把相同的内容发给 LLM。
+//! 缓存以 `extract_core_content` 归一化后的屏幕内容的哈希为键，
+//! 在 `llm.cache.ttl` 秒内直接复用上一次的判断结果
+
+use crate::config::CacheConfig;
+use crate::monitor::extract_core_content;
+use crate::state::Verdict;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// 缓存命中情况
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// 命中次数
+    pub hits: u64,
+    /// 未命中次数（包括过期）
+    pub misses: u64,
+    /// 当前缓存的条目数
+    pub entries: usize,
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "命中 {} 次，未命中 {} 次，缓存 {} 条", self.hits, self.misses, self.entries)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    verdict: Verdict,
+    stored_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    entries: HashMap<u64, Entry>,
+    hits: u64,
+    misses: u64,
+}
+
+/// 状态判断缓存，所有窗格共用
+#[derive(Debug, Default)]
+pub struct VerdictCache {
+    inner: Mutex<Inner>,
+}
+
+impl VerdictCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 进程内共享的缓存
+    pub fn global() -> &'static VerdictCache {
+        static CACHE: OnceLock<VerdictCache> = OnceLock::new();
+        CACHE.get_or_init(VerdictCache::new)
+    }
+
+    /// 屏幕内容的缓存键，忽略计时、token 计数等不代表实质变化的内容
+    pub fn key(screen: &str) -> u64 {
+        seahash::hash(extract_core_content(screen).as_bytes())
+    }
+
+    /// 取出未过期的判断结果，`ttl` 为 0 时不使用缓存
+    pub fn get(&self, screen: &str, config: &CacheConfig) -> Option<Verdict> {
+        if config.ttl == 0 {
+            return None;
+        }
+        let key = Self::key(screen);
+        let ttl = Duration::from_secs(config.ttl);
+        let mut inner = self.inner.lock().unwrap();
+        match inner.entries.get(&key) {
+            Some(entry) if entry.stored_at.elapsed() < ttl => {
+                let verdict = entry.verdict.clone();
+                inner.hits += 1;
+                Some(verdict)
+            }
+            Some(_) => {
+                inner.entries.remove(&key);
+                inner.misses += 1;
+                None
+            }
+            None => {
+                inner.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// 保存判断结果，超过 `max_entries` 时先清理过期条目，再淘汰最早保存的
+    pub fn insert(&self, screen: &str, verdict: &Verdict, config: &CacheConfig) {
+        if config.ttl == 0 || config.max_entries == 0 {
+            return;
+        }
+        let ttl = Duration::from_secs(config.ttl);
+        let mut inner = self.inner.lock().unwrap();
+        if inner.entries.len() >= config.max_entries {
+            inner.entries.retain(|_, entry| entry.stored_at.elapsed() < ttl);
+        }
+        while inner.entries.len() >= config.max_entries {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(key, _)| *key);
+            match oldest {
+                Some(key) => inner.entries.remove(&key),
+                None => break,
+            };
+        }
+        inner.entries.insert(
+            Self::key(screen),
+            Entry {
+                verdict: verdict.clone(),
+                stored_at: Instant::now(),
+            },
+        );
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let inner = self.inner.lock().unwrap();
+        CacheStats {
+            hits: inner.hits,
+            misses: inner.misses,
+            entries: inner.entries.len(),
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! LLM 降级链
+//!
+//! 按 `llm.backends` 的顺序尝试各个后端：每个后端有独立的超时和重试次数，
+//! 重试间隔按指数增长并带随机抖动；连续失败的后端会被熔断一段时间直接跳过。
+//! 所有后端都失败时，状态判断回落到启发式规则。
+//! 远程后端的调用记入用量账本，当天费用达到预算后跳过远程后端
+
+use super::usage::{MeteredBackend, UsageLedger};
+use super::{create_backend, simple_heuristic_check, LlmBackend};
+use crate::config::{CircuitBreakerConfig, LlmConfig};
+use crate::prompt::prepare_screen;
+use crate::state::Verdict;
+use async_trait::async_trait;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// 单个后端的熔断状态
+#[derive(Debug, Default, Clone)]
+struct Breaker {
+    /// 连续失败的调用次数
+    failures: u32,
+    /// 熔断到期时间，到期前跳过这个后端
+    open_until: Option<Instant>,
+}
+
+/// 降级链上执行的一次调用
+#[async_trait]
+trait Call: Sync {
+    type Output: Send;
+
+    async fn call(&self, backend: &dyn LlmBackend, config: &LlmConfig) -> Result<Self::Output, String>;
+}
+
+/// 状态判断：判断提示词和屏幕内容
+///
+/// 发给 LLM 的屏幕按后端的字符预算整理，启发式规则使用完整画面
+struct Classify<'a>(&'a str, &'a str);
+
+#[async_trait]
+impl Call for Classify<'_> {
+    type Output = Verdict;
+
+    async fn call(&self, backend: &dyn LlmBackend, config: &LlmConfig) -> Result<Verdict, String> {
+        if backend.is_heuristic() {
+            return backend.classify(self.0, self.1).await;
+        }
+        let screen = prepare_screen(self.1, config.screen.max_lines, config.max_chars_for(backend.name()));
+        backend.classify(self.0, &screen).await
+    }
+}
+
+/// 生成激活消息：系统提示词和用户提示词
+struct Generate<'a>(&'a str, &'a str);
+
+#[async_trait]
+impl Call for Generate<'_> {
+    type Output = String;
+
+    async fn call(&self, backend: &dyn LlmBackend, _config: &LlmConfig) -> Result<String, String> {
+        backend.generate(self.0, self.1).await
+    }
+}
+
+/// LLM 降级链，记录每个后端的熔断状态和当天的用量
+#[derive(Debug, Default)]
+pub struct LlmChain {
+    breakers: Mutex<HashMap<String, Breaker>>,
+    ledger: Arc<UsageLedger>,
+}
+
+impl LlmChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 进程内共享的降级链，所有窗格共用熔断状态
+    pub fn global() -> &'static LlmChain {
+        static CHAIN: OnceLock<LlmChain> = OnceLock::new();
+        CHAIN.get_or_init(LlmChain::new)
+    }
+
+    /// 用量账本
+    pub fn ledger(&self) -> &UsageLedger {
+        &self.ledger
+    }
+
+    /// 判断 `pane` 的画面状态，所有后端都失败时使用启发式规则
+    pub async fn classify(&self, pane: &str, instructions: &str, screen: &str, config: &LlmConfig) -> Verdict {
+        match self.try_classify(pane, instructions, screen, config).await {
+            Ok((_, verdict)) => verdict,
+            Err(e) => heuristic_verdict(screen, &e),
+        }
+    }
+
+    /// 判断 `pane` 的画面状态，返回给出判断的后端名称和判断结果，所有后端都失败时返回错误
+    pub async fn try_classify(
+        &self,
+        pane: &str,
+        instructions: &str,
+        screen: &str,
+        config: &LlmConfig,
+    ) -> Result<(String, Verdict), String> {
+        self.run(pane, &Classify(instructions, screen), config).await
+    }
+
+    /// 为 `pane` 生成激活消息，返回第一个成功的后端的结果
+    pub async fn generate(&self, pane: &str, system: &str, prompt: &str, config: &LlmConfig) -> Result<String, String> {
+        self.run(pane, &Generate(system, prompt), config).await.map(|(_, message)| message)
+    }
+
+    /// 后端当前是否处于熔断状态
+    pub fn is_tripped(&self, name: &str) -> bool {
+        let breakers = self.breakers.lock().unwrap();
+        breakers
+            .get(name)
+            .and_then(|breaker| breaker.open_until)
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    /// 依次尝试降级链上的后端，返回第一个成功的后端名称和结果
+    async fn run<C: Call>(&self, pane: &str, call: &C, config: &LlmConfig) -> Result<(String, C::Output), String> {
+        let mut errors = Vec::new();
+        let over_budget = self.ledger.budget_exceeded(&config.usage).await;
+        for entry in config.chain() {
+            let name = entry.name.as_str();
+            if self.is_tripped(name) {
+                errors.push(format!("{}: 熔断中", name));
+                continue;
+            }
+            // 配置错误不是临时故障，不重试也不计入熔断
+            let backend = match create_backend(name, config) {
+                Ok(backend) => backend,
+                Err(e) => {
+                    errors.push(format!("{}: {}", name, e));
+                    continue;
+                }
+            };
+            if backend.is_remote() && over_budget {
+                errors.push(format!("{}: 超出每日预算", name));
+                continue;
+            }
+            // 启发式规则不产生用量，直接调用它自己的 classify
+            if backend.is_heuristic() {
+                match call.call(backend.as_ref(), config).await {
+                    Ok(output) => return Ok((entry.name, output)),
+                    Err(e) => {
+                        errors.push(format!("{}: {}", name, e));
+                        continue;
+                    }
+                }
+            }
+            let backend = MeteredBackend::new(backend, pane, Arc::clone(&self.ledger), &config.usage);
+
+            match self.call_with_retry(&backend, call, config).await {
+                Ok(output) => {
+                    self.record_success(name);
+                    return Ok((entry.name, output));
+                }
+                Err(e) => {
+                    println!("⚠️ LLM 后端 {} 调用失败: {}", name, e);
+                    self.record_failure(name, &config.circuit_breaker);
+                    errors.push(format!("{}: {}", name, e));
+                }
+            }
+        }
+        Err(errors.join("; "))
+    }
+
+    /// 带超时地调用一个后端，失败后按退避间隔重试
+    async fn call_with_retry<C: Call>(
+        &self,
+        backend: &dyn LlmBackend,
+        call: &C,
+        config: &LlmConfig,
+    ) -> Result<C::Output, String> {
+        let timeout = config.timeout_for(backend.name());
+        let retries = config.retries_for(backend.name());
+        let mut attempt = 0;
+        loop {
+            let result = match tokio::time::timeout(timeout, call.call(backend, config)).await {
+                Ok(result) => result,
+                Err(_) => Err(format!("{} 秒内没有响应", timeout.as_secs())),
+            };
+            match result {
+                Ok(output) => return Ok(output),
+                Err(e) if attempt < retries => {
+                    let delay = retry_delay(config.retry_backoff_ms, attempt);
+                    attempt += 1;
+                    println!(
+                        "🔁 LLM 后端 {} 失败: {}，{} 毫秒后重试 ({}/{})",
+                        backend.name(),
+                        e,
+                        delay.as_millis(),
+                        attempt,
+                        retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn record_success(&self, name: &str) {
+        self.breakers.lock().unwrap().remove(name);
+    }
+
+    /// 记录一次失败的调用，连续失败达到阈值时熔断
+    ///
+    /// 熔断到期后的第一次调用如果仍然失败，会立即再次熔断
+    fn record_failure(&self, name: &str, config: &CircuitBreakerConfig) {
+        if config.failures == 0 {
+            return;
+        }
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(name.to_string()).or_default();
+        breaker.failures += 1;
+        if breaker.failures >= config.failures {
+            println!(
+                "🔌 LLM 后端 {} 连续失败 {} 次，{} 秒内跳过",
+                name, breaker.failures, config.cooldown
+            );
+            breaker.open_until = Some(Instant::now() + Duration::from_secs(config.cooldown));
+        }
+    }
+}
+
+/// 所有后端都失败时使用启发式规则判断
+pub(crate) fn heuristic_verdict(screen: &str, error: &str) -> Verdict {
+    println!("⚠️ 所有 LLM 后端都失败了 ({})，使用启发式规则判断", error);
+    Verdict::new(simple_heuristic_check(screen), "LLM 不可用，启发式规则判断")
+}
+
+/// 第 `attempt` 次重试（从 0 开始）前的等待时间
+///
+/// 基础间隔每次翻倍，再乘以 0.5 ~ 1.5 之间的随机系数，避免多个窗格同时重试
+pub fn retry_delay(base_ms: u64, attempt: usize) -> Duration {
+    let backoff = base_ms.saturating_mul(1 << attempt.min(16));
+    let jitter = RandomState::new().build_hasher().finish() % 1000;
+    Duration::from_millis(backoff / 2 + backoff.saturating_mul(jitter) / 1000)
+}
@@ -0,0 +1,113 @@
+//! Ollama 后端（通过 ollama-rs）
+
+use super::{Completion, CompletionRequest, LlmBackend, Usage};
+use crate::config::OllamaConfig;
+use async_trait::async_trait;
+use ollama_rs::generation::completion::request::GenerationRequest;
+use ollama_rs::generation::parameters::FormatType;
+use ollama_rs::models::ModelOptions;
+use ollama_rs::Ollama;
+use std::net::IpAddr;
+
+/// 未配置时使用的服务器地址
+const DEFAULT_URL: &str = "http://localhost:11434";
+
+/// 未配置时使用的模型
+const DEFAULT_MODEL: &str = "qwen2.5:3b";
+
+/// 解析 Ollama URL 为主机和端口
+pub fn parse_ollama_url(url: &str) -> (String, u16) {
+    // 移除协议前缀
+    let url = url.trim_start_matches("http://").trim_start_matches("https://");
+    
+    // 分割主机和端口
+    let parts: Vec<&str> = url.split(':').collect();
+    match parts.as_slice() {
+        [host, port_str] => {
+            let port = port_str.parse::<u16>().unwrap_or(11434);
+            (host.to_string(), port)
+        }
+        [host] => {
+            // 默认端口 11434
+            (host.to_string(), 11434)
+        }
+        _ => {
+            // 默认值
+            ("localhost".to_string(), 11434)
+        }
+    }
+}
+
+/// 主机是否是本机；本机的 Ollama 不受每日预算限制，画面也不需要脱敏
+fn is_loopback(host: &str) -> bool {
+    host.eq_ignore_ascii_case("localhost") || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// 本地或远程 Ollama 服务
+pub struct OllamaBackend {
+    client: Ollama,
+    model: String,
+    remote: bool,
+}
+
+impl OllamaBackend {
+    pub fn new(url: &str, model: &str) -> Result<Self, String> {
+        // 解析 URL 获取主机和端口，没有写端口时使用默认端口
+        let (host, port) = parse_ollama_url(url);
+        let scheme = if url.starts_with("https://") { "https" } else { "http" };
+        let client = Ollama::try_new(format!("{}://{}:{}", scheme, host, port))
+            .map_err(|e| format!("无效的 Ollama 地址 {}: {}", url, e))?;
+        Ok(OllamaBackend {
+            client,
+            model: model.to_string(),
+            remote: !is_loopback(&host),
+        })
+    }
+
+    /// 按配置创建，未配置时连接本地默认地址
+    pub fn from_config(config: Option<&OllamaConfig>) -> Result<Self, String> {
+        match config {
+            Some(config) => Self::new(&config.url, &config.model),
+            None => Self::new(DEFAULT_URL, DEFAULT_MODEL),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OllamaBackend {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn is_remote(&self) -> bool {
+        self.remote
+    }
+
+    async fn complete(&self, request: CompletionRequest<'_>) -> Result<Completion, String> {
+        let options = ModelOptions::default()
+            .temperature(request.temperature)
+            .num_predict(request.max_tokens as i32);
+        let mut generation = GenerationRequest::new(self.model.clone(), request.prompt)
+            .system(request.system)
+            .options(options);
+        if request.json {
+            generation = generation.format(FormatType::Json);
+        }
+
+        self.client
+            .generate(generation)
+            .await
+            .map(|response| {
+                let usage = match (response.prompt_eval_count, response.eval_count) {
+                    (None, None) => None,
+                    (prompt, completion) => Some(Usage::new(prompt.unwrap_or(0), completion.unwrap_or(0))),
+                };
+                Completion::new(response.response, usage)
+            })
+            .map_err(|e| format!("Ollama 调用失败: {}", e))
+    }
+}
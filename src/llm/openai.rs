@@ -0,0 +1,119 @@
+//! OpenAI 及兼容服务（Chat Completions API）
+
+use super::{Completion, CompletionRequest, LlmBackend, Usage};
+use crate::config::OpenAiConfig;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// OpenAI 或任何兼容 Chat Completions 的服务
+pub struct OpenAiBackend {
+    config: OpenAiConfig,
+    client: Client,
+    timeout: Duration,
+}
+
+impl OpenAiBackend {
+    pub fn new(config: OpenAiConfig, timeout: Duration) -> Self {
+        OpenAiBackend { config, client: Client::new(), timeout }
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OpenAiBackend {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    async fn complete(&self, request: CompletionRequest<'_>) -> Result<Completion, String> {
+        // 检查 API key 是否为空
+        if self.config.api_key.is_empty() {
+            return Err("OpenAI API key 未设置".to_string());
+        }
+        let url = chat_completions_url(&self.config.api_base);
+        let body = request_body(&self.config.model, &request);
+        chat_completion(&self.client, &url, &self.config.api_key, &body, self.timeout).await
+    }
+}
+
+/// 在 API 地址后拼接 chat/completions 路径
+pub(crate) fn chat_completions_url(api_base: &str) -> String {
+    if api_base.ends_with('/') {
+        format!("{}chat/completions", api_base)
+    } else {
+        format!("{}/chat/completions", api_base)
+    }
+}
+
+/// 构建 Chat Completions 请求体
+///
+/// 需要 JSON 时开启 JSON 模式（`response_format: json_object`）
+pub(crate) fn request_body(model: &str, request: &CompletionRequest<'_>) -> Value {
+    let mut body = json!({
+        "model": model,
+        "messages": [
+            {"role": "system", "content": request.system},
+            {"role": "user", "content": request.prompt}
+        ],
+        "max_tokens": request.max_tokens,
+        "temperature": request.temperature
+    });
+    if request.json {
+        body["response_format"] = json!({"type": "json_object"});
+    }
+    body
+}
+
+/// 发送 Chat Completions 请求并取出回答内容
+///
+/// 只读取 content 字段，忽略推理过程；content 为空时返回空字符串。
+/// 用量取自 `usage.prompt_tokens` 和 `usage.completion_tokens`
+pub(crate) async fn chat_completion(
+    client: &Client,
+    url: &str,
+    api_key: &str,
+    body: &Value,
+    timeout: Duration,
+) -> Result<Completion, String> {
+    let response = client
+        .post(url)
+        .timeout(timeout)
+        .bearer_auth(api_key)
+        .json(body)
+        .send()
+        .await
+        .map_err(|e| format!("HTTP 请求失败: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_else(|_| "无法获取错误信息".to_string());
+        return Err(format!("API 请求失败，状态码: {}, 错误: {}", status.as_u16(), error_text));
+    }
+
+    let response_text = response
+        .text()
+        .await
+        .map_err(|e| format!("读取响应失败: {}", e))?;
+    let json_response: Value = serde_json::from_str(&response_text)
+        .map_err(|e| format!("解析 JSON 失败: {}, 响应: {}", e, response_text))?;
+
+    let message = json_response["choices"][0]["message"]
+        .as_object()
+        .ok_or_else(|| "无法解析 API 响应".to_string())?;
+    let content = message
+        .get("content")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let usage = json_response["usage"].as_object().map(|usage| {
+        Usage::new(
+            usage.get("prompt_tokens").and_then(Value::as_u64).unwrap_or(0),
+            usage.get("completion_tokens").and_then(Value::as_u64).unwrap_or(0),
+        )
+    });
+    Ok(Completion::new(content, usage))
+}
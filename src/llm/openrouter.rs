@@ -0,0 +1,48 @@
+//! OpenRouter 后端
+//!
+//! OpenRouter 提供与 OpenAI 相同的 Chat Completions 接口，只是地址固定
+
+use super::openai::{chat_completion, chat_completions_url, request_body};
+use super::{Completion, CompletionRequest, LlmBackend};
+use crate::config::OpenRouterConfig;
+use async_trait::async_trait;
+use reqwest::Client;
+use std::time::Duration;
+
+/// OpenRouter API 地址
+const OPENROUTER_API_BASE: &str = "https://openrouter.ai/api/v1";
+
+/// OpenRouter 服务
+pub struct OpenRouterBackend {
+    config: OpenRouterConfig,
+    client: Client,
+    timeout: Duration,
+}
+
+impl OpenRouterBackend {
+    pub fn new(config: OpenRouterConfig, timeout: Duration) -> Self {
+        OpenRouterBackend { config, client: Client::new(), timeout }
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OpenRouterBackend {
+    fn name(&self) -> &str {
+        "openrouter"
+    }
+
+    fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    async fn complete(&self, request: CompletionRequest<'_>) -> Result<Completion, String> {
+        if self.config.api_key.is_empty() {
+            return Err("OpenRouter API key 未设置".to_string());
+        }
+        let url = chat_completions_url(OPENROUTER_API_BASE);
+        let body = request_body(&self.config.model, &request);
+        chat_completion(&self.client, &url, &self.config.api_key, &body, self.timeout)
+            .await
+            .map_err(|e| format!("OpenRouter 调用失败: {}", e))
+    }
+}
@@ -0,0 +1,322 @@
+//! LLM 用量和费用统计
+//!
+//! 每次调用 LLM 后端都记录服务商、模型、输入/输出 token 数和按价格表估算的费用，
+//! 追加到 `llm.usage.file`（JSON Lines）。进程内按天累计总额和每个窗格的用量，
+//! 当天费用达到 `llm.usage.daily_budget` 后降级链跳过远程后端，改用启发式规则
+
+use super::{Completion, CompletionRequest, LlmBackend};
+use crate::config::{ModelPrice, UsageConfig};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// 一次调用的 token 用量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    /// 输入 token 数
+    pub prompt_tokens: u64,
+    /// 输出 token 数
+    pub completion_tokens: u64,
+}
+
+impl Usage {
+    pub fn new(prompt_tokens: u64, completion_tokens: u64) -> Self {
+        Usage { prompt_tokens, completion_tokens }
+    }
+
+    /// 按每百万 token 的价格估算费用（美元）
+    pub fn cost(&self, price: &ModelPrice) -> f64 {
+        (self.prompt_tokens as f64 * price.input + self.completion_tokens as f64 * price.output) / 1_000_000.0
+    }
+}
+
+/// 用量日志中的一条记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Local>,
+    pub pane: String,
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// 估算费用（美元），价格表中没有这个模型时为 0
+    pub cost: f64,
+}
+
+/// 累计用量
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Totals {
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+}
+
+impl Totals {
+    fn add(&mut self, record: &UsageRecord) {
+        self.calls += 1;
+        self.prompt_tokens += record.prompt_tokens;
+        self.completion_tokens += record.completion_tokens;
+        self.cost += record.cost;
+    }
+}
+
+/// 当天的累计用量
+#[derive(Debug, Default)]
+struct Today {
+    /// 统计的日期，跨天后从日志重新加载
+    date: Option<NaiveDate>,
+    total: Totals,
+    panes: HashMap<String, Totals>,
+    /// 当天是否已经提示过超出预算
+    budget_warned: bool,
+}
+
+/// 用量账本
+///
+/// 读写用量日志时持有异步锁，文件 I/O 使用 `tokio::fs`，不会阻塞运行时线程
+#[derive(Debug, Default)]
+pub struct UsageLedger {
+    today: Mutex<Today>,
+}
+
+impl UsageLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次调用，追加到用量日志并输出日志
+    pub async fn record(&self, pane: &str, provider: &str, model: &str, usage: Usage, config: &UsageConfig) -> UsageRecord {
+        let record = UsageRecord {
+            timestamp: Local::now(),
+            pane: pane.to_string(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            cost: config.prices.get(model).map_or(0.0, |price| usage.cost(price)),
+        };
+        // 先加载当天已有的记录，避免刚写入的记录被重复计算
+        let mut today = self.today.lock().await;
+        refresh_today(&mut today, config).await;
+        if let Err(e) = append_record(&config.path(), &record).await {
+            eprintln!("⚠️ 无法写入用量日志 {}: {}", config.path().display(), e);
+        }
+        today.total.add(&record);
+        let pane_total = today.panes.entry(pane.to_string()).or_default();
+        pane_total.add(&record);
+        let pane_cost = pane_total.cost;
+        println!(
+            "💰 [{}] {}/{}: 输入 {} + 输出 {} tokens ≈ ${:.4}（窗格今日 ${:.4}，全部今日 ${:.4}）",
+            pane,
+            provider,
+            model,
+            record.prompt_tokens,
+            record.completion_tokens,
+            record.cost,
+            pane_cost,
+            today.total.cost
+        );
+        record
+    }
+
+    /// 当天的累计用量
+    pub async fn today(&self, config: &UsageConfig) -> Totals {
+        let mut today = self.today.lock().await;
+        refresh_today(&mut today, config).await;
+        today.total
+    }
+
+    /// 某个窗格当天的累计用量
+    pub async fn pane_today(&self, pane: &str, config: &UsageConfig) -> Totals {
+        let mut today = self.today.lock().await;
+        refresh_today(&mut today, config).await;
+        today.panes.get(pane).copied().unwrap_or_default()
+    }
+
+    /// 当天费用是否已经达到预算，第一次超出时输出提示
+    pub async fn budget_exceeded(&self, config: &UsageConfig) -> bool {
+        let Some(budget) = config.daily_budget else {
+            return false;
+        };
+        let mut today = self.today.lock().await;
+        refresh_today(&mut today, config).await;
+        let exceeded = today.total.cost >= budget;
+        if exceeded && !today.budget_warned {
+            today.budget_warned = true;
+            println!(
+                "💸 今日 LLM 费用 ${:.4} 已达到预算 ${:.2}，今天剩余时间跳过远程后端，使用启发式规则判断",
+                today.total.cost, budget
+            );
+        }
+        exceeded
+    }
+}
+
+/// 第一次使用或跨天时从用量日志加载当天的记录，重启后预算仍然有效
+async fn refresh_today(today: &mut Today, config: &UsageConfig) {
+    let date = Local::now().date_naive();
+    if today.date == Some(date) {
+        return;
+    }
+    *today = Today { date: Some(date), ..Today::default() };
+    let path = config.path();
+    let records = match tokio::fs::read_to_string(&path).await {
+        Ok(content) => parse_records(&content),
+        Err(_) => Vec::new(),
+    };
+    for record in records.iter().filter(|record| record.timestamp.date_naive() == date) {
+        today.total.add(record);
+        today.panes.entry(record.pane.clone()).or_default().add(record);
+    }
+}
+
+async fn append_record(path: &Path, record: &UsageRecord) -> Result<(), String> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(dir).await.map_err(|e| e.to_string())?;
+    }
+    let line = serde_json::to_string(record).map_err(|e| e.to_string())? + "\n";
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    file.write_all(line.as_bytes()).await.map_err(|e| e.to_string())?;
+    // tokio 的文件写入在后台线程完成，flush 之后记录才确实写入
+    file.flush().await.map_err(|e| e.to_string())
+}
+
+/// 读取用量日志，文件不存在时返回空列表，无法解析的行被跳过
+pub fn load_records(path: &Path) -> Result<Vec<UsageRecord>, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("无法读取用量日志 {}: {}", path.display(), e)),
+    };
+    Ok(parse_records(&content))
+}
+
+/// 逐行解析用量记录，跳过无法解析的行
+fn parse_records(content: &str) -> Vec<UsageRecord> {
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// 记录用量的后端包装，调用成功且服务商返回了用量时记账
+pub struct MeteredBackend {
+    inner: Box<dyn LlmBackend>,
+    pane: String,
+    ledger: Arc<UsageLedger>,
+    config: UsageConfig,
+}
+
+impl MeteredBackend {
+    pub fn new(inner: Box<dyn LlmBackend>, pane: &str, ledger: Arc<UsageLedger>, config: &UsageConfig) -> Self {
+        MeteredBackend {
+            inner,
+            pane: pane.to_string(),
+            ledger,
+            config: config.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmBackend for MeteredBackend {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn is_remote(&self) -> bool {
+        self.inner.is_remote()
+    }
+
+    fn is_heuristic(&self) -> bool {
+        self.inner.is_heuristic()
+    }
+
+    async fn complete(&self, request: CompletionRequest<'_>) -> Result<Completion, String> {
+        let completion = self.inner.complete(request).await?;
+        if let Some(usage) = completion.usage {
+            self.ledger.record(&self.pane, self.name(), self.model(), usage, &self.config).await;
+        }
+        Ok(completion)
+    }
+}
+
+/// 一段时间内的用量汇总
+#[derive(Debug, Clone, Default)]
+pub struct UsageSummary {
+    pub since: Option<NaiveDate>,
+    pub by_day: BTreeMap<NaiveDate, Totals>,
+    pub by_pane: BTreeMap<String, Totals>,
+    pub by_model: BTreeMap<String, Totals>,
+    pub total: Totals,
+}
+
+/// 汇总 `since` 当天及之后的记录
+pub fn summarize(records: &[UsageRecord], since: NaiveDate) -> UsageSummary {
+    let mut summary = UsageSummary { since: Some(since), ..UsageSummary::default() };
+    for record in records.iter().filter(|record| record.timestamp.date_naive() >= since) {
+        summary.by_day.entry(record.timestamp.date_naive()).or_default().add(record);
+        summary.by_pane.entry(record.pane.clone()).or_default().add(record);
+        summary
+            .by_model
+            .entry(format!("{}/{}", record.provider, record.model))
+            .or_default()
+            .add(record);
+        summary.total.add(record);
+    }
+    summary
+}
+
+impl fmt::Display for UsageSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(since) = self.since {
+            writeln!(f, "LLM 用量（{} 起）", since)?;
+        }
+        if self.total.calls == 0 {
+            return writeln!(f, "没有记录");
+        }
+        let sections = [
+            ("按日期", self.by_day.iter().map(|(day, totals)| (day.to_string(), totals)).collect::<Vec<_>>()),
+            ("按窗格", self.by_pane.iter().map(|(pane, totals)| (pane.clone(), totals)).collect()),
+            ("按模型", self.by_model.iter().map(|(model, totals)| (model.clone(), totals)).collect()),
+        ];
+        for (title, rows) in sections {
+            writeln!(f, "\n{}:", title)?;
+            writeln!(f, "  {:<32} {:>6} {:>12} {:>12} {:>10}", "", "调用", "输入 tokens", "输出 tokens", "费用")?;
+            for (name, totals) in rows {
+                write_row(f, &name, totals)?;
+            }
+        }
+        writeln!(f)?;
+        write_row(f, "合计", &self.total)
+    }
+}
+
+fn write_row(f: &mut fmt::Formatter<'_>, name: &str, totals: &Totals) -> fmt::Result {
+    writeln!(
+        f,
+        "  {:<32} {:>6} {:>12} {:>12} {:>10}",
+        name,
+        totals.calls,
+        totals.prompt_tokens,
+        totals.completion_tokens,
+        format!("${:.4}", totals.cost)
+    )
+}
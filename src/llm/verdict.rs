@@ -0,0 +1,108 @@
+//! 解析 LLM 返回的状态判断
+//!
+//! 要求模型输出 JSON 对象，但也接受代码块包裹、前后带说明文字、
+//! 字段名或标签格式不规范，以及只有一个状态标签的纯文本回答
+
+use crate::state::{ClaudeState, Verdict};
+use serde_json::{json, Map, Value};
+
+/// 判断结果的 JSON Schema，用于工具调用
+pub fn verdict_schema() -> Value {
+    let labels: Vec<&str> = ClaudeState::ALL.iter().map(|state| state.label()).collect();
+    json!({
+        "type": "object",
+        "properties": {
+            "state": {"type": "string", "enum": labels},
+            "confidence": {"type": "number", "minimum": 0, "maximum": 1},
+            "reason": {"type": "string"},
+            "suggested_action": {"type": "string"}
+        },
+        "required": ["state", "confidence", "reason"]
+    })
+}
+
+/// 把 LLM 的回答解析为判断结果
+pub fn parse_verdict(response: &str) -> Result<Verdict, String> {
+    let text = response.trim();
+    if let Some(verdict) = extract_json_object(text)
+        .and_then(|json| serde_json::from_str::<Value>(json).ok())
+        .and_then(|value| value.as_object().and_then(verdict_from_json))
+    {
+        return Ok(verdict);
+    }
+
+    // 不是 JSON：在文本中找状态标签，其余内容作为理由
+    let state = find_state_label(text).ok_or_else(|| format!("LLM 返回未知状态: {}", text))?;
+    let reason = if normalize_label(text) == Some(state) { String::new() } else { text.to_string() };
+    Ok(Verdict::new(state, reason))
+}
+
+/// 取出第一个 `{` 到最后一个 `}` 之间的内容
+fn extract_json_object(text: &str) -> Option<&str> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    (start < end).then(|| &text[start..=end])
+}
+
+/// 从 JSON 对象读取判断结果，兼容常见的字段别名
+fn verdict_from_json(object: &Map<String, Value>) -> Option<Verdict> {
+    let field = |names: &[&str]| names.iter().find_map(|name| object.get(*name));
+    let text_field = |names: &[&str]| {
+        field(names)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+
+    let state = field(&["state", "status", "label"])
+        .and_then(Value::as_str)
+        .and_then(normalize_label)?;
+    Some(Verdict {
+        state,
+        confidence: field(&["confidence", "score"]).and_then(parse_confidence),
+        reason: text_field(&["reason", "explanation", "reasoning"]).unwrap_or_default(),
+        suggested_action: text_field(&["suggested_action", "suggestedAction", "action"]),
+    })
+}
+
+/// 置信度可以是 0~1 的小数、百分数或数字字符串
+fn parse_confidence(value: &Value) -> Option<f32> {
+    let number = match value {
+        Value::Number(number) => number.as_f64()?,
+        Value::String(text) => {
+            let text = text.trim();
+            match text.strip_suffix('%') {
+                Some(percent) => percent.trim().parse::<f64>().ok()? / 100.0,
+                None => text.parse().ok()?,
+            }
+        }
+        _ => return None,
+    };
+    let number = if number > 1.0 && number <= 100.0 { number / 100.0 } else { number };
+    Some(number.clamp(0.0, 1.0) as f32)
+}
+
+/// 宽松地解析状态标签：忽略大小写、首尾标点，空格和连字符视为下划线
+fn normalize_label(label: &str) -> Option<ClaudeState> {
+    let label = label
+        .trim()
+        .trim_matches(|c: char| !c.is_alphanumeric() && c != '_')
+        .replace([' ', '-'], "_");
+    ClaudeState::from_label(&label)
+}
+
+/// 在纯文本中查找状态标签，优先匹配全大写的标签
+fn find_state_label(text: &str) -> Option<ClaudeState> {
+    if let Some(state) = normalize_label(text) {
+        return Some(state);
+    }
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+        .collect();
+    words
+        .iter()
+        .find_map(|word| ClaudeState::ALL.iter().copied().find(|state| state.label() == *word))
+        .or_else(|| words.iter().find_map(|word| ClaudeState::from_label(word)))
+}
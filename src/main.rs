@@ -1,54 +1,321 @@
 use dotenvy::dotenv;
-use std::io;
-use std::time::Instant;
-
-mod config;
-mod args;
-mod tmux;
-mod activity;
-mod llm;
-mod monitor;
-
-use config::Config;
-use args::Args;
-use monitor::run_monitoring_loop;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use claude_watch::config::validate::{self, Severity};
+use claude_watch::config::layers::{find_project_file, redacted_value, Locations};
+use claude_watch::config::Config;
+use claude_watch::args::{Args, Command, ConfigCommand};
+use claude_watch::discovery::run_discovery_loop;
+use claude_watch::llm::usage::{load_records, summarize};
+use claude_watch::commands;
+use claude_watch::monitor::watch_panes;
+use claude_watch::reload::{self, ConfigUpdates, FileWatcher};
+use claude_watch::shutdown::{self, Shutdown, Signal, Signals};
+use claude_watch::terminal::TerminalBackend;
+use claude_watch::tmux::TmuxBackend;
 use clap::Parser;
 
+/// 收到退出信号后等待监控任务自行结束的最长时间
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
+
+/// 检查配置文件修改时间的间隔
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
 fn main() -> io::Result<()> {
     dotenv().ok();
     let args = Args::parse();
+    let terminal: Arc<dyn TerminalBackend> = Arc::new(TmuxBackend::new());
+    let runtime = tokio::runtime::Runtime::new()?;
 
-    // 加载配置
-    let mut config = Config::load(&args.config).unwrap_or_else(|_| {
-        eprintln!("无法加载配置文件 {}，使用默认配置", args.config);
-        Config::default()
-    });
+    let code = match args.command() {
+        // 检查配置时需要看到所有错误，不能在加载失败时直接退出
+        Command::Config { action: ConfigCommand::Check } => runtime.block_on(check_config(&args, terminal.as_ref())),
+        Command::Config { action: ConfigCommand::Show { resolved } } => {
+            runtime.block_on(show_config(&args, &load_or_exit(&args), resolved, terminal.as_ref()));
+            0
+        }
+        Command::Usage { days } => {
+            print_usage(&load_or_exit(&args), days)?;
+            0
+        }
+        Command::Watch => {
+            let result = runtime.block_on(watch(&args, load_or_exit(&args), terminal));
+            io::stdout().flush()?;
+            return result;
+        }
+        Command::Status => runtime.block_on(commands::status(&load_or_exit(&args), terminal.as_ref())),
+        Command::Capture { raw } => {
+            let config = load_or_exit(&args);
+            match runtime.block_on(commands::capture(&config, terminal.as_ref(), raw)) {
+                Ok(screen) => {
+                    println!("{}", screen.trim_end_matches('\n'));
+                    0
+                }
+                Err(e) => {
+                    eprintln!("❌ 无法捕获窗格: {}", e);
+                    1
+                }
+            }
+        }
+        Command::Classify { file, no_llm } => match commands::read_screen(&file) {
+            Ok(text) => {
+                let verdict = runtime.block_on(commands::classify(&load_or_exit(&args), &text, !no_llm));
+                commands::print_verdict(&verdict);
+                verdict.state.exit_code()
+            }
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                1
+            }
+        },
+        Command::Send { text, keys } => {
+            let config = load_or_exit(&args);
+            match runtime.block_on(commands::send(&config, terminal.as_ref(), text.as_deref(), &keys)) {
+                Ok(sent) => {
+                    println!("📨 已发送: {}", sent.join(", "));
+                    0
+                }
+                Err(e) => {
+                    eprintln!("❌ 发送失败: {}", e);
+                    1
+                }
+            }
+        }
+    };
+    io::stdout().flush()?;
+    std::process::exit(code);
+}
 
-    // 使用命令行参数覆盖配置（如果提供）
-    if let Some(pane) = &args.pane {
-        config.tmux.pane = pane.clone();
+/// 加载配置，配置无效时输出错误后退出
+fn load_or_exit(args: &Args) -> Config {
+    match load_config(args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("❌ 配置无效，可以用 `claude-watch config check` 查看详情");
+            std::process::exit(1);
+        }
     }
-    if let Some(backend) = &args.backend {
-        config.llm.backend = backend.clone();
+}
+
+/// `watch`：持续监控窗格直到收到退出信号
+async fn watch(args: &Args, config: Config, terminal: Arc<dyn TerminalBackend>) -> io::Result<()> {
+    if config.tmux.discover {
+        println!("开始自动发现并监控所有 tmux 会话中的 Claude Code");
+    } else {
+        let pane_ids: Vec<String> = config.tmux.pane_list().into_iter().map(|p| p.id).collect();
+        println!("开始监控 Claude Code 在 tmux pane {} 中的状态", pane_ids.join(", "));
     }
-    if let Some(interval) = args.interval {
-        config.monitoring.interval = interval;
+    println!("使用 LLM 后端: {}", config.llm.backend);
+
+    // 主监控循环：每个窗格一个异步任务
+    run_until_signal(args, config, terminal).await
+}
+
+/// 输出最近 `days` 天（包括今天）的 LLM 用量汇总
+fn print_usage(config: &Config, days: u32) -> io::Result<()> {
+    let path = config.llm.usage.path();
+    let records = load_records(&path).map_err(io::Error::other)?;
+    let since = chrono::Local::now().date_naive() - chrono::Duration::days(i64::from(days.max(1)) - 1);
+    println!("用量日志: {}", path.display());
+    print!("{}", summarize(&records, since));
+    Ok(())
+}
+
+/// `config show`：输出有效配置，`--resolved` 时逐项标出来源
+async fn show_config(args: &Args, config: &Config, resolved: bool, terminal: &dyn TerminalBackend) {
+    let config = match &args.pane {
+        Some(pane) => match terminal.pane_dir(pane).await {
+            Some(dir) => config.in_dir(&dir),
+            None => config.clone(),
+        },
+        None => config.clone(),
+    };
+    match (&config.layers, resolved) {
+        (Some(layers), true) => {
+            println!("# 配置层（优先级从低到高）:");
+            for layer in layers.layers() {
+                println!("#   {}", layer.source.describe());
+            }
+            for value in layers.describe(&config) {
+                println!("{}", value);
+            }
+        }
+        _ => print!("{}", serde_yaml::to_string(&redacted_value(&config)).unwrap_or_default()),
     }
-    if let Some(stuck_sec) = args.stuck_sec {
-        config.monitoring.stuck_sec = stuck_sec;
+}
+
+/// 加载配置，输出警告，有错误时返回所有错误
+fn load_config(args: &Args) -> Result<Config, String> {
+    let file = args.config_file();
+    let (config, diagnostics) = Config::resolve(args).map_err(|d| format!("❌ {}", d.render(file)))?;
+    for diagnostic in diagnostics.iter().filter(|d| d.severity == Severity::Warning) {
+        eprintln!("⚠️ {}", diagnostic.render(file));
     }
-    if let Some(max_retry) = args.max_retry {
-        config.monitoring.max_retry = max_retry;
+    if validate::has_errors(&diagnostics) {
+        let errors: Vec<String> = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .map(|d| format!("❌ {}", d.render(file)))
+            .collect();
+        return Err(errors.join("\n"));
     }
+    Ok(config)
+}
 
-    let mut last_active = Instant::now();
-    let mut retry_count = 0usize;
+/// `config check`：检查配置文件和窗格，有错误时返回 1
+async fn check_config(args: &Args, terminal: &dyn TerminalBackend) -> i32 {
+    let (config, mut diagnostics) = match Config::resolve(args) {
+        Ok(result) => result,
+        Err(e) => {
+            println!("❌ {}", e.render(args.config_file()));
+            return 1;
+        }
+    };
+    let panes = validate::check_panes(&config, terminal).await;
+    diagnostics.extend(panes.into_iter().map(|d| match &config.layers {
+        Some(layers) => layers.attribute(d),
+        None => d,
+    }));
 
-    println!("开始监控 Claude Code 在 tmux pane {} 中的状态", config.tmux.pane);
-    println!("使用 LLM 后端: {}", config.llm.backend);
+    for diagnostic in &diagnostics {
+        let icon = if diagnostic.severity == Severity::Error { "❌" } else { "⚠️" };
+        println!("{} {}", icon, diagnostic.render(args.config_file()));
+    }
+    let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    if errors > 0 {
+        println!("发现 {} 个错误，{} 个警告", errors, diagnostics.len() - errors);
+        1
+    } else {
+        println!("✅ 配置文件 {} 检查通过（{} 个警告）", args.config_file(), diagnostics.len());
+        0
+    }
+}
 
-    // 主监控循环
-    tokio::runtime::Runtime::new().unwrap().block_on(async {
-        run_monitoring_loop(&config, &mut last_active, &mut retry_count).await
-    })
-}
\ No newline at end of file
+/// 按配置启动所有监控任务
+async fn run_watchers(updates: ConfigUpdates, terminal: Arc<dyn TerminalBackend>, shutdown: Shutdown) -> io::Result<()> {
+    if updates.current().tmux.discover {
+        run_discovery_loop(updates, terminal, shutdown).await
+    } else {
+        watch_panes(updates, terminal, shutdown).await
+    }
+}
+
+/// 需要监视的配置文件
+///
+/// 包括正在使用的各层文件、尚不存在的系统/用户/`--config` 文件，以及各窗格工作目录对应的项目配置
+async fn config_files(args: &Args, config: &Config, terminal: &dyn TerminalBackend) -> Vec<PathBuf> {
+    let locations = Locations::from_env();
+    let mut files = vec![locations.system, PathBuf::from(args.config_file())];
+    files.extend(locations.user);
+    if let Some(layers) = &config.layers {
+        files.extend(layers.layers().iter().filter_map(|layer| layer.source.path().map(Path::to_path_buf)));
+    }
+    for pane in terminal.list_panes().await.unwrap_or_default() {
+        if let Some(project) = terminal.pane_dir(&pane.id).await.and_then(|dir| find_project_file(&dir)) {
+            files.push(project);
+        }
+    }
+    files
+}
+
+/// 运行监控直到收到退出信号
+///
+/// 配置文件被修改或收到 SIGHUP 时重新加载配置，校验通过后交给正在运行的任务，不重置各窗格的计时；
+/// 只有切换自动发现模式时才停止所有任务后重新启动。SIGINT / SIGTERM 通知所有任务在当前操作完成后退出，
+/// 任务在宽限期内没有结束或再次收到退出信号时直接中止
+async fn run_until_signal(args: &Args, mut config: Config, terminal: Arc<dyn TerminalBackend>) -> io::Result<()> {
+    let mut signals = Signals::new()?;
+    let mut files = FileWatcher::new(config_files(args, &config, terminal.as_ref()).await);
+    let mut poll = tokio::time::interval(CONFIG_POLL_INTERVAL);
+
+    loop {
+        let (trigger, shutdown) = shutdown::channel();
+        let (publisher, updates) = reload::channel(config.clone());
+        let mut watchers = tokio::spawn(run_watchers(updates, Arc::clone(&terminal), shutdown));
+
+        // 收到退出信号时为 None，需要重新启动任务时为新配置
+        let restart = loop {
+            let reason = tokio::select! {
+                result = &mut watchers => {
+                    // 所有窗格都已关闭
+                    return result.map_err(io::Error::other)?;
+                }
+                signal = signals.recv() => match signal {
+                    Signal::Terminate => break None,
+                    Signal::Reload => format!("收到 SIGHUP，重新加载配置 {}", args.config_file()),
+                },
+                _ = poll.tick() => {
+                    let changed = files.poll();
+                    if changed.is_empty() {
+                        continue;
+                    }
+                    let names: Vec<String> = changed.iter().map(|path| path.display().to_string()).collect();
+                    format!("配置文件 {} 已修改，重新加载", names.join(", "))
+                }
+            };
+            println!("🔄 {}", reason);
+            // 读取 API key 可能要执行 api_key_cmd，不能阻塞监控任务所在的线程
+            let reload_args = args.clone();
+            let reloaded = tokio::task::spawn_blocking(move || load_config(&reload_args))
+                .await
+                .unwrap_or_else(|e| Err(format!("❌ {}", e)));
+            match reloaded {
+                Ok(reloaded) => {
+                    files = FileWatcher::new(config_files(args, &reloaded, terminal.as_ref()).await);
+                    if reloaded.tmux.discover != publisher.current().tmux.discover {
+                        println!("🔄 监控模式已切换，重新启动所有监控任务");
+                        break Some(reloaded);
+                    }
+                    println!("✅ 配置已重新加载，使用 LLM 后端: {}", reloaded.llm.backend);
+                    publisher.publish(reloaded);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    eprintln!("⚠️ 新配置无效，继续使用原配置");
+                }
+            }
+        };
+
+        if restart.is_none() {
+            println!("🛑 收到退出信号，等待当前操作完成...");
+        }
+        trigger.trigger();
+
+        let deadline = tokio::time::sleep(SHUTDOWN_GRACE);
+        tokio::pin!(deadline);
+        loop {
+            tokio::select! {
+                result = &mut watchers => {
+                    if let Ok(Err(e)) = result {
+                        eprintln!("⚠️ 监控任务退出时出错: {}", e);
+                    }
+                    break;
+                }
+                _ = &mut deadline => {
+                    println!("⚠️ 监控任务 {} 秒内没有结束，强制中止", SHUTDOWN_GRACE.as_secs());
+                    watchers.abort();
+                    break;
+                }
+                again = signals.recv() => {
+                    if again == Signal::Terminate {
+                        println!("⚠️ 再次收到退出信号，立即中止");
+                        watchers.abort();
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        match restart {
+            Some(reloaded) => config = reloaded,
+            None => {
+                println!("👋 claude-watch 已退出");
+                return Ok(());
+            }
+        }
+    }
+}
@@ -1,35 +1,178 @@
 use crate::config::Config;
-use crate::activity::is_claude_active;
-use crate::llm::ask_llm_final_status;
-use crate::llm::TaskStatus;
-use crate::tmux::{capture, send_keys};
-use std::collections::HashMap;
-use std::sync::{OnceLock, Mutex};
-use std::thread;
+use crate::activity::{detect_screen_state, is_claude_active, parse_permission_prompt, parse_usage_limit, PermissionPrompt, UsageLimit};
+use crate::llm::{ask_llm_final_status, simple_heuristic_check};
+use crate::permission::{self, PermissionDecision};
+use crate::prompt::PromptContext;
+use crate::recovery::run_recovery_step;
+use crate::state::{ClaudeState, Verdict};
+use crate::reload::ConfigUpdates;
+use crate::shutdown::Shutdown;
+use crate::terminal::TerminalBackend;
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use std::io;
+use tokio::task::{AbortHandle, JoinSet};
 
-/// Pane状态枚举
-#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
-pub enum PaneStatus {
-    Active,
-    Stuck,
-    Idle,
-    Completed,
+// 简单的println日志，复杂的日志系统暂时跳过
+
+/// 生成激活消息时最多附带的无效尝试数
+pub const MAX_FAILED_ATTEMPTS: usize = 5;
+
+/// 上下文已满时最多连续发送 `/compact` 的次数，之后等待人工处理
+pub const MAX_COMPACT_ATTEMPTS: usize = 3;
+
+/// 单个窗格的监控状态
+///
+/// 每个被监控的窗格持有独立的一份，多个窗格并发监控时互不干扰
+#[derive(Debug, Clone)]
+pub struct PaneState {
+    /// 最近一次检测到活动的时间
+    pub last_active: Instant,
+    /// 当前恢复步骤已经尝试的次数
+    pub retry_count: usize,
+    /// 当前所处的恢复步骤（`Config::recovery_steps` 中的下标）
+    pub recovery_step: usize,
+    /// 最近一次识别出的 Claude Code 状态
+    pub last_state: Option<ClaudeState>,
+    /// 最近一次 LLM 判断的结果，恢复步骤通知时附带判断依据
+    pub last_verdict: Option<Verdict>,
+    /// 最近一次执行的恢复操作，提示词模板中的 `{{last_action}}`
+    pub last_action: Option<String>,
+    /// 本轮卡住以来没有奏效的恢复操作，生成激活消息时告诉 LLM 避免重复
+    pub failed_attempts: Vec<String>,
+    /// 上下文已满以来发送 `/compact` 的次数
+    pub compact_attempts: usize,
+    /// 退出通知，收到后监控循环在下一次等待时结束
+    pub shutdown: Shutdown,
+    /// 配置热重载通知，监控循环在每轮检查开始时应用新配置
+    pub updates: Option<ConfigUpdates>,
+    /// 触发用量限制时计划恢复的时间，期间不执行任何恢复操作
+    pub rate_limited_until: Option<DateTime<Local>>,
+    /// 上一次记录的屏幕内容，用于内容变化检测
+    last_content: String,
+    /// 上一次提取到的执行时间，用于时间递增检测
+    last_execution_time: Option<u64>,
+    /// 最近一次处理过的权限对话框，避免对同一个对话框重复应答或通知
+    last_permission_prompt: Option<String>,
+    /// 当前用量限制提示的内容
+    rate_limit_message: Option<String>,
+    /// 已经恢复过的用量限制提示，仍留在屏幕上时不再重复处理
+    resumed_rate_limit: Option<String>,
 }
 
-// 简单的println日志，复杂的日志系统暂时跳过
+impl Default for PaneState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-/// 全局状态，用于追踪时间变化
-static TIME_TRACKER: OnceLock<Mutex<HashMap<String, u64>>> = OnceLock::new();
+impl PaneState {
+    pub fn new() -> Self {
+        Self {
+            last_active: Instant::now(),
+            retry_count: 0,
+            recovery_step: 0,
+            last_state: None,
+            last_verdict: None,
+            last_action: None,
+            failed_attempts: Vec::new(),
+            compact_attempts: 0,
+            shutdown: Shutdown::never(),
+            updates: None,
+            rate_limited_until: None,
+            last_content: String::new(),
+            last_execution_time: None,
+            last_permission_prompt: None,
+            rate_limit_message: None,
+            resumed_rate_limit: None,
+        }
+    }
 
-/// 重置全局状态（用于测试）
-pub fn reset_time_tracker() {
-    // OnceLock不支持重置，所以我们使用一个技巧：在测试中忽略时间递增检查
-    // 在实际使用中，这不会成为问题
+    /// 使用指定退出通知的监控状态
+    pub fn with_shutdown(shutdown: Shutdown) -> Self {
+        Self {
+            shutdown,
+            ..Self::new()
+        }
+    }
+
+    /// 使用指定退出通知和配置更新通知的监控状态
+    pub fn with_updates(shutdown: Shutdown, updates: ConfigUpdates) -> Self {
+        Self {
+            updates: Some(updates),
+            ..Self::with_shutdown(shutdown)
+        }
+    }
+
+    /// 渲染提示词模板用的变量
+    pub fn prompt_context(&self, config: &Config, screen: &str) -> PromptContext {
+        PromptContext {
+            idle_seconds: self.last_active.elapsed().as_secs(),
+            last_action: self.last_action.clone(),
+            state: self.last_state,
+            failed_attempts: self.failed_attempts.clone(),
+            ..PromptContext::from_config(config, screen)
+        }
+    }
+
+    /// 判断屏幕内容相对上一次记录是否有实质性变化，有变化时更新记录
+    pub fn content_changed(&mut self, text: &str) -> bool {
+        if self.last_content.is_empty() {
+            // 第一次运行，有内容就认为有变化
+            self.last_content = text.to_string();
+            return true;
+        }
+        // 智能内容变化检测：忽略纯时间变化和系统信息变化
+        let changed = has_substantial_content_change(text, &self.last_content);
+        if changed {
+            self.last_content = text.to_string();
+        }
+        changed
+    }
+
+    /// 回到恢复阶梯的第一步
+    pub fn reset_recovery(&mut self) {
+        self.retry_count = 0;
+        self.recovery_step = 0;
+        self.failed_attempts.clear();
+        self.compact_attempts = 0;
+    }
+
+    /// 记录一次没有奏效的恢复操作，只保留最近 `MAX_FAILED_ATTEMPTS` 条
+    pub fn record_failed_attempt(&mut self, action: String) {
+        if self.failed_attempts.len() >= MAX_FAILED_ATTEMPTS {
+            self.failed_attempts.remove(0);
+        }
+        self.failed_attempts.push(action);
+    }
+
+    /// 检查执行时间是否相对上一次记录在递增
+    pub fn time_increasing(&mut self, text: &str) -> bool {
+        is_time_increasing(text, &mut self.last_execution_time)
+    }
+}
+
+/// 检查时间是否在递增（表明Claude Code在工作）
+///
+/// `previous` 是上一次看到的执行时间，由调用方保存（监控循环保存在 `PaneState` 中），
+/// 看到新的时间时更新它。第一次看到时间认为是活动的
+pub fn is_time_increasing(text: &str, previous: &mut Option<u64>) -> bool {
+    match (extract_execution_time(text), *previous) {
+        (Some(current), Some(previous_time)) if current > previous_time => {
+            *previous = Some(current);
+            true
+        }
+        (Some(current), None) => {
+            *previous = Some(current);
+            true
+        }
+        _ => false,
+    }
 }
 
-/// 预编译正则表达式以提高性能
+// 预编译正则表达式以提高性能
 lazy_static::lazy_static! {
     static ref TIME_PATTERN: regex::Regex = regex::Regex::new(r"\((\d+)s[^)]*\)").unwrap();
     static ref SIMPLE_TIME_PATTERN: regex::Regex = regex::Regex::new(r"(\d+)s").unwrap();
@@ -48,6 +191,8 @@ lazy_static::lazy_static! {
         regex::Regex::new(r"╰─*╯").unwrap(),
         regex::Regex::new(r"│.*│").unwrap(),
     ];
+    /// 只由框线字符组成的行
+    static ref BORDER_LINE: regex::Regex = regex::Regex::new(r"^[\s─━═│┃╭╮╰╯┌┐└┘├┤┬┴┼]+$").unwrap();
     static ref STATUS_PATTERNS: Vec<regex::Regex> = vec![
         regex::Regex::new(r"✽").unwrap(),
         regex::Regex::new(r"✶").unwrap(),
@@ -91,30 +236,122 @@ pub fn extract_execution_time(text: &str) -> Option<u64> {
     None
 }
 
-/// 检查时间是否在递增（表明Claude Code在工作）
-pub fn is_time_increasing(current_text: &str, pane: &str) -> bool {
-    let tracker = TIME_TRACKER.get_or_init(|| Mutex::new(HashMap::new()));
-    let current_time = extract_execution_time(current_text);
+/// 并发监控配置中的所有窗格
+///
+/// 每个窗格启动一个独立的异步任务，使用应用了项目配置和窗格覆盖项的配置和独立的 `PaneState`。
+/// 收到新配置时为新加入列表的窗格启动任务、中止被移出列表的窗格的任务，其余任务自行应用新配置。
+/// 所有窗格都关闭后返回
+pub async fn watch_panes(
+    mut updates: ConfigUpdates,
+    terminal: Arc<dyn TerminalBackend>,
+    shutdown: Shutdown,
+) -> io::Result<()> {
+    let mut tasks = JoinSet::new();
+    let mut watchers: HashMap<String, AbortHandle> = HashMap::new();
+    // 已经关闭的窗格，留在配置中也不再重新监控
+    let mut closed: HashSet<String> = HashSet::new();
+    let mut config = updates.current();
     
-    if let Some(current) = current_time {
-        let key = pane.to_string();
-        
-        if let Ok(mut tracker_guard) = tracker.lock() {
-            if let Some(&previous_time) = tracker_guard.get(&key) {
-                // 如果时间比上次大，说明在递增
-                if current > previous_time {
-                    tracker_guard.insert(key, current);
-                    return true;
-                }
+    loop {
+        let panes = config.tmux.pane_list();
+        closed.retain(|id| panes.iter().any(|pane| &pane.id == id));
+        watchers.retain(|id, handle| {
+            if panes.iter().any(|pane| &pane.id == id) {
+                true
             } else {
-                // 第一次记录时间
-                tracker_guard.insert(key, current);
-                return true; // 第一次看到时间，认为是活动的
+                println!("➖ [{}] 已从配置中移除，停止监控", id);
+                handle.abort();
+                false
             }
+        });
+        for pane in panes {
+            if watchers.contains_key(&pane.id) || closed.contains(&pane.id) {
+                continue;
+            }
+            let pane_config = pane_config(&config, terminal.as_ref(), &pane.id).await;
+            let terminal = Arc::clone(&terminal);
+            let mut state = PaneState::with_updates(shutdown.clone(), updates.clone());
+            println!("👀 开始监控 tmux pane {} (后端: {})", pane.id, pane_config.llm.backend);
+            let handle = tasks.spawn(async move {
+                let result = run_monitoring_loop(&pane_config, terminal.as_ref(), &mut state).await;
+                (pane_config.tmux.pane, result)
+            });
+            watchers.insert(pane.id, handle);
+        }
+        if tasks.is_empty() {
+            return Ok(());
+        }
+        
+        tokio::select! {
+            Some(updated) = updates.changed() => config = updated,
+            Some(joined) = tasks.join_next() => match joined {
+                Ok((pane, Ok(()))) => {
+                    watchers.remove(&pane);
+                    closed.insert(pane);
+                }
+                Ok((_, Err(e))) => return Err(e),
+                Err(e) if e.is_cancelled() => {}
+                Err(e) => return Err(io::Error::other(e)),
+            },
         }
     }
+}
+
+/// 窗格的有效配置
+///
+/// 先按窗格的工作目录向上查找项目配置 `.claude-watch.yaml`，再应用 `tmux.panes` 中的覆盖项。
+/// 项目配置可能要读取新的 `api_key_cmd`，所以在阻塞线程中解析，不占用异步工作线程
+pub async fn pane_config(config: &Config, terminal: &dyn TerminalBackend, pane: &str) -> Config {
+    let Some(dir) = terminal.pane_dir(pane).await else {
+        return config.for_pane_in(pane, None);
+    };
+    let (global, id) = (config.clone(), pane.to_string());
+    tokio::task::spawn_blocking(move || global.for_pane_in(&id, Some(&dir)))
+        .await
+        .unwrap_or_else(|_| config.for_pane_in(pane, None))
+}
+
+/// 对一屏内容做一次完整的状态判断，不依赖历史记录
+pub async fn classify_screen(config: &Config, text: &str) -> ClaudeState {
+    judge_screen(config, text).await.state
+}
+
+/// 对一屏内容做一次完整的状态判断，并给出判断依据
+///
+/// 识别顺序与监控循环一致：权限对话框、用量限制、执行中，
+/// 之后是启发式检查，只有启发式得出卡住/出错时才询问 LLM
+pub async fn judge_screen(config: &Config, text: &str) -> Verdict {
+    match judge_screen_locally(text) {
+        Some(verdict) => verdict,
+        None => ask_llm_final_status(&PromptContext::from_config(config, text), config).await,
+    }
+}
+
+/// 只用本地规则判断一屏内容
+///
+/// 启发式得出卡住/出错、需要交给 LLM 确认时返回 None
+pub fn judge_screen_locally(text: &str) -> Option<Verdict> {
+    if parse_permission_prompt(text).is_some() {
+        return Some(Verdict::new(ClaudeState::WaitingForPermission, "出现权限确认对话框"));
+    }
+    if let Some(limit) = parse_usage_limit(text, Local::now()) {
+        return Some(Verdict::new(ClaudeState::RateLimited, limit.message));
+    }
+    if is_claude_active(text) {
+        return Some(Verdict::new(detect_screen_state(text).unwrap_or(ClaudeState::Working), "画面显示正在执行"));
+    }
     
-    false
+    let heuristic_state = simple_heuristic_check(text);
+    if !heuristic_state.needs_recovery() {
+        return Some(Verdict::new(heuristic_state, "启发式规则判断"));
+    }
+    None
+}
+
+/// 捕获 `tmux.pane` 并判断一次状态
+pub async fn classify_once(config: &Config, terminal: &dyn TerminalBackend) -> Result<ClaudeState, String> {
+    let text = terminal.capture(&config.tmux.pane).await?;
+    Ok(classify_screen(config, &text).await)
 }
 
 /// 运行主监控循环
@@ -124,59 +361,95 @@ pub fn is_time_increasing(current_text: &str, pane: &str) -> bool {
 /// 2. 检测 Claude Code 是否活跃
 /// 3. 如果无活动超过指定时间，调用 LLM 判断状态
 /// 4. 根据判断结果采取相应行动
+///
+/// 当窗格不再存在时返回 `Ok(())`，其余情况一直运行
 pub async fn run_monitoring_loop(
     config: &Config,
-    last_active: &mut Instant,
-    retry_count: &mut usize,
+    terminal: &dyn TerminalBackend,
+    state: &mut PaneState,
 ) -> io::Result<()> {
+    let pane_id = config.tmux.pane.clone();
+    let pane = pane_id.as_str();
+    let mut current = config.clone();
     loop {
-        let text = capture(&config.tmux.pane);
+        // 新配置在一轮检查开始时整体生效，计时和恢复进度不受影响
+        if let Some(global) = state.updates.as_mut().and_then(ConfigUpdates::take) {
+            current = pane_config(&global, terminal, pane).await;
+            println!(
+                "🔧 [{}] 已应用新配置 (后端: {}, 检查间隔: {} 秒, 卡住判定: {} 秒)",
+                pane, current.llm.backend, current.monitoring.interval, current.monitoring.stuck_sec
+            );
+        }
+        let config = &current;
         
-        // 新增：基于内容变化的活动检测 - 优化版本，使用线程安全的方式
-        let has_content_changed = {
-            use std::sync::Mutex;
-            static LAST_CONTENT: OnceLock<Mutex<String>> = OnceLock::new();
-            
-            let content_mutex = LAST_CONTENT.get_or_init(|| Mutex::new(String::new()));
-            
-            if let Ok(mut last_content) = content_mutex.lock() {
-                if last_content.is_empty() {
-                    // 第一次运行，有内容就认为有变化
-                    last_content.clone_from(&text);
-                    true
-                } else {
-                    // 智能内容变化检测：忽略纯时间变化和系统信息变化
-                    let changed = has_substantial_content_change(&text, &last_content);
-                    if changed {
-                        last_content.clone_from(&text);
-                    }
-                    changed
+        if state.shutdown.is_triggered() {
+            println!("🛑 [{}] 收到退出信号，停止监控", pane);
+            return Ok(());
+        }
+        
+        let text = match terminal.capture(pane).await {
+            Ok(text) => text,
+            Err(e) => {
+                if !terminal.pane_exists(pane).await {
+                    println!("👋 [{}] tmux pane 已关闭，停止监控", pane);
+                    return Ok(());
                 }
-            } else {
-                true // 如果获取锁失败，认为有变化
+                eprintln!("⚠️ 无法捕获 tmux pane {}: {}", pane, e);
+                state.shutdown.sleep(Duration::from_secs(config.monitoring.interval)).await;
+                continue;
             }
         };
         
+        // 权限确认对话框不需要等到卡住超时，出现后立即按策略处理
+        if let Some(prompt) = parse_permission_prompt(&text) {
+            handle_permission_prompt(config, terminal, state, &prompt).await;
+            state.shutdown.sleep(Duration::from_secs(config.monitoring.interval)).await;
+            continue;
+        }
+        state.last_permission_prompt = None;
+        
+        // 用量限制期间等待重置，到时间后发送继续消息
+        if let Some(until) = state.rate_limited_until {
+            if Local::now() >= until {
+                resume_after_rate_limit(config, terminal, state).await;
+            }
+            state.shutdown.sleep(Duration::from_secs(config.monitoring.interval)).await;
+            continue;
+        }
+        match parse_usage_limit(&text, Local::now()) {
+            Some(limit) if state.resumed_rate_limit.as_deref() != Some(limit.message.as_str()) => {
+                enter_rate_limit(config, state, Some(&limit));
+                state.shutdown.sleep(Duration::from_secs(config.monitoring.interval)).await;
+                continue;
+            }
+            Some(_) => {}
+            None => state.resumed_rate_limit = None,
+        }
+        
+        // 基于内容变化的活动检测，状态保存在本窗格的 PaneState 中
+        let has_content_changed = state.content_changed(&text);
+        
         // 检查 Claude Code 是否仍在活动
         if is_claude_active(&text) || has_content_changed {
             // Claude Code 仍在活动或有实质性进展
-            *last_active = Instant::now();
-            *retry_count = 0;
+            state.last_active = Instant::now();
+            state.reset_recovery();
+            state.last_state = Some(detect_screen_state(&text).unwrap_or(ClaudeState::Working));
             if has_content_changed {
-                println!("🔄 检测到内容变化，Claude Code 正在工作中...");
+                println!("🔄 [{}] 检测到内容变化，Claude Code 正在工作中...", pane);
             } else {
-                println!("🔄 Claude Code 正在工作中...");
+                println!("🔄 [{}] Claude Code 正在工作中...", pane);
             }
         } else {
             // Claude Code 不活动，检查是否超时
-            if last_active.elapsed() >= Duration::from_secs(config.monitoring.stuck_sec) {
-                println!("⏸️ Claude Code 停止工作超过 {} 秒，调用 LLM 判断状态...", config.monitoring.stuck_sec);
+            if state.last_active.elapsed() >= Duration::from_secs(config.monitoring.stuck_sec) {
+                println!("⏸️ [{}] Claude Code 停止工作超过 {} 秒，调用 LLM 判断状态...", pane, config.monitoring.stuck_sec);
                 
                 // 关键改进：检查时间是否在递增，这是最可靠的活动指示
-                if is_time_increasing(&text, &config.tmux.pane) {
+                if state.time_increasing(&text) {
                     println!("🔄 检测到时间在递增，Claude Code 正在工作中，跳过 LLM 调用...");
-                    *last_active = Instant::now();
-                    thread::sleep(Duration::from_secs(config.monitoring.interval));
+                    state.last_active = Instant::now();
+                    state.shutdown.sleep(Duration::from_secs(config.monitoring.interval)).await;
                     continue;
                 }
                 
@@ -186,169 +459,200 @@ pub async fn run_monitoring_loop(
                 if should_skip_llm {
                     println!("🔄 检测到可能仍在处理的状态，跳过 LLM 调用，继续观察...");
                     // 重置计时器，给予更多时间
-                    *last_active = Instant::now();
-                    thread::sleep(Duration::from_secs(config.monitoring.interval));
+                    state.last_active = Instant::now();
+                    state.shutdown.sleep(Duration::from_secs(config.monitoring.interval)).await;
                     continue;
                 }
                 
-                // 优先进行启发式完成检查，避免不必要的LLM调用
-                let final_status = crate::llm::simple_heuristic_check(&text);
-                if final_status == crate::llm::TaskStatus::Done {
-                    println!("✅ 启发式检查确认任务已完成，进入完成状态监控...");
-                    // 进入完成状态监控循环
-                    if monitor_completion_state(&config.tmux.pane).is_err() {
-                        println!("⚠️ 完成状态监控中断，重新开始正常监控");
+                // 优先使用启发式检查识别状态，只有得出卡住/出错时才调用 LLM 做最终判断
+                let heuristic_state = simple_heuristic_check(&text);
+                let claude_state = if heuristic_state.needs_recovery() {
+                    let verdict = ask_llm_final_status(&state.prompt_context(config, &text), config).await;
+                    println!("🤖 [{}] LLM 判断状态: {}", pane, verdict);
+                    if let Some(action) = &verdict.suggested_action {
+                        println!("💡 [{}] LLM 建议: {}", pane, action);
                     }
-                    continue;
-                }
+                    let llm_state = verdict.state;
+                    state.last_verdict = Some(verdict);
+                    llm_state
+                } else {
+                    println!("🔍 [{}] 启发式检查识别状态: {}", pane, heuristic_state);
+                    heuristic_state
+                };
+                state.last_state = Some(claude_state);
                 
-                // 如果启发式检查无法确定，再使用LLM进行最终判断
-                match ask_llm_final_status(&text, &config.llm.backend, config).await {
-                    Ok(TaskStatus::Done) => {
-                        println!("✅ LLM 确认任务已完成，进入完成状态监控...");
-                        // 进入完成状态监控循环
-                        if monitor_completion_state(&config.tmux.pane).is_err() {
+                match claude_state {
+                    ClaudeState::Working | ClaudeState::Thinking => {
+                        println!("🔄 [{}] Claude Code 仍在处理中 ({})，继续观察...", pane, claude_state);
+                        state.last_active = Instant::now();
+                        state.reset_recovery();
+                    }
+                    ClaudeState::Done => {
+                        println!("✅ [{}] 任务已完成，进入完成状态监控...", pane);
+                        let interval = Duration::from_secs(config.monitoring.interval);
+                        if monitor_completion_state(terminal, pane, interval, &state.shutdown).await.is_err() {
                             println!("⚠️ 完成状态监控中断，重新开始正常监控");
                         }
                     }
-                    Ok(TaskStatus::Stuck) => {
-                        println!("⚠️ LLM 确认任务卡住");
-                        if *retry_count < config.monitoring.max_retry {
-                            // 首先尝试智能激活：让LLM直接对终端说话
-                            println!("尝试智能激活：让LLM直接对终端说话...");
-                            match attempt_llm_activation(config, &config.tmux.pane).await {
-                                Ok(true) => {
-                                    println!("✅ LLM智能激活成功，Claude恢复响应");
-                                    *last_active = Instant::now();
-                                    *retry_count = 0; // 重置重试计数
-                                },
-                                Ok(false) => {
-                                    println!("⚠️ LLM智能激活无效，尝试传统Retry命令");
-                                    // 如果智能激活无效，再尝试传统Retry
-                                    println!("重试 {}/{}", *retry_count + 1, config.monitoring.max_retry);
-                                    send_keys("Retry", &config.tmux.pane);
-                                    *retry_count += 1;
-                                    
-                                    // 发送Retry后，等待一段时间让Claude响应
-                                    println!("等待 {} 秒让 Claude 响应 Retry 命令...", config.monitoring.stuck_sec);
-                                    thread::sleep(Duration::from_secs(config.monitoring.stuck_sec));
-                                    
-                                    // 检查Retry是否有效 - 严格判断是否有实质性进展
-                                    let response_text = capture(&config.tmux.pane);
-                                    if has_substantial_progress(&response_text) {
-                                        println!("✅ Retry 命令有效，Claude 有实质性进展");
-                                        *last_active = Instant::now();
-                                    } else {
-                                        println!("⚠️ Retry 命令无效或只有读秒变化，仍然认为卡住");
-                                        // 不重置计时器，让系统继续判断，下次会再次进入卡住检测
-                                    }
-                                },
-                                Err(e) => {
-                                    println!("⚠️ LLM智能激活失败: {}，尝试传统Retry命令", e);
-                                    // 如果LLM激活失败，回退到传统Retry
-                                    println!("重试 {}/{}", *retry_count + 1, config.monitoring.max_retry);
-                                    send_keys("Retry", &config.tmux.pane);
-                                    *retry_count += 1;
-                                    
-                                    thread::sleep(Duration::from_secs(config.monitoring.stuck_sec));
-                                    
-                                    let response_text = capture(&config.tmux.pane);
-                                    if has_substantial_progress(&response_text) {
-                                        println!("✅ Retry 命令有效，Claude 有实质性进展");
-                                        *last_active = Instant::now();
-                                    } else {
-                                        println!("⚠️ Retry 命令无效或只有读秒变化，仍然认为卡住");
-                                    }
-                                }
-                            }
-                        } else {
-                            // 达到最大重试次数，启用高级恢复策略
-                            println!("达到最大重试次数，启用高级恢复策略...");
-                            
-                            // 尝试高级解决方案
-                            let advanced_solutions = vec![
-                                ("请继续你的工作", "LLM温柔提醒"),
-                                ("你好，看起来你可能卡住了，请继续处理任务", "LLM明确提醒"),
-                                ("/compact", "发送 /compact 命令"),
-                                ("Escaping", "发送 Escaping 命令"),
-                                ("Ctrl+C", "发送 Ctrl+C 中断当前操作"),
-                            ];
-                            
-                            let mut solution_found = false;
-                            for (command, description) in advanced_solutions {
-                                println!("尝试高级解决方案: {}", description);
-                                send_keys(command, &config.tmux.pane);
-                                
-                                // 等待响应
-                                thread::sleep(Duration::from_secs(config.monitoring.stuck_sec));
-                                
-                                let solution_text = capture(&config.tmux.pane);
-                                if has_substantial_progress(&solution_text) {
-                                    println!("✅ 高级解决方案有效: {}", description);
-                                    *last_active = Instant::now();
-                                    solution_found = true;
-                                    break;
-                                } else {
-                                    println!("⚠️ 高级解决方案无效: {}", description);
-                                }
-                            }
-                            
-                            if !solution_found {
-                                println!("❌ 所有高级解决方案都无效，进入守护模式");
-                                println!("💡 建议：可能需要手动干预或重启Claude Code");
-                                // 进入守护模式，不重置计时器，避免无限循环
-                            }
-                            
-                            *retry_count = 0; // 重置重试计数，但只在有实质性进展时重置计时器
+                    ClaudeState::Interrupted => {
+                        println!("✋ [{}] Claude Code 被用户中断，不自动恢复，等待画面变化...", pane);
+                        let interval = Duration::from_secs(config.monitoring.interval);
+                        if monitor_completion_state(terminal, pane, interval, &state.shutdown).await.is_err() {
+                            println!("⚠️ 完成状态监控中断，重新开始正常监控");
                         }
                     }
-                    Err(e) => {
-                        eprintln!("⚠️ 状态判断失败: {}，等待下次检查", e);
-                        // 等待更长时间再重试
-                        thread::sleep(Duration::from_secs(config.monitoring.stuck_sec));
+                    ClaudeState::WaitingForPermission => {
+                        println!("🔐 [{}] Claude Code 正在等待权限确认，不发送恢复命令，需要人工处理", pane);
+                        state.last_active = Instant::now();
+                    }
+                    ClaudeState::AskingUserQuestion => {
+                        println!("❓ [{}] Claude Code 正在等待用户回答问题，不发送恢复命令", pane);
+                        state.last_active = Instant::now();
+                    }
+                    ClaudeState::RateLimited => {
+                        enter_rate_limit(config, state, None);
+                    }
+                    ClaudeState::ContextFull if state.compact_attempts >= MAX_COMPACT_ATTEMPTS => {
+                        println!(
+                            "📦 [{}] 已发送 {} 次 /compact 上下文仍然已满，不再发送，需要人工处理",
+                            pane, state.compact_attempts
+                        );
+                        state.last_active = Instant::now();
+                    }
+                    ClaudeState::ContextFull => {
+                        state.compact_attempts += 1;
+                        println!(
+                            "📦 [{}] Claude Code 上下文已满，发送 /compact 压缩上下文 ({}/{})",
+                            pane, state.compact_attempts, MAX_COMPACT_ATTEMPTS
+                        );
+                        send_to_pane(terminal, "/compact", pane).await;
+                        state.shutdown.sleep(Duration::from_secs(config.monitoring.stuck_sec)).await;
+                        let response_text = terminal.capture(pane).await.unwrap_or_default();
+                        if has_substantial_progress(&response_text) {
+                            state.last_active = Instant::now();
+                        }
+                    }
+                    ClaudeState::Stuck | ClaudeState::Errored => {
+                        println!("⚠️ [{}] 确认任务卡住 ({})", pane, claude_state);
+                        run_recovery_step(config, terminal, state).await;
                     }
                 }
             } else {
-                let wait_time = config.monitoring.stuck_sec - last_active.elapsed().as_secs();
-                println!("⏳ 等待 {} 秒后判断 Claude Code 状态...", wait_time);
+                let wait_time = config.monitoring.stuck_sec - state.last_active.elapsed().as_secs();
+                println!("⏳ [{}] 等待 {} 秒后判断 Claude Code 状态...", pane, wait_time);
             }
         }
         
-        thread::sleep(Duration::from_secs(config.monitoring.interval));
+        state.shutdown.sleep(Duration::from_secs(config.monitoring.interval)).await;
+    }
+}
+
+/// 进入用量限制状态
+///
+/// 无法得知重置时间时按 `rate_limit.fallback_wait` 等待
+fn enter_rate_limit(config: &Config, state: &mut PaneState, limit: Option<&UsageLimit>) {
+    let pane = config.tmux.pane.as_str();
+    let resets_at = limit
+        .and_then(|limit| limit.resets_at)
+        .unwrap_or_else(|| Local::now() + chrono::Duration::seconds(config.rate_limit.fallback_wait as i64));
+    let until = resets_at + chrono::Duration::seconds(config.rate_limit.resume_delay as i64);
+    
+    println!(
+        "⏳ [{}] Claude Code 触发用量限制，暂停所有恢复操作，将在 {} 发送 \"{}\"",
+        pane,
+        until.format("%Y-%m-%d %H:%M:%S"),
+        config.rate_limit.resume_message
+    );
+    state.rate_limited_until = Some(until);
+    state.rate_limit_message = limit.map(|limit| limit.message.clone());
+    state.last_state = Some(ClaudeState::RateLimited);
+    state.last_active = Instant::now();
+    state.reset_recovery();
+}
+
+/// 用量限制重置后发送继续消息，并恢复正常监控
+async fn resume_after_rate_limit(config: &Config, terminal: &dyn TerminalBackend, state: &mut PaneState) {
+    let pane = config.tmux.pane.as_str();
+    println!("▶️ [{}] 用量限制已重置，发送 \"{}\" 继续任务", pane, config.rate_limit.resume_message);
+    send_to_pane(terminal, &config.rate_limit.resume_message, pane).await;
+    
+    state.rate_limited_until = None;
+    state.resumed_rate_limit = state.rate_limit_message.take();
+    state.last_state = None;
+    state.last_active = Instant::now();
+    state.reset_recovery();
+}
+
+/// 按权限策略处理对话框
+///
+/// 同一个对话框只处理一次；对话框存在期间不会触发任何卡住恢复
+async fn handle_permission_prompt(
+    config: &Config,
+    terminal: &dyn TerminalBackend,
+    state: &mut PaneState,
+    prompt: &PermissionPrompt,
+) {
+    let pane = config.tmux.pane.as_str();
+    let subject = prompt.subject();
+    state.last_active = Instant::now();
+    state.last_state = Some(ClaudeState::WaitingForPermission);
+    
+    if state.last_permission_prompt.as_deref() == Some(subject.as_str()) {
+        return;
+    }
+    state.last_permission_prompt = Some(subject.clone());
+    
+    let decision = permission::decide(prompt, &config.permissions);
+    match &decision {
+        PermissionDecision::Allow(rule) => println!("✅ [{}] 自动批准权限请求 {} (规则: {})", pane, subject, rule),
+        PermissionDecision::Deny(rule) => println!("⛔ [{}] 自动拒绝权限请求 {} (规则: {})", pane, subject, rule),
+        PermissionDecision::Ask => {
+            println!("🔔 [{}] Claude Code 请求权限: {} — {}，等待人工处理", pane, subject, prompt.question);
+            return;
+        }
+    }
+    
+    if let Err(e) = permission::answer(terminal, pane, prompt, &decision).await {
+        println!("❌ [{}] 应答权限对话框失败: {}", pane, e);
+        // 允许下一轮重新尝试
+        state.last_permission_prompt = None;
     }
 }
 
 /// 监控完成状态
 /// 
-/// 在 LLM 确认任务完成后，进入守护模式监控：
-/// 持续检查画面是否有变化，如果有变化说明 Claude Code 可能开始新任务
-/// 这是守护进程模式的核心功能
-fn monitor_completion_state(pane: &str) -> Result<(), String> {
-    let mut last_hash = 0u64;
+/// 在 LLM 确认任务完成（或用户中断）后，进入守护模式监控：
+/// 记下当前画面，每隔 `interval` 检查一次，画面变化说明 Claude Code 可能开始新任务，此时返回
+/// 如果窗格无法再被捕获（例如已关闭），返回错误；收到退出通知时直接返回
+pub async fn monitor_completion_state(
+    terminal: &dyn TerminalBackend,
+    pane: &str,
+    interval: Duration,
+    shutdown: &Shutdown,
+) -> Result<(), String> {
+    let last_hash = seahash::hash(terminal.capture(pane).await?.as_bytes());
     let mut check_count = 0usize;
     
     println!("🔄 进入完成状态监控模式...");
     
     loop {
-        let text = capture(pane);
-        let hash = seahash::hash(text.as_bytes());
-        
-        if hash != last_hash {
+        if !shutdown.sleep(interval).await {
+            return Ok(());
+        }
+
+        let text = terminal.capture(pane).await?;
+        if seahash::hash(text.as_bytes()) != last_hash {
             // 画面发生变化，说明 Claude Code 可能开始了新任务
             println!("🔍 检测到画面变化，Claude Code 可能开始新任务");
             return Ok(());
         }
         
-        last_hash = hash;
         check_count += 1;
         
         // 每检查 10 次报告一次状态
-        if check_count % 10 == 0 {
+        if check_count.is_multiple_of(10) {
             println!("💤 仍在完成状态，持续监控中... (检查次数: {})", check_count);
         }
-        
-        // 睡眠 120 秒（2 分钟）- 避免频繁检查
-        thread::sleep(Duration::from_secs(120));
     }
 }
 
@@ -459,62 +763,23 @@ pub fn check_if_should_skip_llm_call(text: &str) -> bool {
     false
 }
 
-/// 使用LLM智能激活卡住的Claude Code
-/// 
-/// 这是核心的智能激活功能，当Claude Code卡住时，
-/// 直接调用LLM让它对终端说话，从而激活Claude Code
-async fn attempt_llm_activation(config: &Config, pane: &str) -> Result<bool, String> {
+/// 调用LLM生成一句激活消息
+///
+/// 提示词来自 `prompts.activation` 模板，包含屏幕末尾、检测到的状态和之前无效的尝试，
+/// 让 LLM 针对具体情况给出提示
+pub async fn generate_activation_message(config: &Config, context: &PromptContext) -> Result<String, String> {
     println!("🤖 调用LLM生成激活消息...");
-    
-    // 构建激活prompt
-    let activation_prompt = r#"Claude Code在处理任务时似乎卡住了，需要你生成一句简短而有效的话来激活它。
-
-具体场景：
-- Claude Code可能在深度思考、执行工具调用或处理复杂任务时暂时停止响应
-- 需要一句自然的、友好的提醒来让它重新开始工作
-- 应该像是用户在和Claude Code对话，而不是机械的命令
-
-要求：
-1. 生成一句简短、自然、友好的话（10-20个字为佳）
-2. 语气要温和，像是在和AI助手对话
-3. 内容应该是提醒或询问，让Claude意识到需要继续工作
-4. 避免使用"卡住"、"错误"、"问题"等负面词汇
-5. 不要包含特殊命令符号（如/、\、#等）
-
-示例：
-"请继续处理任务"
-"你好，请继续工作"
-"看起来可以继续了"
-"请继续你的工作"
-
-请只返回要说的话，不要任何解释或其他内容。"#;
-    
-    // 调用LLM生成激活消息
-    match crate::llm::ask_llm_for_activation(activation_prompt, &config.llm.backend, config) {
-        Ok(activation_msg) => {
-            let activation_message = activation_msg;
-            println!("🤖 LLM生成激活消息: {}", activation_message);
-            
-            // 发送激活消息到终端
-            send_keys(&activation_message, pane);
-            
-            // 等待Claude响应
-            println!("⏳ 等待Claude对激活消息的响应...");
-            thread::sleep(Duration::from_secs(config.monitoring.stuck_sec));
-            
-            // 检查激活是否有效
-            let response_text = capture(pane);
-            if has_substantial_progress(&response_text) {
-                println!("✅ LLM激活成功！Claude有实质性进展");
-                Ok(true)
-            } else {
-                println!("⚠️ LLM激活后无实质性进展");
-                Ok(false)
-            }
-        },
-        Err(e) => {
-            Err(format!("LLM调用失败: {}", e))
-        }
+    crate::llm::ask_llm_for_activation(context, config)
+        .await
+        .map_err(|e| format!("LLM调用失败: {}", e))
+}
+
+/// 发送文本到窗格，发送失败只记录日志
+///
+/// 恢复流程中的发送失败不应中断监控，下一轮检查会重新评估状态
+async fn send_to_pane(terminal: &dyn TerminalBackend, text: &str, pane: &str) {
+    if let Err(e) = terminal.send_text(text, pane).await {
+        println!("❌ 发送到 tmux pane {} 失败: {}", pane, e);
     }
 }
 
@@ -668,7 +933,7 @@ pub fn extract_core_content(text: &str) -> String {
     }
     
     // 4. 标准化空白字符 - 移除多余的空格、换行等
-    processed = regex::Regex::new(r"\s+").unwrap().replace_all(&processed.trim(), " ").to_string();
+    processed = regex::Regex::new(r"\s+").unwrap().replace_all(processed.trim(), " ").to_string();
     
     // 5. 移除常见的状态指示符变化 - 这些会频繁变化但不代表实质性活动
     for pattern in &*STATUS_PATTERNS {
@@ -677,7 +942,67 @@ pub fn extract_core_content(text: &str) -> String {
     
     // 最终清理：移除连续的替换标记和多余空格
     processed = regex::Regex::new(r"\[STATUS\]\s*\[STATUS\]\s*").unwrap().replace_all(&processed, "[STATUS] ").to_string();
-    processed = regex::Regex::new(r"\s+").unwrap().replace_all(&processed.trim(), " ").to_string();
+    processed = regex::Regex::new(r"\s+").unwrap().replace_all(processed.trim(), " ").to_string();
     
     processed.trim().to_string()
-}
\ No newline at end of file
+}
+
+/// 去掉 Claude Code 界面装饰，只保留对判断有用的文本
+///
+/// 1. 删除欢迎横幅（包含 "Welcome to Claude Code" 的框）
+/// 2. 删除框线，框内的文字（输入框、权限对话框）去掉边框后保留
+/// 3. 删除 `SYSTEM_INFO_PATTERNS` 匹配的快捷键提示等系统信息
+/// 4. 合并连续的空行
+pub fn strip_ui_chrome(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    let mut boxed: Option<Vec<&str>> = None;
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.trim_start().starts_with('╭') {
+            if let Some(unclosed) = boxed.take() {
+                lines.extend(unclosed);
+            }
+            boxed = Some(Vec::new());
+        } else if line.trim_start().starts_with('╰') {
+            if let Some(content) = boxed.take() {
+                if !content.iter().any(|line| line.contains("Welcome to Claude Code")) {
+                    lines.extend(content);
+                }
+            }
+        } else if let Some(content) = boxed.as_mut() {
+            content.push(line);
+        } else {
+            lines.push(line);
+        }
+    }
+    if let Some(unclosed) = boxed {
+        lines.extend(unclosed);
+    }
+
+    let mut result: Vec<String> = Vec::new();
+    for line in lines {
+        if BORDER_LINE.is_match(line) {
+            continue;
+        }
+        let inner = match line.trim().strip_prefix('│').and_then(|rest| rest.strip_suffix('│')) {
+            Some(inner) => inner.trim_end(),
+            None => line,
+        };
+        let cleaned = SYSTEM_INFO_PATTERNS
+            .iter()
+            .fold(inner.to_string(), |acc, pattern| pattern.replace_all(&acc, "").into_owned());
+        let cleaned = cleaned.trim_end();
+        if cleaned.trim().is_empty() {
+            if !inner.trim().is_empty() || result.last().is_none_or(|last| last.is_empty()) {
+                continue;
+            }
+            result.push(String::new());
+        } else {
+            result.push(cleaned.to_string());
+        }
+    }
+    while result.last().is_some_and(|last| last.is_empty()) {
+        result.pop();
+    }
+    result.join("\n")
+}
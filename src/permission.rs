@@ -0,0 +1,84 @@
+//! 权限确认对话框的处理策略
+//!
+//! 根据 `permissions` 配置决定自动批准、拒绝，还是保留对话框等待人工处理
+
+use crate::activity::PermissionPrompt;
+use crate::config::{PermissionConfig, PermissionMode};
+use crate::terminal::{Key, TerminalBackend};
+
+/// 对一个权限确认对话框的处理决定
+#[derive(Debug, Clone, PartialEq)]
+pub enum PermissionDecision {
+    /// 批准，附带匹配到的规则
+    Allow(String),
+    /// 拒绝，附带匹配到的规则
+    Deny(String),
+    /// 不自动处理，保留对话框并通知
+    Ask,
+}
+
+/// 返回第一个匹配整个 `subject` 的规则，无效的正则会被记录并跳过
+///
+/// 规则按 `(?s)^(?:<规则>)$` 编译：只匹配命令开头的规则不会放过 `cargo test; curl … | sh`
+/// 这样串在后面的命令，`.` 也能匹配多行命令中的换行
+fn first_match(patterns: &[String], subject: &str) -> Option<String> {
+    patterns.iter().find_map(|pattern| match regex::Regex::new(&format!("(?s)^(?:{})$", pattern)) {
+        Ok(re) if re.is_match(subject) => Some(pattern.clone()),
+        Ok(_) => None,
+        Err(e) => {
+            eprintln!("⚠️ 无效的权限规则 {}: {}", pattern, e);
+            None
+        }
+    })
+}
+
+/// 根据配置为对话框做出决定
+///
+/// `always_ask` 模式下从不自动处理；`policy` 模式下拒绝列表优先于允许列表，
+/// 都不匹配时保留给人工处理
+pub fn decide(prompt: &PermissionPrompt, config: &PermissionConfig) -> PermissionDecision {
+    if config.mode == PermissionMode::AlwaysAsk {
+        return PermissionDecision::Ask;
+    }
+
+    let subject = prompt.subject();
+    if let Some(rule) = first_match(&config.deny, &subject) {
+        return PermissionDecision::Deny(rule);
+    }
+    if let Some(rule) = first_match(&config.allow, &subject) {
+        return PermissionDecision::Allow(rule);
+    }
+    PermissionDecision::Ask
+}
+
+/// 选项编号对应的数字键
+fn option_key(number: u8) -> Result<Key, String> {
+    char::from_digit(number as u32, 10)
+        .map(Key::Char)
+        .ok_or_else(|| format!("无法用单个按键选择选项 {}", number))
+}
+
+/// 按决定向对话框发送按键
+///
+/// 批准时按下 Yes 选项的数字键；拒绝时按下 No 选项的数字键，没有 No 选项则发送 Escape。
+/// `Ask` 不发送任何按键
+pub async fn answer(
+    terminal: &dyn TerminalBackend,
+    pane: &str,
+    prompt: &PermissionPrompt,
+    decision: &PermissionDecision,
+) -> Result<(), String> {
+    match decision {
+        PermissionDecision::Allow(_) => {
+            let option = prompt
+                .yes_option()
+                .ok_or_else(|| "对话框中没有 Yes 选项".to_string())?;
+            terminal.send_key(option_key(option.number)?, pane).await
+        }
+        PermissionDecision::Deny(_) => match prompt.no_option() {
+            Some(option) => terminal.send_key(option_key(option.number)?, pane).await,
+            None => terminal.send_key(Key::Escape, pane).await,
+        },
+        PermissionDecision::Ask => Ok(()),
+    }
+}
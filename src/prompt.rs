@@ -0,0 +1,167 @@
+//! 状态判断和激活消息的提示词模板
+//!
+//! 模板中的 `{{变量}}` 在每次调用 LLM 前替换。未配置模板文件时使用内置模板，
+//! 按 `prompts.language` 选择中文或英文版本
+
+use crate::config::{Config, Language, PromptConfig};
+use crate::monitor::strip_ui_chrome;
+use crate::state::ClaudeState;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::fs;
+
+const JUDGE_ZH: &str = include_str!("../prompts/judge.zh.md");
+const JUDGE_EN: &str = include_str!("../prompts/judge.en.md");
+const ACTIVATION_ZH: &str = include_str!("../prompts/activation.zh.md");
+const ACTIVATION_EN: &str = include_str!("../prompts/activation.en.md");
+
+/// `{{screen_tail}}` 保留的屏幕末尾行数
+pub const SCREEN_TAIL_LINES: usize = 30;
+
+/// `{{screen_tail}}` 最多保留的字符数
+pub const SCREEN_TAIL_MAX_CHARS: usize = 3000;
+
+lazy_static! {
+    /// `{{ name }}`，两侧允许空格
+    static ref VARIABLE: Regex = Regex::new(r"\{\{\s*(\w+)\s*\}\}").unwrap();
+}
+
+/// 渲染模板时可用的变量
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PromptContext {
+    /// 当前屏幕内容：`{{screen}}`
+    pub screen: String,
+    /// 窗格 ID：`{{pane}}`
+    pub pane: String,
+    /// 画面没有变化的秒数：`{{idle_seconds}}`
+    pub idle_seconds: u64,
+    /// 上一次执行的恢复操作：`{{last_action}}`
+    pub last_action: Option<String>,
+    /// 会话的任务目标：`{{task_goal}}`
+    pub task_goal: Option<String>,
+    /// 检测到的状态：`{{state}}`
+    pub state: Option<ClaudeState>,
+    /// 之前没有奏效的恢复操作，从早到晚：`{{failed_attempts}}`
+    pub failed_attempts: Vec<String>,
+}
+
+impl PromptContext {
+    pub fn new(pane: &str, screen: &str) -> Self {
+        PromptContext {
+            screen: screen.to_string(),
+            pane: pane.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// 使用配置中的窗格和任务目标
+    pub fn from_config(config: &Config, screen: &str) -> Self {
+        PromptContext {
+            task_goal: config.prompts.task_goal.clone(),
+            ..Self::new(&config.tmux.pane, screen)
+        }
+    }
+}
+
+/// 替换模板中的变量，未知变量保持原样
+pub fn render(template: &str, context: &PromptContext, language: Language) -> String {
+    let missing = match language {
+        Language::Zh => ("无", "未指定", "未知"),
+        Language::En => ("none", "not specified", "unknown"),
+    };
+    VARIABLE
+        .replace_all(template, |caps: &Captures| match &caps[1] {
+            "screen" => context.screen.clone(),
+            "pane" => context.pane.clone(),
+            "idle_seconds" => context.idle_seconds.to_string(),
+            "last_action" => context.last_action.clone().unwrap_or_else(|| missing.0.to_string()),
+            "task_goal" => context.task_goal.clone().unwrap_or_else(|| missing.1.to_string()),
+            "screen_tail" => prepare_screen(&context.screen, SCREEN_TAIL_LINES, SCREEN_TAIL_MAX_CHARS),
+            "state" => context.state.map_or_else(|| missing.2.to_string(), |state| state.to_string()),
+            "failed_attempts" if context.failed_attempts.is_empty() => missing.0.to_string(),
+            "failed_attempts" => context
+                .failed_attempts
+                .iter()
+                .map(|attempt| format!("- {}", attempt))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+/// 发给 LLM 前整理屏幕内容
+///
+/// 去掉界面装饰后保留最后 `max_lines` 个非空行，再从前往后删除整行直到不超过 `max_chars` 个字符；
+/// 最后一行本身就超出时只保留它的末尾
+pub fn prepare_screen(screen: &str, max_lines: usize, max_chars: usize) -> String {
+    let stripped = strip_ui_chrome(screen);
+    let lines: Vec<&str> = stripped.lines().collect();
+
+    let mut start = lines.len();
+    let mut kept = 0;
+    while start > 0 && kept < max_lines {
+        start -= 1;
+        if !lines[start].trim().is_empty() {
+            kept += 1;
+        }
+    }
+    let mut lines = &lines[start..];
+    while lines.len() > 1 && lines.iter().map(|line| line.chars().count() + 1).sum::<usize>() - 1 > max_chars {
+        lines = &lines[1..];
+    }
+
+    let tail = lines.join("\n");
+    let chars = tail.chars().count();
+    if chars > max_chars {
+        tail.chars().skip(chars - max_chars).collect()
+    } else {
+        tail
+    }
+}
+
+/// 状态判断的系统提示词，屏幕内容另外作为用户消息发送
+///
+/// 用户消息中的画面按实际使用的后端的字符预算截断，系统提示词里不再重复一份：
+/// 自定义模板中的 `{{screen}}` 替换为指向用户消息的说明
+pub fn judge_prompt(config: &PromptConfig, context: &PromptContext) -> String {
+    let (builtin, screen) = match config.language {
+        Language::Zh => (JUDGE_ZH, "（屏幕内容见用户消息）"),
+        Language::En => (JUDGE_EN, "(the screen is in the user message)"),
+    };
+    let context = PromptContext {
+        screen: screen.to_string(),
+        ..context.clone()
+    };
+    render(&load_template(config.judge.as_deref(), builtin), &context, config.language)
+}
+
+/// 生成激活消息的用户提示词
+pub fn activation_prompt(config: &PromptConfig, context: &PromptContext) -> String {
+    let builtin = match config.language {
+        Language::Zh => ACTIVATION_ZH,
+        Language::En => ACTIVATION_EN,
+    };
+    render(&load_template(config.activation.as_deref(), builtin), context, config.language)
+}
+
+/// 生成激活消息时的系统提示词
+pub fn activation_system_prompt(language: Language) -> &'static str {
+    match language {
+        Language::Zh => "你是一个Claude Code激活助手。当Claude Code卡住时，你需要生成一句简短、有效的话来激活它。",
+        Language::En => "You are a Claude Code activation assistant. When Claude Code stalls, you write one short, effective message to get it going again.",
+    }
+}
+
+/// 读取模板文件，每次调用都重新读取以便修改立即生效
+///
+/// 读取失败时使用内置模板，不中断监控
+fn load_template(path: Option<&str>, builtin: &str) -> String {
+    match path {
+        Some(path) => fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("⚠️ 无法读取提示词模板 {}: {}，使用内置模板", path, e);
+            builtin.to_string()
+        }),
+        None => builtin.to_string(),
+    }
+}
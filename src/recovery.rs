@@ -0,0 +1,197 @@
+//! 卡住恢复的升级阶梯
+//!
+//! 每次检测到卡住时执行 `recovery.steps` 中的一个步骤并检查效果：
+//! 成功则重置阶梯，同一步骤失败 `attempts` 次后升级到下一步，全部失败后从头开始
+
+use crate::config::{Config, RecoveryAction, RecoveryStep, SuccessCheck};
+use crate::monitor::{generate_activation_message, has_substantial_content_change, has_substantial_progress, PaneState};
+use crate::activity::is_claude_active;
+use crate::prompt::PromptContext;
+use crate::terminal::TerminalBackend;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// 钩子命令没有设置 `timeout` 时的最长运行秒数
+const DEFAULT_HOOK_TIMEOUT: u64 = 60;
+
+/// 执行恢复阶梯中的当前步骤
+///
+/// 返回这一步是否让 Claude Code 恢复了工作
+pub async fn run_recovery_step(config: &Config, terminal: &dyn TerminalBackend, state: &mut PaneState) -> bool {
+    let pane = config.tmux.pane.as_str();
+    let steps = config.recovery_steps();
+    if steps.is_empty() {
+        return false;
+    }
+    if state.recovery_step >= steps.len() {
+        state.reset_recovery();
+    }
+    let step = &steps[state.recovery_step];
+
+    println!(
+        "🪜 [{}] 恢复步骤 {}/{}: {} (第 {}/{} 次)",
+        pane,
+        state.recovery_step + 1,
+        steps.len(),
+        step.describe(),
+        state.retry_count + 1,
+        step.attempts.max(1)
+    );
+
+    let before = terminal.capture(pane).await.unwrap_or_default();
+    let reason = state.last_verdict.as_ref().map(|verdict| verdict.reason.as_str());
+    let context = state.prompt_context(config, &before);
+    let action = match perform_step(config, terminal, step, &context, reason).await {
+        Ok(action) => action,
+        Err(e) => {
+            println!("⚠️ [{}] 恢复步骤执行失败: {}", pane, e);
+            step.describe()
+        }
+    };
+    state.last_action = Some(action.clone());
+
+    let wait = step.wait.unwrap_or(config.monitoring.stuck_sec);
+    println!("⏳ 等待 {} 秒检查恢复效果...", wait);
+    if !state.shutdown.sleep(Duration::from_secs(wait)).await {
+        return false;
+    }
+
+    let after = terminal.capture(pane).await.unwrap_or_default();
+    if step_succeeded(step.check, &before, &after) {
+        println!("✅ [{}] 恢复步骤有效: {}", pane, step.describe());
+        state.last_active = Instant::now();
+        state.reset_recovery();
+        return true;
+    }
+
+    println!("⚠️ [{}] 恢复步骤无效: {}", pane, step.describe());
+    state.record_failed_attempt(action);
+    state.retry_count += 1;
+    if state.retry_count >= step.attempts.max(1) {
+        state.retry_count = 0;
+        state.recovery_step += 1;
+        if state.recovery_step >= steps.len() {
+            println!("❌ [{}] 所有恢复步骤都无效，下次卡住时从第一步重新开始", pane);
+            state.recovery_step = 0;
+        }
+    }
+    false
+}
+
+/// 执行一个恢复动作
+///
+/// `context` 用于渲染激活消息模板，`reason` 是 LLM 判断卡住的依据，通知时一并输出。
+/// 返回实际执行的操作，LLM 激活消息包含发送的内容
+async fn perform_step(
+    config: &Config,
+    terminal: &dyn TerminalBackend,
+    step: &RecoveryStep,
+    context: &PromptContext,
+    reason: Option<&str>,
+) -> Result<String, String> {
+    let pane = config.tmux.pane.as_str();
+    match &step.action {
+        RecoveryAction::SendText { .. } | RecoveryAction::SendKey { .. } => deliver(terminal, pane, step).await,
+        RecoveryAction::LlmMessage { fallback } => match generate_activation_message(config, context).await {
+            Ok(message) => {
+                println!("🤖 LLM生成激活消息: {}", message);
+                terminal.send_text(&message, pane).await?;
+                Ok(format!("llm_message \"{}\"", message))
+            }
+            Err(e) => match fallback {
+                Some(text) => {
+                    println!("⚠️ LLM生成激活消息失败: {}，改为发送 \"{}\"", e, text);
+                    terminal.send_text(text, pane).await?;
+                    Ok(format!("send_text \"{}\"", text))
+                }
+                None => Err(e),
+            },
+        },
+        RecoveryAction::ShellHook { command, timeout } => {
+            let timeout = Duration::from_secs(timeout.unwrap_or(DEFAULT_HOOK_TIMEOUT));
+            run_shell_hook(command, pane, timeout).await.map(|_| step.describe())
+        }
+        RecoveryAction::Notify { message } => {
+            println!("🔔 [{}] {}", pane, message.replace("{pane}", pane));
+            if let Some(reason) = reason.filter(|reason| !reason.is_empty()) {
+                println!("   判断依据: {}", reason);
+            }
+            Ok(step.describe())
+        }
+    }
+}
+
+/// 把 `send_text` / `send_key` 步骤发送到窗格，返回执行的操作
+///
+/// 恢复阶梯和 `send` 子命令都通过这里发送输入，其余动作返回错误
+pub async fn deliver(terminal: &dyn TerminalBackend, pane: &str, step: &RecoveryStep) -> Result<String, String> {
+    match &step.action {
+        RecoveryAction::SendText { text } => terminal.send_text(text, pane).await.map(|_| step.describe()),
+        RecoveryAction::SendKey { key } => terminal.send_key(*key, pane).await.map(|_| step.describe()),
+        _ => Err(format!("{} 不是发送输入的动作", step.describe())),
+    }
+}
+
+/// 通过 `sh -c` 运行外部钩子命令，超过 `timeout` 没有结束时终止它
+///
+/// 钩子在自己的进程组中运行，超时后整组终止，钩子启动的子进程（如 `sleep 100; foo` 或管道）不会留下来
+async fn run_shell_hook(command: &str, pane: &str, timeout: Duration) -> Result<(), String> {
+    println!("🪝 [{}] 运行钩子: {}", pane, command);
+    let mut hook = Command::new("sh");
+    hook.arg("-c")
+        .arg(command)
+        .env("CLAUDE_WATCH_PANE", pane)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    hook.process_group(0);
+    let child = hook.spawn().map_err(|e| format!("无法运行钩子: {}", e))?;
+    let group = child.id();
+
+    let output = match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(output) => output.map_err(|e| format!("无法运行钩子: {}", e))?,
+        Err(_) => {
+            kill_process_group(group);
+            return Err(format!("钩子运行超过 {} 秒，已终止", timeout.as_secs()));
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !stdout.trim().is_empty() {
+        println!("{}", stdout.trim_end());
+    }
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "钩子退出状态 {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// 终止钩子的进程组；`sh` 本身由 `kill_on_drop` 终止
+#[cfg(unix)]
+fn kill_process_group(group: Option<u32>) {
+    if let Some(group) = group {
+        // 进程组 ID 等于钩子 sh 进程的 PID（`process_group(0)`）
+        unsafe {
+            libc::killpg(group as libc::pid_t, libc::SIGKILL);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(_group: Option<u32>) {}
+
+/// 根据检查方式判断步骤是否有效
+pub fn step_succeeded(check: SuccessCheck, before: &str, after: &str) -> bool {
+    match check {
+        SuccessCheck::Progress => has_substantial_progress(after),
+        SuccessCheck::Changed => has_substantial_content_change(after, before),
+        SuccessCheck::Active => is_claude_active(after),
+        SuccessCheck::None => false,
+    }
+}
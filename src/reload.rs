@@ -0,0 +1,121 @@
+//! 配置热重载
+//!
+//! 主循环轮询配置文件的修改时间，发现变化（或收到 SIGHUP）后重新解析并校验配置，
+//! 通过 `ConfigUpdates` 把新配置交给正在运行的监控任务。每个任务在下一轮检查开始时
+//! 整体切换到新配置，不会出现一半新一半旧的情况；`PaneState` 中的计时和恢复进度保持不变
+
+use crate::config::Config;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::watch;
+
+/// 发布新配置的一端
+#[derive(Debug)]
+pub struct ConfigPublisher {
+    sender: watch::Sender<Arc<Config>>,
+}
+
+/// 监控任务持有的配置更新通知
+#[derive(Debug, Clone)]
+pub struct ConfigUpdates {
+    receiver: watch::Receiver<Arc<Config>>,
+}
+
+/// 以 `config` 为初始配置创建一对配置通知
+pub fn channel(config: Config) -> (ConfigPublisher, ConfigUpdates) {
+    let (sender, receiver) = watch::channel(Arc::new(config));
+    (ConfigPublisher { sender }, ConfigUpdates { receiver })
+}
+
+impl ConfigPublisher {
+    /// 把新配置交给所有持有对应 `ConfigUpdates` 的任务
+    pub fn publish(&self, config: Config) {
+        self.sender.send_replace(Arc::new(config));
+    }
+
+    /// 最近发布的配置
+    pub fn current(&self) -> Arc<Config> {
+        self.sender.borrow().clone()
+    }
+}
+
+impl ConfigUpdates {
+    /// 永远不会更新的配置，用于不需要热重载的场景（例如 `--once` 和测试）
+    pub fn fixed(config: Config) -> Self {
+        let (_, updates) = channel(config);
+        updates
+    }
+
+    /// 当前配置
+    pub fn current(&self) -> Arc<Config> {
+        self.receiver.borrow().clone()
+    }
+
+    /// 有尚未取走的新配置时返回它，不等待
+    pub fn take(&mut self) -> Option<Arc<Config>> {
+        if self.receiver.has_changed().unwrap_or(false) {
+            Some(self.receiver.borrow_and_update().clone())
+        } else {
+            None
+        }
+    }
+
+    /// 等待下一次发布的配置
+    ///
+    /// 发布端已经被丢弃时返回 None
+    pub async fn changed(&mut self) -> Option<Arc<Config>> {
+        self.receiver.changed().await.ok()?;
+        Some(self.receiver.borrow_and_update().clone())
+    }
+}
+
+/// 文件的修改时间和大小，文件不存在时为 None
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// 通过轮询修改时间监视一组配置文件
+///
+/// 不依赖文件系统通知，编辑器先删除再写入的保存方式也能检测到；
+/// 尚不存在的文件被创建时同样视为变化
+#[derive(Debug, Default)]
+pub struct FileWatcher {
+    files: Vec<(PathBuf, Stamp)>,
+}
+
+impl FileWatcher {
+    /// 记录文件当前的状态
+    pub fn new(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mut files: Vec<(PathBuf, Stamp)> = Vec::new();
+        for path in paths {
+            if !files.iter().any(|(known, _)| *known == path) {
+                let current = stamp(&path);
+                files.push((path, current));
+            }
+        }
+        FileWatcher { files }
+    }
+
+    /// 监视的文件
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.files.iter().map(|(path, _)| path)
+    }
+
+    /// 返回上次检查以来被修改、创建或删除的文件
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, last) in &mut self.files {
+            let current = stamp(path);
+            if current != *last {
+                *last = current;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+}
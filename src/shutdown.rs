@@ -0,0 +1,125 @@
+//! 优雅退出与信号处理
+//!
+//! 监控任务持有 `Shutdown`，在每次等待时同时监听退出通知：
+//! 正在进行的发送操作会完成，之后的等待立即结束，任务在下一轮检查前退出
+
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// 通知所有监控任务退出的一端
+#[derive(Debug)]
+pub struct ShutdownTrigger {
+    sender: watch::Sender<bool>,
+}
+
+/// 监控任务持有的退出通知
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    receiver: watch::Receiver<bool>,
+}
+
+/// 创建一对退出通知
+pub fn channel() -> (ShutdownTrigger, Shutdown) {
+    let (sender, receiver) = watch::channel(false);
+    (ShutdownTrigger { sender }, Shutdown { receiver })
+}
+
+impl ShutdownTrigger {
+    /// 通知所有持有对应 `Shutdown` 的任务退出
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::never()
+    }
+}
+
+impl Shutdown {
+    /// 永远不会触发的退出通知，用于不需要优雅退出的场景（例如测试）
+    pub fn never() -> Self {
+        let (_, shutdown) = channel();
+        shutdown
+    }
+
+    /// 是否已经收到退出通知
+    pub fn is_triggered(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    /// 等待退出通知
+    ///
+    /// 通知端已经被丢弃且没有触发时永远等待
+    pub async fn triggered(&self) {
+        let mut receiver = self.receiver.clone();
+        if receiver.wait_for(|triggered| *triggered).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    /// 等待指定时长，收到退出通知时提前结束
+    ///
+    /// 完整等待返回 true，被退出通知打断返回 false
+    pub async fn sleep(&self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => true,
+            _ = self.triggered() => false,
+        }
+    }
+}
+
+/// 进程收到的控制信号
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// SIGINT / SIGTERM：退出
+    Terminate,
+    /// SIGHUP：重新加载配置
+    Reload,
+}
+
+/// 监听进程信号
+pub struct Signals {
+    #[cfg(unix)]
+    interrupt: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+}
+
+impl Signals {
+    /// 注册信号处理，必须在 tokio 运行时中调用
+    #[cfg(unix)]
+    pub fn new() -> std::io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+        Ok(Signals {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+            hangup: signal(SignalKind::hangup())?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn new() -> std::io::Result<Self> {
+        Ok(Signals {})
+    }
+
+    /// 等待下一个信号
+    #[cfg(unix)]
+    pub async fn recv(&mut self) -> Signal {
+        tokio::select! {
+            _ = self.interrupt.recv() => Signal::Terminate,
+            _ = self.terminate.recv() => Signal::Terminate,
+            _ = self.hangup.recv() => Signal::Reload,
+        }
+    }
+
+    /// 非 Unix 平台只支持 Ctrl+C
+    #[cfg(not(unix))]
+    pub async fn recv(&mut self) -> Signal {
+        let _ = tokio::signal::ctrl_c().await;
+        Signal::Terminate
+    }
+}
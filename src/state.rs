@@ -0,0 +1,139 @@
+//! Claude Code 会话状态模型
+//!
+//! 启发式检测和 LLM 判断都产出同一个 `ClaudeState`，监控循环据此决定采取的动作；
+//! LLM 判断时还会附带置信度、理由和建议操作（`Verdict`）
+
+use std::fmt;
+
+/// Claude Code 会话状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ClaudeState {
+    /// 正在执行工具调用、编辑文件等
+    Working,
+    /// 正在思考（Cogitating、Thinking 等）
+    Thinking,
+    /// 弹出了权限确认对话框，等待选择
+    WaitingForPermission,
+    /// Claude 向用户提问，等待回答
+    AskingUserQuestion,
+    /// 触发了用量限制，需要等待重置
+    RateLimited,
+    /// 上下文已满，需要压缩
+    ContextFull,
+    /// 出现错误后停止
+    Errored,
+    /// 被用户中断
+    Interrupted,
+    /// 任务已完成
+    Done,
+    /// 画面停止变化且没有明确原因
+    Stuck,
+}
+
+impl ClaudeState {
+    /// 所有状态，按标签解析时使用
+    pub const ALL: [ClaudeState; 10] = [
+        ClaudeState::Working,
+        ClaudeState::Thinking,
+        ClaudeState::WaitingForPermission,
+        ClaudeState::AskingUserQuestion,
+        ClaudeState::RateLimited,
+        ClaudeState::ContextFull,
+        ClaudeState::Errored,
+        ClaudeState::Interrupted,
+        ClaudeState::Done,
+        ClaudeState::Stuck,
+    ];
+
+    /// 状态标签，也是 LLM 需要返回的文本
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClaudeState::Working => "WORKING",
+            ClaudeState::Thinking => "THINKING",
+            ClaudeState::WaitingForPermission => "WAITING_FOR_PERMISSION",
+            ClaudeState::AskingUserQuestion => "ASKING_USER_QUESTION",
+            ClaudeState::RateLimited => "RATE_LIMITED",
+            ClaudeState::ContextFull => "CONTEXT_FULL",
+            ClaudeState::Errored => "ERRORED",
+            ClaudeState::Interrupted => "INTERRUPTED",
+            ClaudeState::Done => "DONE",
+            ClaudeState::Stuck => "STUCK",
+        }
+    }
+
+    /// 从标签解析状态，忽略大小写和首尾空白
+    pub fn from_label(label: &str) -> Option<ClaudeState> {
+        let label = label.trim();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|state| state.label().eq_ignore_ascii_case(label))
+    }
+
+    /// Claude Code 是否仍在工作（不需要任何干预）
+    pub fn is_active(&self) -> bool {
+        matches!(self, ClaudeState::Working | ClaudeState::Thinking)
+    }
+
+    /// 是否需要执行卡住恢复流程
+    pub fn needs_recovery(&self) -> bool {
+        matches!(self, ClaudeState::Stuck | ClaudeState::Errored)
+    }
+
+    /// `--once` 模式的退出码
+    ///
+    /// 0：正在工作或已完成；2：等待人工处理或外部条件；3：卡住或出错。
+    /// 1 保留给无法捕获窗格等运行错误
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ClaudeState::Working | ClaudeState::Thinking | ClaudeState::Done => 0,
+            ClaudeState::Stuck | ClaudeState::Errored => 3,
+            _ => 2,
+        }
+    }
+}
+
+impl fmt::Display for ClaudeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// 一次状态判断的结果
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Verdict {
+    /// 判断出的状态
+    pub state: ClaudeState,
+    /// 置信度（0 到 1），判断方没有给出时为 None
+    pub confidence: Option<f32>,
+    /// 判断依据
+    pub reason: String,
+    /// 建议的下一步操作
+    pub suggested_action: Option<String>,
+}
+
+impl Verdict {
+    /// 只有状态和理由的判断结果
+    pub fn new(state: ClaudeState, reason: impl Into<String>) -> Self {
+        Verdict {
+            state,
+            confidence: None,
+            reason: reason.into(),
+            suggested_action: None,
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.state)?;
+        if let Some(confidence) = self.confidence {
+            write!(f, " (置信度 {:.2})", confidence)?;
+        }
+        if !self.reason.is_empty() {
+            write!(f, ": {}", self.reason)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+//! 终端后端抽象
+//!
+//! 监控逻辑只通过 `TerminalBackend` 访问终端，tmux 只是其中一种实现，
+//! 测试时可以替换为 `testing::ScriptedTerminal` 这样的内存实现
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// 窗格的基本信息，用于自动发现
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaneInfo {
+    /// 窗格 ID（例如 `%3`）
+    pub id: String,
+    /// 窗格所属的会话名称
+    pub session: String,
+    /// 窗格前台进程名称
+    pub current_command: String,
+    /// 窗格 shell 进程 PID
+    pub pid: u32,
+}
+
+/// 可以发送到窗格的按键
+///
+/// 与文本分开发送：文本总是按字面输入，按键才会被终端解释为控制键。
+/// 在配置中以键名书写，接受 tmux 键名（`C-c`、`Escape`、`BTab`）和常见写法（`ctrl+c`、`esc`、`shift+tab`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Key {
+    Enter,
+    Escape,
+    Tab,
+    BackTab,
+    Backspace,
+    Up,
+    Down,
+    Left,
+    Right,
+    CtrlC,
+    CtrlD,
+    /// 单个字符，例如选择对话框选项时的数字键
+    Char(char),
+}
+
+impl Key {
+    /// tmux `send-keys` 使用的键名
+    pub fn tmux_name(&self) -> String {
+        match self {
+            Key::Enter => "Enter".to_string(),
+            Key::Escape => "Escape".to_string(),
+            Key::Tab => "Tab".to_string(),
+            Key::BackTab => "BTab".to_string(),
+            Key::Backspace => "BSpace".to_string(),
+            Key::Up => "Up".to_string(),
+            Key::Down => "Down".to_string(),
+            Key::Left => "Left".to_string(),
+            Key::Right => "Right".to_string(),
+            Key::CtrlC => "C-c".to_string(),
+            Key::CtrlD => "C-d".to_string(),
+            Key::Char(c) => c.to_string(),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(Key::Char(c));
+        }
+
+        match name.trim().to_lowercase().replace(['+', '_'], "-").as_str() {
+            "enter" | "return" | "c-m" => Ok(Key::Enter),
+            "escape" | "esc" => Ok(Key::Escape),
+            "tab" => Ok(Key::Tab),
+            "btab" | "backtab" | "s-tab" | "shift-tab" => Ok(Key::BackTab),
+            "bspace" | "backspace" => Ok(Key::Backspace),
+            "up" => Ok(Key::Up),
+            "down" => Ok(Key::Down),
+            "left" => Ok(Key::Left),
+            "right" => Ok(Key::Right),
+            "c-c" | "ctrl-c" => Ok(Key::CtrlC),
+            "c-d" | "ctrl-d" => Ok(Key::CtrlD),
+            _ => Err(format!("未知的按键: {}", name)),
+        }
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> Self {
+        key.tmux_name()
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.tmux_name())
+    }
+}
+
+/// 终端后端 trait
+///
+/// 所有方法都以窗格 ID 作为目标，错误统一以字符串形式返回。
+/// 方法都是异步的，实现不应阻塞运行时线程
+#[async_trait::async_trait]
+pub trait TerminalBackend: Send + Sync {
+    /// 捕获窗格当前显示的文本内容
+    async fn capture(&self, pane: &str) -> Result<String, String>;
+
+    /// 向窗格按字面输入一段文本，并在之后按下回车提交
+    async fn send_text(&self, text: &str, pane: &str) -> Result<(), String>;
+
+    /// 向窗格发送单个按键
+    async fn send_key(&self, key: Key, pane: &str) -> Result<(), String>;
+
+    /// 检查窗格是否存在
+    async fn pane_exists(&self, pane: &str) -> bool;
+
+    /// 列出所有会话中的全部窗格
+    async fn list_panes(&self) -> Result<Vec<PaneInfo>, String>;
+
+    /// 窗格当前的工作目录，无法获取时返回 None
+    async fn pane_dir(&self, pane: &str) -> Option<PathBuf>;
+}
@@ -2,7 +2,9 @@
 //! 
 //! 提供测试辅助功能，包括模拟对象、测试数据、性能分析等
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
 use tokio::time::sleep;
 use async_trait::async_trait;
@@ -14,7 +16,6 @@ pub use crate::monitor::{
     check_if_should_skip_llm_call,
     has_substantial_progress,
     is_just_time_counter,
-    PaneStatus,
 };
 
 // 为测试暴露私有函数
@@ -24,15 +25,17 @@ pub use crate::monitor::{
 };
 
 pub use crate::activity::is_claude_active;
+pub use crate::state::ClaudeState;
 pub use crate::config::Config;
 pub use crate::args::Args;
+pub use crate::terminal::{Key, PaneInfo, TerminalBackend};
 
 /// 测试固件数据结构
 #[derive(Debug, Clone)]
 pub struct TestFixture {
     pub description: String,
     pub pane_content: String,
-    pub expected_status: PaneStatus,
+    pub expected_status: ClaudeState,
     pub expected_skip_llm: bool,
     pub expected_progress: bool,
 }
@@ -43,7 +46,7 @@ pub struct TestStep {
     pub name: String,
     pub action: String,
     pub expected_output: Option<String>,
-    pub expected_status: Option<PaneStatus>,
+    pub expected_status: Option<ClaudeState>,
 }
 
 /// 测试场景
@@ -57,7 +60,7 @@ pub struct TestScenario {
 /// 模拟监控服务trait
 #[async_trait]
 pub trait MockMonitorService: Send + Sync {
-    async fn check_pane_status(&self, pane_id: &str) -> PaneStatus;
+    async fn check_pane_status(&self, pane_id: &str) -> ClaudeState;
     async fn handle_stuck_pane(&self, pane_id: &str) -> Result<(), String>;
 }
 
@@ -67,6 +70,12 @@ pub struct PerformanceProfiler {
     start_times: HashMap<String, Instant>,
 }
 
+impl Default for PerformanceProfiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PerformanceProfiler {
     pub fn new() -> Self {
         Self {
@@ -100,6 +109,12 @@ pub struct AsyncTestHelper {
     timeout: Duration,
 }
 
+impl Default for AsyncTestHelper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AsyncTestHelper {
     pub fn new() -> Self {
         Self {
@@ -149,6 +164,12 @@ pub struct TestValidator {
     errors: Vec<String>,
 }
 
+impl Default for TestValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TestValidator {
     pub fn new() -> Self {
         Self {
@@ -198,48 +219,54 @@ pub struct TestFixtures {
     fixtures: Vec<TestFixture>,
 }
 
+impl Default for TestFixtures {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TestFixtures {
     pub fn new() -> Self {
         let fixtures = vec![
             TestFixture {
                 description: "标准读秒格式".to_string(),
                 pane_content: "* Herding… (169s · ↑ 8.7k tokens · esc to interrupt)".to_string(),
-                expected_status: PaneStatus::Active,
+                expected_status: ClaudeState::Thinking,
                 expected_skip_llm: true,
                 expected_progress: true,
             },
             TestFixture {
                 description: "工具调用".to_string(),
                 pane_content: "Tool use: Reading file".to_string(),
-                expected_status: PaneStatus::Active,
+                expected_status: ClaudeState::Working,
                 expected_skip_llm: false,
                 expected_progress: true,
             },
             TestFixture {
                 description: "任务完成".to_string(),
                 pane_content: "✅ Task completed successfully".to_string(),
-                expected_status: PaneStatus::Idle,
+                expected_status: ClaudeState::Done,
                 expected_skip_llm: false,
                 expected_progress: true,
             },
             TestFixture {
                 description: "错误状态".to_string(),
                 pane_content: "Error: compilation failed".to_string(),
-                expected_status: PaneStatus::Idle,
+                expected_status: ClaudeState::Errored,
                 expected_skip_llm: false,
                 expected_progress: true,
             },
             TestFixture {
                 description: "中断状态".to_string(),
                 pane_content: "Interrupted by user".to_string(),
-                expected_status: PaneStatus::Idle,
+                expected_status: ClaudeState::Interrupted,
                 expected_skip_llm: false,
                 expected_progress: true,
             },
             TestFixture {
                 description: "纯时间计数器".to_string(),
                 pane_content: "104s".to_string(),
-                expected_status: PaneStatus::Idle,
+                expected_status: ClaudeState::Stuck,
                 expected_skip_llm: true,
                 expected_progress: false,
             },
@@ -262,6 +289,12 @@ pub struct TestScenarios {
     scenarios: Vec<TestScenario>,
 }
 
+impl Default for TestScenarios {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TestScenarios {
     pub fn new() -> Self {
         let scenarios = vec![
@@ -273,25 +306,25 @@ impl TestScenarios {
                         name: "开始监控".to_string(),
                         action: "initialize_monitoring".to_string(),
                         expected_output: Some("Monitoring started".to_string()),
-                        expected_status: Some(PaneStatus::Idle),
+                        expected_status: Some(ClaudeState::Done),
                     },
                     TestStep {
                         name: "检测活动".to_string(),
                         action: "detect_activity".to_string(),
                         expected_output: Some("Activity detected".to_string()),
-                        expected_status: Some(PaneStatus::Active),
+                        expected_status: Some(ClaudeState::Working),
                     },
                     TestStep {
                         name: "处理卡住".to_string(),
                         action: "handle_stuck".to_string(),
                         expected_output: Some("Stuck handled".to_string()),
-                        expected_status: Some(PaneStatus::Active),
+                        expected_status: Some(ClaudeState::Working),
                     },
                     TestStep {
                         name: "任务完成".to_string(),
                         action: "task_completed".to_string(),
                         expected_output: Some("Task completed".to_string()),
-                        expected_status: Some(PaneStatus::Idle),
+                        expected_status: Some(ClaudeState::Done),
                     },
                 ],
             },
@@ -314,7 +347,7 @@ pub struct TestDataGenerator;
 
 impl TestDataGenerator {
     pub fn generate_random_terminal_output() -> String {
-        let templates = vec![
+        let templates = [
             "* Herding… (123s · ↑ 8.7k tokens · esc to interrupt)",
             "* Cogitating… (456s · ↓ 12.3k tokens · esc to interrupt)",
             "Tool use: Reading file (789s · 5.6k tokens)",
@@ -346,7 +379,7 @@ impl TestDataGenerator {
 
     pub fn generate_mixed_content() -> String {
         let mut content = String::new();
-        let lines = vec![
+        let lines = [
             "Starting task...",
             "Tool use: Reading configuration",
             "* Cogitating… (100s · ↑ 5.2k tokens · esc to interrupt)",
@@ -370,7 +403,13 @@ impl TestDataGenerator {
 
 /// 模拟监控服务实现
 pub struct MockMonitorServiceImpl {
-    pub responses: HashMap<String, PaneStatus>,
+    pub responses: HashMap<String, ClaudeState>,
+}
+
+impl Default for MockMonitorServiceImpl {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MockMonitorServiceImpl {
@@ -380,15 +419,15 @@ impl MockMonitorServiceImpl {
         }
     }
 
-    pub fn set_response(&mut self, pane_id: &str, status: PaneStatus) {
+    pub fn set_response(&mut self, pane_id: &str, status: ClaudeState) {
         self.responses.insert(pane_id.to_string(), status);
     }
 }
 
 #[async_trait]
 impl MockMonitorService for MockMonitorServiceImpl {
-    async fn check_pane_status(&self, pane_id: &str) -> PaneStatus {
-        self.responses.get(pane_id).copied().unwrap_or(PaneStatus::Idle)
+    async fn check_pane_status(&self, pane_id: &str) -> ClaudeState {
+        self.responses.get(pane_id).copied().unwrap_or(ClaudeState::Stuck)
     }
 
     async fn handle_stuck_pane(&self, _pane_id: &str) -> Result<(), String> {
@@ -397,6 +436,145 @@ impl MockMonitorService for MockMonitorServiceImpl {
     }
 }
 
+/// 脚本化终端中记录的一次输入
+#[derive(Debug, Clone, PartialEq)]
+pub enum SentInput {
+    Text { pane: String, text: String },
+    Key { pane: String, key: Key },
+}
+
+/// 脚本化的内存终端后端
+///
+/// 每个窗格持有一个屏幕队列：每次 capture 弹出下一屏，
+/// 只剩最后一屏时重复返回它；未配置的窗格视为不存在。
+/// 所有发送的文本和按键都会被记录下来，供测试断言
+pub struct ScriptedTerminal {
+    screens: Mutex<HashMap<String, VecDeque<String>>>,
+    commands: Mutex<HashMap<String, String>>,
+    dirs: Mutex<HashMap<String, PathBuf>>,
+    sent: Mutex<Vec<SentInput>>,
+}
+
+impl Default for ScriptedTerminal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptedTerminal {
+    pub fn new() -> Self {
+        Self {
+            screens: Mutex::new(HashMap::new()),
+            commands: Mutex::new(HashMap::new()),
+            dirs: Mutex::new(HashMap::new()),
+            sent: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 为窗格追加一屏内容
+    pub fn push_screen(&self, pane: &str, screen: &str) {
+        self.screens
+            .lock()
+            .unwrap()
+            .entry(pane.to_string())
+            .or_default()
+            .push_back(screen.to_string());
+    }
+
+    /// 设置窗格的前台进程名称（默认为 `bash`）
+    pub fn set_command(&self, pane: &str, command: &str) {
+        self.commands
+            .lock()
+            .unwrap()
+            .insert(pane.to_string(), command.to_string());
+    }
+
+    /// 设置窗格的工作目录（默认没有）
+    pub fn set_dir(&self, pane: &str, dir: impl Into<PathBuf>) {
+        self.dirs.lock().unwrap().insert(pane.to_string(), dir.into());
+    }
+
+    /// 移除窗格，模拟窗格被关闭
+    pub fn remove_pane(&self, pane: &str) {
+        self.screens.lock().unwrap().remove(pane);
+    }
+
+    /// 返回目前为止记录的所有输入
+    pub fn sent(&self) -> Vec<SentInput> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    fn has_pane(&self, pane: &str) -> bool {
+        self.screens.lock().unwrap().contains_key(pane)
+    }
+}
+
+#[async_trait::async_trait]
+impl TerminalBackend for ScriptedTerminal {
+    async fn capture(&self, pane: &str) -> Result<String, String> {
+        let mut screens = self.screens.lock().unwrap();
+        let queue = screens
+            .get_mut(pane)
+            .ok_or_else(|| format!("窗格不存在: {}", pane))?;
+        if queue.len() > 1 {
+            Ok(queue.pop_front().unwrap_or_default())
+        } else {
+            queue
+                .front()
+                .cloned()
+                .ok_or_else(|| format!("窗格没有可用的屏幕内容: {}", pane))
+        }
+    }
+
+    async fn send_text(&self, text: &str, pane: &str) -> Result<(), String> {
+        if !self.has_pane(pane) {
+            return Err(format!("窗格不存在: {}", pane));
+        }
+        self.sent.lock().unwrap().push(SentInput::Text {
+            pane: pane.to_string(),
+            text: text.to_string(),
+        });
+        Ok(())
+    }
+
+    async fn send_key(&self, key: Key, pane: &str) -> Result<(), String> {
+        if !self.has_pane(pane) {
+            return Err(format!("窗格不存在: {}", pane));
+        }
+        self.sent.lock().unwrap().push(SentInput::Key {
+            pane: pane.to_string(),
+            key,
+        });
+        Ok(())
+    }
+
+    async fn pane_exists(&self, pane: &str) -> bool {
+        self.has_pane(pane)
+    }
+
+    async fn list_panes(&self) -> Result<Vec<PaneInfo>, String> {
+        let commands = self.commands.lock().unwrap();
+        let mut panes: Vec<PaneInfo> = self
+            .screens
+            .lock()
+            .unwrap()
+            .keys()
+            .map(|id| PaneInfo {
+                id: id.clone(),
+                session: "scripted".to_string(),
+                current_command: commands.get(id).cloned().unwrap_or_else(|| "bash".to_string()),
+                pid: 0,
+            })
+            .collect();
+        panes.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(panes)
+    }
+
+    async fn pane_dir(&self, pane: &str) -> Option<PathBuf> {
+        self.dirs.lock().unwrap().get(pane).cloned()
+    }
+}
+
 /// 测试断言辅助函数
 pub mod assertions {
     use super::*;
@@ -437,12 +615,7 @@ pub mod setup {
 
     pub fn create_test_args() -> Args {
         use clap::Parser;
-        Args::parse_from(&["claude-watch", "--pane", "%6"])
-    }
-
-    pub fn reset_global_state() {
-        // 重置全局状态以确保测试独立性
-        crate::monitor::reset_time_tracker();
+        Args::parse_from(["claude-watch", "--pane", "%6"])
     }
 }
 
@@ -483,7 +656,7 @@ macro_rules! assert_time {
 macro_rules! test_fixture {
     ($fixture:expr) => {
         let result = $crate::testing::is_claude_active(&$fixture.pane_content);
-        assert_eq!(result, $fixture.expected_status == $crate::testing::PaneStatus::Active,
+        assert_eq!(result, $fixture.expected_status.is_active(),
                    "固件测试失败: {} - 预期状态: {:?}, 实际: {}", 
                    $fixture.description, $fixture.expected_status, result);
         
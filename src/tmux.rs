@@ -1,79 +1,130 @@
-use std::process::Command;
+use crate::terminal::{Key, PaneInfo, TerminalBackend};
+use std::path::PathBuf;
+use tokio::process::Command;
 
-/// 发送按键命令到指定的tmux窗格
-/// 
-/// 这个函数会分两步发送：先发送文本，等待一小段时间，然后发送回车键
-/// 这样可以解决时序问题，确保消息被正确接收和处理
-/// 
-/// 参数说明：
-/// - text: 要发送的文本内容
-/// - pane: 目标tmux窗格ID
-pub fn send_keys(text: &str, pane: &str) {
-    println!("🔧 发送命令到 tmux pane {}: {}", pane, text);
-    
-    // 第一步：发送文本内容
-    let text_result = Command::new("tmux")
-        .args(&["send-keys", "-t", pane, text])
-        .output();
-    
-    match text_result {
-        Ok(output) => {
-            if output.status.success() {
-                println!("✅ 文本发送成功");
-            } else {
-                println!("⚠️ 文本发送失败，状态码: {}", output.status);
-                if !output.stderr.is_empty() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    println!("❌ 文本发送错误: {}", stderr.trim());
-                }
-                return;
-            }
-        }
-        Err(e) => {
-            println!("❌ 无法执行文本发送命令: {}", e);
-            return;
-        }
-    }
-    
-    // 等待一小段时间，确保文本被完全接收
-    std::thread::sleep(std::time::Duration::from_millis(150));
-    
-    // 第二步：发送回车键 (C-m)
-    let enter_result = Command::new("tmux")
-        .args(&["send-keys", "-t", pane, "C-m"])
-        .output();
-    
-    match enter_result {
-        Ok(output) => {
-            if output.status.success() {
-                println!("✅ 回车键发送成功");
-            } else {
-                println!("⚠️ 回车键发送失败，状态码: {}", output.status);
-                if !output.stderr.is_empty() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    println!("❌ 回车键发送错误: {}", stderr.trim());
-                }
-            }
-        }
-        Err(e) => {
-            println!("❌ 无法执行回车键发送命令: {}", e);
-        }
+/// 基于 tmux 命令行的终端后端
+///
+/// 每次调用都会异步执行一次 tmux 子进程，不持有任何状态
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TmuxBackend;
+
+impl TmuxBackend {
+    pub fn new() -> Self {
+        TmuxBackend
     }
 }
 
-/// 从指定的tmux窗格捕获内容
-/// 
-/// 这个函数会捕获tmux窗格中的所有文本内容
-/// 参数说明：
-/// - pane: 目标tmux窗格ID
-/// 
-/// 命令结构：tmux capture-pane -p -t {pane}
-/// - -p: 以纯文本格式输出
-/// - -t {pane}: 指定目标窗格
-pub fn capture(pane: &str) -> String {
-    let out = Command::new("tmux")
-        .args(&["capture-pane", "-p", "-t", pane])
+/// 执行 tmux 命令，失败时把 stderr 转换为错误信息
+async fn run_tmux(args: &[&str]) -> Result<std::process::Output, String> {
+    let output = Command::new("tmux")
+        .args(args)
         .output()
-        .expect("tmux capture failed");
-    String::from_utf8_lossy(&out.stdout).into_owned()
-}
\ No newline at end of file
+        .await
+        .map_err(|e| format!("无法执行 tmux 命令: {}", e))?;
+
+    if output.status.success() {
+        Ok(output)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("tmux 命令失败，状态码: {}, 错误: {}", output.status, stderr.trim()))
+    }
+}
+
+/// `list-panes` 使用的输出格式，字段之间以制表符分隔
+const LIST_PANES_FORMAT: &str = "#{pane_id}\t#{session_name}\t#{pane_current_command}\t#{pane_pid}";
+
+/// 解析 `tmux list-panes -a -F LIST_PANES_FORMAT` 的输出
+///
+/// 格式不完整的行会被跳过
+pub fn parse_list_panes(output: &str) -> Vec<PaneInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let id = fields.next()?.trim();
+            let session = fields.next()?;
+            let current_command = fields.next()?;
+            let pid = fields.next()?.trim().parse::<u32>().ok()?;
+            if id.is_empty() {
+                return None;
+            }
+            Some(PaneInfo {
+                id: id.to_string(),
+                session: session.to_string(),
+                current_command: current_command.to_string(),
+                pid,
+            })
+        })
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl TerminalBackend for TmuxBackend {
+    /// 从指定的tmux窗格捕获内容
+    ///
+    /// 命令结构：tmux capture-pane -p -t {pane}
+    /// - -p: 以纯文本格式输出
+    /// - -t {pane}: 指定目标窗格
+    async fn capture(&self, pane: &str) -> Result<String, String> {
+        let out = run_tmux(&["capture-pane", "-p", "-t", pane]).await?;
+        Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+    }
+
+    /// 发送文本到指定的tmux窗格
+    ///
+    /// 这个函数会分两步发送：先发送文本，等待一小段时间，然后发送回车键
+    /// 这样可以解决时序问题，确保消息被正确接收和处理。
+    /// 文本使用 `send-keys -l` 按字面发送，不会被 tmux 解释为键名
+    async fn send_text(&self, text: &str, pane: &str) -> Result<(), String> {
+        println!("🔧 发送命令到 tmux pane {}: {}", pane, text);
+
+        // 第一步：发送文本内容
+        run_tmux(&["send-keys", "-t", pane, "-l", text])
+            .await
+            .map_err(|e| format!("文本发送失败: {}", e))?;
+        println!("✅ 文本发送成功");
+
+        // 等待一小段时间，确保文本被完全接收
+        tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+
+        // 第二步：发送回车键
+        self.send_key(Key::Enter, pane)
+            .await
+            .map_err(|e| format!("回车键发送失败: {}", e))?;
+        println!("✅ 回车键发送成功");
+        Ok(())
+    }
+
+    /// 命令结构：tmux send-keys -t {pane} {键名}
+    ///
+    /// 单个字符按字面发送，避免 `;` 等字符被 tmux 特殊处理
+    async fn send_key(&self, key: Key, pane: &str) -> Result<(), String> {
+        let name = key.tmux_name();
+        let args: &[&str] = match key {
+            Key::Char(_) => &["send-keys", "-t", pane, "-l", &name],
+            _ => &["send-keys", "-t", pane, &name],
+        };
+        run_tmux(args).await.map(|_| ())
+    }
+
+    /// 通过 display-message 查询窗格 ID 判断窗格是否存在
+    async fn pane_exists(&self, pane: &str) -> bool {
+        // 目标不存在时部分 tmux 版本仍然返回 0，只是输出为空
+        run_tmux(&["display-message", "-p", "-t", pane, "#{pane_id}"])
+            .await
+            .is_ok_and(|out| !String::from_utf8_lossy(&out.stdout).trim().is_empty())
+    }
+
+    /// 命令结构：tmux list-panes -a -F {LIST_PANES_FORMAT}
+    async fn list_panes(&self) -> Result<Vec<PaneInfo>, String> {
+        let out = run_tmux(&["list-panes", "-a", "-F", LIST_PANES_FORMAT]).await?;
+        Ok(parse_list_panes(&String::from_utf8_lossy(&out.stdout)))
+    }
+
+    /// 通过 display-message 查询 pane_current_path
+    async fn pane_dir(&self, pane: &str) -> Option<PathBuf> {
+        let out = run_tmux(&["display-message", "-p", "-t", pane, "#{pane_current_path}"]).await.ok()?;
+        let path = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        (!path.is_empty()).then(|| PathBuf::from(path))
+    }
+}
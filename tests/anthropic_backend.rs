@@ -0,0 +1,153 @@
+use claude_watch::config::{AnthropicConfig, BackendConfig, Config};
+use claude_watch::llm::create_backend;
+use claude_watch::prompt::PromptContext;
+use claude_watch::ClaudeState;
+use serde_json::json;
+use std::time::{Duration, Instant};
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn anthropic_config(server: &MockServer) -> Config {
+    let mut config = Config::default();
+    config.llm.backend = "anthropic".to_string();
+    config.llm.anthropic = Some(AnthropicConfig {
+        api_key: "sk-ant-test".to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        base_url: server.uri(),
+        model: "claude-test".to_string(),
+        version: "2023-06-01".to_string(),
+    });
+    config
+}
+
+fn message_response(text: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "id": "msg_01",
+        "type": "message",
+        "role": "assistant",
+        "model": "claude-test",
+        "content": [{"type": "text", "text": text}],
+        "stop_reason": "end_turn"
+    }))
+}
+
+/// 测试从 YAML 解析 anthropic 配置，base_url 和 version 有默认值
+#[test]
+fn test_parse_anthropic_config() {
+    let yaml = r#"
+llm:
+  backend: anthropic
+  anthropic:
+    api_key: sk-ant-xxx
+    model: claude-3-5-haiku-latest
+monitoring:
+  interval: 5
+  stuck_sec: 60
+  max_retry: 3
+tmux:
+  pane: "%0"
+"#;
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+    let anthropic = config.llm.anthropic.unwrap();
+
+    assert_eq!(anthropic.base_url, "https://api.anthropic.com");
+    assert_eq!(anthropic.version, "2023-06-01");
+}
+
+/// 测试通过 Messages API 判断状态：系统提示词放在顶层 system 字段，判断结果通过强制工具调用返回
+#[tokio::test]
+async fn test_anthropic_classify() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(header("x-api-key", "sk-ant-test"))
+        .and(header("anthropic-version", "2023-06-01"))
+        .and(body_partial_json(json!({
+            "model": "claude-test",
+            "max_tokens": 256,
+            "messages": [{"role": "user", "content": "✅ Task completed"}],
+            "tool_choice": {"type": "tool", "name": "report_verdict"}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "type": "message",
+            "role": "assistant",
+            "content": [{
+                "type": "tool_use",
+                "id": "toolu_01",
+                "name": "report_verdict",
+                "input": {"state": "DONE", "confidence": 0.95, "reason": "出现完成总结", "suggested_action": ""}
+            }],
+            "stop_reason": "tool_use"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let config = anthropic_config(&server);
+
+    let backend = create_backend("anthropic", &config.llm).unwrap();
+    let verdict = backend.classify("判断状态", "✅ Task completed").await.unwrap();
+    assert_eq!(verdict.state, ClaudeState::Done);
+    assert_eq!(verdict.confidence, Some(0.95));
+    assert_eq!(verdict.reason, "出现完成总结");
+    assert_eq!(verdict.suggested_action, None);
+
+    let request = &server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    assert!(body["system"].as_str().is_some_and(|system| !system.is_empty()));
+}
+
+/// 测试生成激活消息
+#[tokio::test]
+async fn test_anthropic_generate_activation() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(message_response("请继续处理任务\n"))
+        .mount(&server)
+        .await;
+    let config = anthropic_config(&server);
+
+    let message = claude_watch::ask_llm_for_activation(&PromptContext::new("%0", "等待中"), &config).await;
+    assert_eq!(message, Ok("请继续处理任务".to_string()));
+}
+
+/// 测试错误响应提取 error.message，缺少 API key 时不发送请求
+#[tokio::test]
+async fn test_anthropic_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(529).set_body_json(json!({
+            "type": "error",
+            "error": {"type": "overloaded_error", "message": "Overloaded"}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut config = anthropic_config(&server);
+
+    let backend = create_backend("anthropic", &config.llm).unwrap();
+    let error = backend.classify("判断状态", "screen").await.unwrap_err();
+    assert!(error.contains("529") && error.contains("Overloaded"), "{}", error);
+
+    config.llm.anthropic.as_mut().unwrap().api_key.clear();
+    let backend = create_backend("anthropic", &config.llm).unwrap();
+    assert!(backend.classify("判断状态", "screen").await.is_err());
+}
+
+/// 测试 HTTP 请求在降级链的超时时间内结束
+#[tokio::test]
+async fn test_anthropic_request_uses_chain_timeout() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(message_response("STUCK").set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+    let mut config = anthropic_config(&server);
+    config.llm.backends = vec![BackendConfig { timeout: Some(1), ..BackendConfig::new("anthropic") }];
+    let backend = create_backend("anthropic", &config.llm).unwrap();
+
+    let started = Instant::now();
+    assert!(backend.classify("判断状态", "screen").await.is_err());
+    assert!(started.elapsed() < Duration::from_secs(3), "{:?}", started.elapsed());
+}
@@ -0,0 +1,155 @@
+use claude_watch::args::{Args, Command};
+use claude_watch::commands;
+use claude_watch::config::{Config, OpenAiConfig, PaneConfig};
+use claude_watch::state::ClaudeState;
+use claude_watch::terminal::Key;
+use claude_watch::testing::{ScriptedTerminal, SentInput};
+use clap::Parser;
+use predicates::prelude::*;
+
+const ERROR_SCREEN: &str = "Error: compilation failed\n$ ";
+const WORKING_SCREEN: &str = "● Read(src/main.rs)\n  ⎿  Read 42 lines\n\n✻ Thinking… (12s · esc to interrupt)\n";
+const SECRET_SCREEN: &str = "● Bash(env)\n  ⎿  OPENAI_API_KEY=sk-proj-abcdefghijklmnop1234\n";
+
+fn config(pane: &str, backend: &str) -> Config {
+    let mut config = Config::default();
+    config.tmux.pane = pane.to_string();
+    config.llm.backend = backend.to_string();
+    config
+}
+
+fn text(pane: &str, text: &str) -> SentInput {
+    SentInput::Text { pane: pane.to_string(), text: text.to_string() }
+}
+
+/// 测试子命令解析：默认 watch，--once 等同于 status，--pane 可以写在子命令之后
+#[test]
+fn test_subcommand_parsing() {
+    assert_eq!(Args::parse_from(["claude-watch"]).command(), Command::Watch);
+    assert_eq!(Args::parse_from(["claude-watch", "--once"]).command(), Command::Status);
+
+    let args = Args::parse_from(["claude-watch", "status", "--pane", "%3", "--backend", "none"]);
+    assert_eq!((args.command(), args.pane.as_deref()), (Command::Status, Some("%3")));
+
+    let args = Args::parse_from(["claude-watch", "watch", "--discover", "-i", "3", "--stuck-sec", "30", "-m", "2"]);
+    assert_eq!(args.command(), Command::Watch);
+    assert_eq!((args.discover, args.interval, args.stuck_sec, args.max_retry), (true, Some(3), Some(30), Some(2)));
+
+    let args = Args::parse_from(["claude-watch", "send", "continue", "-k", "esc", "--key", "C-c"]);
+    let expected = Command::Send { text: Some("continue".to_string()), keys: vec![Key::Escape, Key::CtrlC] };
+    assert_eq!(args.command(), expected);
+    assert!(Args::try_parse_from(["claude-watch", "send", "--key", "Enter"]).is_ok());
+    assert!(Args::try_parse_from(["claude-watch", "send"]).is_err());
+    assert!(Args::try_parse_from(["claude-watch", "send", "-k", "hyper"]).is_err());
+    assert!(Args::try_parse_from(["claude-watch", "classify"]).is_err());
+}
+
+/// 测试 send 通过恢复步骤的发送路径依次发送文本和按键
+#[tokio::test]
+async fn test_send_delivers_text_then_keys() {
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%1", "$ ");
+    let sent = commands::send(&config("%1", "none"), &terminal, Some("continue"), &[Key::Escape, Key::Enter])
+        .await
+        .unwrap();
+    assert_eq!(sent, vec!["send_text \"continue\"", "send_key Escape", "send_key Enter"]);
+    assert_eq!(
+        terminal.sent(),
+        vec![
+            text("%1", "continue"),
+            SentInput::Key { pane: "%1".to_string(), key: Key::Escape },
+            SentInput::Key { pane: "%1".to_string(), key: Key::Enter },
+        ]
+    );
+
+    assert!(commands::send(&config("%9", "none"), &terminal, Some("x"), &[]).await.is_err());
+    let mut several = config("%1", "none");
+    several.tmux.panes = vec![PaneConfig::new("%1"), PaneConfig::new("%2")];
+    let error = commands::send(&several, &terminal, Some("x"), &[]).await.unwrap_err();
+    assert!(error.contains("--pane"), "{}", error);
+    assert_eq!(terminal.sent().len(), 3);
+}
+
+/// 测试 capture 输出远程后端收到的画面，--raw 输出原样
+#[tokio::test]
+async fn test_capture_shows_what_the_llm_receives() {
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%1", SECRET_SCREEN);
+
+    let raw = commands::capture(&config("%1", "none"), &terminal, true).await.unwrap();
+    assert_eq!(raw, SECRET_SCREEN);
+    let local = commands::capture(&config("%1", "none"), &terminal, false).await.unwrap();
+    assert_eq!(local, SECRET_SCREEN);
+
+    let mut remote = config("%1", "openai");
+    remote.llm.openai = Some(OpenAiConfig {
+        api_key: "sk-test".to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_base: "https://api.openai.com/v1".to_string(),
+        model: "gpt-4o-mini".to_string(),
+    });
+    let normalized = commands::capture(&remote, &terminal, false).await.unwrap();
+    assert!(normalized.contains("[REDACTED]"), "{}", normalized);
+    assert!(!normalized.contains("sk-proj"), "{}", normalized);
+    remote.llm.redaction.enabled = false;
+    assert!(commands::capture(&remote, &terminal, false).await.unwrap().contains("sk-proj"));
+}
+
+/// 测试 status 判断每个窗格并取最严重的退出码
+#[tokio::test]
+async fn test_status_takes_worst_exit_code() {
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%1", WORKING_SCREEN);
+    terminal.push_screen("%2", ERROR_SCREEN);
+    let mut config = config("%1", "none");
+    config.tmux.panes = vec![PaneConfig::new("%1"), PaneConfig::new("%2")];
+    assert_eq!(commands::status(&config, &terminal).await, 3);
+
+    config.tmux.panes[1] = PaneConfig::new("%9");
+    assert_eq!(commands::status(&config, &terminal).await, 1);
+    config.tmux.panes.remove(1);
+    assert_eq!(commands::status(&config, &terminal).await, 0);
+}
+
+/// 测试 classify 从文件和标准输入读取画面，输出状态和判断依据
+#[test]
+fn test_classify_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let screen = dir.path().join("screen.txt");
+    std::fs::write(&screen, ERROR_SCREEN).unwrap();
+    let run = |args: &[&str]| {
+        let mut command = assert_cmd::Command::cargo_bin("claude-watch").unwrap();
+        command.current_dir(dir.path()).env("XDG_CONFIG_HOME", dir.path());
+        command.args(["--backend", "none"]).args(args);
+        command
+    };
+
+    run(&["classify", "--no-llm", screen.to_str().unwrap()])
+        .assert()
+        .code(ClaudeState::Errored.exit_code())
+        .stdout(predicate::str::starts_with("ERRORED\n").and(predicate::str::contains("判断依据: ")));
+    run(&["classify", "-"])
+        .write_stdin(WORKING_SCREEN)
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("判断依据: 画面显示正在执行"));
+    run(&["classify", "nonexistent.txt"]).assert().code(1).stderr(predicate::str::contains("nonexistent.txt"));
+}
+
+/// 测试 watch 子命令接受监控参数，窗格不存在时直接结束
+#[test]
+fn test_watch_command_accepts_monitoring_options() {
+    let dir = tempfile::tempdir().unwrap();
+    assert_cmd::Command::cargo_bin("claude-watch")
+        .unwrap()
+        .current_dir(dir.path())
+        .env("XDG_CONFIG_HOME", dir.path())
+        .env("TMUX_TMPDIR", dir.path().join("no-server"))
+        .args(["watch", "--interval", "3", "--stuck-sec", "30", "--max-retry", "2"])
+        .args(["--pane", "%99999", "--backend", "none"])
+        .timeout(std::time::Duration::from_secs(30))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("开始监控 Claude Code 在 tmux pane %99999"));
+}
@@ -132,20 +132,18 @@ fn test_progress_detection_comprehensive() {
 
 #[test]
 fn test_time_increasing_logic() {
-    setup::reset_global_state();
-    
-    let pane_id = "test_pane";
+    let mut previous = None;
     
     // 第一次应该返回true - 使用简化的ASCII格式
-    let result1 = is_time_increasing("* Herding (100s) tokens esc to interrupt", pane_id);
+    let result1 = is_time_increasing("* Herding (100s) tokens esc to interrupt", &mut previous);
     assert!(result1, "第一次检测应该返回true");
     
     // 相同时间应该返回false
-    let result2 = is_time_increasing("* Herding (100s) tokens esc to interrupt", pane_id);
+    let result2 = is_time_increasing("* Herding (100s) tokens esc to interrupt", &mut previous);
     assert!(!result2, "相同时间应该返回false");
     
     // 增加时间应该返回true
-    let result3 = is_time_increasing("* Herding (101s) tokens esc to interrupt", pane_id);
+    let result3 = is_time_increasing("* Herding (101s) tokens esc to interrupt", &mut previous);
     assert!(result3, "增加时间应该返回true");
 }
 
@@ -337,7 +335,7 @@ fn test_fixture_based_testing() {
         
         // 测试固件逻辑
         let activity = is_claude_active(&fixture.pane_content);
-        let expected_activity = fixture.expected_status == PaneStatus::Active;
+        let expected_activity = fixture.expected_status.is_active();
         assert_eq!(activity, expected_activity, 
                    "固件测试失败 - {}: 预期活动: {}, 实际: {}", 
                    fixture.description, expected_activity, activity);
@@ -464,13 +462,13 @@ fn test_mock_monitor_service() {
     let mut mock = MockMonitorServiceImpl::new();
     
     // 设置模拟响应
-    mock.set_response("%0", PaneStatus::Active);
-    mock.set_response("%1", PaneStatus::Idle);
+    mock.set_response("%0", ClaudeState::Working);
+    mock.set_response("%1", ClaudeState::Done);
     
     // 验证设置
     assert_eq!(mock.responses.len(), 2);
-    assert_eq!(mock.responses.get("%0"), Some(&PaneStatus::Active));
-    assert_eq!(mock.responses.get("%1"), Some(&PaneStatus::Idle));
+    assert_eq!(mock.responses.get("%0"), Some(&ClaudeState::Working));
+    assert_eq!(mock.responses.get("%1"), Some(&ClaudeState::Done));
 }
 
 #[tokio::test]
@@ -0,0 +1,238 @@
+use claude_watch::args::Args;
+use claude_watch::config::layers::{LayerStack, Locations};
+use claude_watch::config::validate::has_errors;
+use claude_watch::config::{Config, Diagnostic, Source};
+use claude_watch::monitor::pane_config;
+use claude_watch::testing::ScriptedTerminal;
+use clap::Parser;
+use predicates::prelude::*;
+use std::path::PathBuf;
+
+/// 系统、用户、两个项目目录和 --config 文件各写一层
+struct Layout {
+    dir: tempfile::TempDir,
+}
+
+impl Layout {
+    fn new() -> Self {
+        let layout = Layout { dir: tempfile::tempdir().unwrap() };
+        layout.write(
+            "etc/config.yaml",
+            "llm:\n  backend: none\nmonitoring:\n  interval: 9\n  stuck_sec: 120\n  max_retry: 2\n",
+        );
+        layout.write(
+            "xdg/claude-watch/config.yaml",
+            "llm:\n  backend: openai\n  openai:\n    api_key: sk-user\n    model: gpt-user\nmonitoring:\n  interval: 7\n",
+        );
+        layout.write("work/app/.claude-watch.yaml", "monitoring:\n  stuck_sec: 90\n");
+        layout.write("work/lib/.claude-watch.yaml", "monitoring:\n  interval: 2\n");
+        layout.write("config.yaml", "");
+        std::fs::create_dir_all(layout.path("work/app/src")).unwrap();
+        layout
+    }
+
+    fn path(&self, relative: &str) -> PathBuf {
+        self.dir.path().join(relative)
+    }
+
+    fn write(&self, relative: &str, content: &str) {
+        let path = self.path(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn locations(&self, cwd: &str) -> Locations {
+        Locations {
+            system: self.path("etc/config.yaml"),
+            user: Some(self.path("xdg/claude-watch/config.yaml")),
+            cwd: self.path(cwd),
+        }
+    }
+
+    fn resolve(&self, cwd: &str) -> Result<(Config, Vec<Diagnostic>), Diagnostic> {
+        LayerStack::discover_in(&self.args(&[]), &self.locations(cwd))?.resolve()
+    }
+
+    fn args(&self, extra: &[&str]) -> Args {
+        let config = self.path("config.yaml");
+        let mut args = vec!["claude-watch", "--config", config.to_str().unwrap()];
+        args.extend(extra);
+        Args::parse_from(args)
+    }
+}
+
+fn source_of(stack: &LayerStack, path: &str) -> Source {
+    stack.origin(path).unwrap().source.clone()
+}
+
+/// 测试按优先级逐项合并各层，并记录每个值的来源
+#[test]
+fn test_layers_merge_field_by_field() {
+    let layout = Layout::new();
+    layout.write("config.yaml", "monitoring:\n  max_retry: 5\n");
+    let args = layout.args(&["--stuck-sec", "100"]);
+
+    let stack = LayerStack::discover_in(&args, &layout.locations("work/app/src")).unwrap();
+    assert_eq!(stack.project_file(), Some(layout.path("work/app/.claude-watch.yaml").as_path()));
+    let (config, diagnostics) = stack.resolve().unwrap();
+
+    assert!(!has_errors(&diagnostics), "{:?}", diagnostics);
+    assert_eq!(config.llm.backend, "openai");
+    let openai = config.llm.openai.as_ref().unwrap();
+    assert_eq!((openai.api_key.as_str(), openai.model.as_str()), ("sk-user", "gpt-user"));
+    assert_eq!(openai.api_base, "https://api.openai.com/v1");
+    let monitoring = &config.monitoring;
+    assert_eq!((monitoring.interval, monitoring.stuck_sec, monitoring.max_retry), (7, 100, 5));
+
+    let stack = config.layers.as_ref().unwrap();
+    let user = layout.path("xdg/claude-watch/config.yaml");
+    assert_eq!(source_of(stack, "monitoring.interval"), Source::User(user));
+    assert_eq!(source_of(stack, "monitoring.stuck_sec"), Source::Cli);
+    assert_eq!(source_of(stack, "monitoring.max_retry"), Source::File(layout.path("config.yaml")));
+    assert_eq!(source_of(stack, "tmux.discover_interval"), Source::Default);
+}
+
+/// 测试上层切换 API key 来源或后端时替换下层的相关设置
+#[test]
+fn test_layer_replaces_related_keys() {
+    let layout = Layout::new();
+    layout.write("config.yaml", "llm:\n  openai:\n    api_key_cmd: \"echo sk-cmd\"\n");
+    let (config, diagnostics) = layout.resolve("work").unwrap();
+    assert!(!has_errors(&diagnostics), "{:?}", diagnostics);
+    assert_eq!(config.llm.openai.as_ref().unwrap().api_key, "sk-cmd");
+
+    let describe = config.layers.as_ref().unwrap().describe(&config);
+    let key = describe.iter().find(|value| value.path == "llm.openai.api_key").unwrap();
+    assert_eq!(key.value, "\"[REDACTED]\"");
+    assert!(key.source.starts_with("api_key_cmd (配置文件"), "{}", key.source);
+
+    layout.write("xdg/claude-watch/config.yaml", "llm:\n  backends: [ollama, openai]\n");
+    layout.write("config.yaml", "llm:\n  backend: none\n");
+    let (config, _) = layout.resolve("work").unwrap();
+    assert!(config.llm.backends.is_empty(), "{:?}", config.llm.backends);
+}
+
+/// 测试诊断信息指向设置该值的那一层文件
+#[test]
+fn test_diagnostics_point_at_their_layer() {
+    let layout = Layout::new();
+    layout.write("xdg/claude-watch/config.yaml", "llm:\n  backend: none\nmonitoring:\n  intervall: 3\n");
+    layout.write("work/app/.claude-watch.yaml", "monitoring:\n  interval: 500\n");
+
+    let (_, diagnostics) = layout.resolve("work/app").unwrap();
+    let rendered: Vec<String> = diagnostics.iter().map(|d| d.render("config.yaml")).collect();
+    let user = layout.path("xdg/claude-watch/config.yaml");
+    let project = layout.path("work/app/.claude-watch.yaml");
+    let unknown = format!("{}:4:3: 错误: monitoring.intervall: 未知的配置项，是否是 `interval`？", user.display());
+    assert!(rendered.contains(&unknown), "{:?}", rendered);
+    let timing = format!("{}:2:3: 错误: monitoring.interval", project.display());
+    assert!(rendered.iter().any(|line| line.starts_with(&timing)), "{:?}", rendered);
+
+    layout.write("work/app/.claude-watch.yaml", "monitoring:\n  interval: soon\n");
+    let error = layout.resolve("work/app").unwrap_err();
+    assert_eq!(error.file, Some(project.display().to_string()));
+    assert_eq!(error.location.map(|(line, _)| line), Some(2));
+}
+
+/// 测试每个窗格按自己的工作目录使用项目配置
+#[tokio::test]
+async fn test_pane_uses_project_of_its_directory() {
+    let layout = Layout::new();
+    let (config, _) = layout.resolve("work/app/src").unwrap();
+    let terminal = ScriptedTerminal::new();
+    terminal.set_dir("%1", layout.path("work/lib"));
+    terminal.set_dir("%2", layout.path("work/app/src"));
+    terminal.set_dir("%3", layout.path("elsewhere"));
+
+    let in_lib = pane_config(&config, &terminal, "%1").await;
+    assert_eq!((in_lib.monitoring.interval, in_lib.monitoring.stuck_sec), (2, 120));
+    assert_eq!(in_lib.tmux.pane, "%1");
+    let in_app = pane_config(&config, &terminal, "%2").await;
+    assert_eq!((in_app.monitoring.interval, in_app.monitoring.stuck_sec), (7, 90));
+    let outside = pane_config(&config, &terminal, "%3").await;
+    assert_eq!(outside.monitoring.stuck_sec, 120);
+    let unknown = pane_config(&config, &terminal, "%4").await;
+    assert_eq!(unknown.monitoring.stuck_sec, 90);
+
+    // 无效的项目配置不影响窗格使用全局配置
+    layout.write("work/lib/.claude-watch.yaml", "monitoring:\n  interval: [\n");
+    assert_eq!(config.in_dir(&layout.path("work/lib")).monitoring.interval, 7);
+}
+
+/// 测试 config show --resolved 输出有效配置和来源，并隐藏 API key
+#[test]
+fn test_config_show_command() {
+    let layout = Layout::new();
+    let run = |args: &[&str]| {
+        assert_cmd::Command::cargo_bin("claude-watch")
+            .unwrap()
+            .env("XDG_CONFIG_HOME", layout.path("xdg"))
+            .env_remove("OPENAI_API_KEY")
+            .current_dir(layout.path("work/app/src"))
+            .args(args)
+            .assert()
+            .success()
+    };
+    let project = layout.path("work/app/.claude-watch.yaml");
+
+    run(&["--interval", "3", "config", "show", "--resolved"]).stdout(
+        predicate::str::contains(format!("monitoring.stuck_sec: 90  # 项目配置 {}", project.display()))
+            .and(predicate::str::contains("monitoring.interval: 3  # 命令行参数"))
+            .and(predicate::str::contains("llm.openai.model: \"gpt-user\"  # 用户配置"))
+            .and(predicate::str::contains("[REDACTED]"))
+            .and(predicate::str::contains("sk-user").not()),
+    );
+    run(&["config", "show"])
+        .stdout(predicate::str::contains("stuck_sec: 90").and(predicate::str::contains("sk-user").not()));
+}
+
+/// 测试当前目录默认的 config.yaml 低于项目配置，只有 --config 指定的文件高于项目配置
+#[test]
+fn test_implicit_config_ranks_below_project() {
+    let layout = Layout::new();
+    layout.write("work/app/config.yaml", "monitoring:\n  stuck_sec: 30\n  max_retry: 4\n");
+    let run = |args: &[&str]| {
+        assert_cmd::Command::cargo_bin("claude-watch")
+            .unwrap()
+            .env("XDG_CONFIG_HOME", layout.path("xdg"))
+            .env_remove("OPENAI_API_KEY")
+            .current_dir(layout.path("work/app"))
+            .args(args)
+            .assert()
+            .success()
+    };
+    let project = layout.path("work/app/.claude-watch.yaml");
+
+    run(&["config", "show", "--resolved"]).stdout(
+        predicate::str::contains(format!("monitoring.stuck_sec: 90  # 项目配置 {}", project.display()))
+            .and(predicate::str::contains("monitoring.max_retry: 4  # 当前目录配置 config.yaml")),
+    );
+    run(&["--config", "config.yaml", "config", "show", "--resolved"]).stdout(
+        predicate::str::contains("monitoring.stuck_sec: 30  # 配置文件 config.yaml")
+            .and(predicate::str::contains("monitoring.max_retry: 4  # 配置文件 config.yaml")),
+    );
+}
+
+/// 测试 api_key_cmd 每次加载只执行一次，各窗格的项目配置复用已读取的 key
+#[tokio::test]
+async fn test_key_command_runs_once_per_load() {
+    let layout = Layout::new();
+    let count = layout.path("runs");
+    let command = format!("echo run >> {}; echo sk-cmd", count.display());
+    layout.write("config.yaml", &format!("llm:\n  openai:\n    api_key_cmd: \"{}\"\n", command));
+    let runs = || std::fs::read_to_string(&count).unwrap_or_default().lines().count();
+
+    let (config, _) = layout.resolve("work").unwrap();
+    let terminal = ScriptedTerminal::new();
+    terminal.set_dir("%1", layout.path("work/lib"));
+    terminal.set_dir("%2", layout.path("work/app/src"));
+    for pane in ["%1", "%2", "%1"] {
+        let pane_config = pane_config(&config, &terminal, pane).await;
+        assert_eq!(pane_config.llm.openai.as_ref().unwrap().api_key, "sk-cmd");
+    }
+    assert_eq!(runs(), 1);
+
+    // 重新加载配置时再读取一次
+    layout.resolve("work").unwrap();
+    assert_eq!(runs(), 2);
+}
@@ -0,0 +1,126 @@
+use claude_watch::args::Args;
+use claude_watch::config::resolve::interpolate_with;
+use claude_watch::config::validate::has_errors;
+use claude_watch::config::{Config, OpenAiConfig};
+use clap::Parser;
+
+fn lookup(name: &str) -> Option<String> {
+    match name {
+        "API_KEY" => Some("sk-env".to_string()),
+        "INTERVAL" => Some("3".to_string()),
+        "EMPTY" => Some(String::new()),
+        _ => None,
+    }
+}
+
+fn openai(api_key: &str) -> OpenAiConfig {
+    OpenAiConfig {
+        api_key: api_key.to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_base: "https://api.openai.com/v1".to_string(),
+        model: "gpt-4o-mini".to_string(),
+    }
+}
+
+/// 测试 ${VAR}、${VAR:-default}、转义和注释
+#[test]
+fn test_interpolation() {
+    let content = "key: \"${API_KEY}\"\nurl: ${BASE:-http://localhost:11434}/v1\nempty: \"${EMPTY}\" \"${EMPTY:-d}\"\n\
+                   # ${NOT_SET} in a comment\nprice: $5 $${API_KEY}\n";
+    assert_eq!(
+        interpolate_with(content, lookup).unwrap(),
+        "key: \"sk-env\"\nurl: http://localhost:11434/v1\nempty: \"\" \"d\"\n# ${NOT_SET} in a comment\nprice: $5 ${API_KEY}\n"
+    );
+}
+
+/// 测试未设置的变量和不完整的表达式报告行列号
+#[test]
+fn test_interpolation_errors() {
+    let error = interpolate_with("llm:\n  backend: ${BACKEND}\n", lookup).unwrap_err();
+    assert_eq!(error.location, Some((2, 12)));
+    assert!(error.message.contains("BACKEND"), "{}", error.message);
+
+    assert!(interpolate_with("key: ${API_KEY\n", lookup).is_err());
+    assert!(interpolate_with("key: ${API-KEY}\n", lookup).is_err());
+}
+
+/// 测试插值后按 YAML 类型解析
+#[test]
+fn test_interpolated_values_are_typed() {
+    let content = "llm:\n  backend: none\nmonitoring:\n  interval: ${INTERVAL}\n  stuck_sec: ${STUCK:-60}\n  max_retry: 3\ntmux:\n  pane: \"%0\"\n";
+    let config: Config = serde_yaml::from_str(&interpolate_with(content, lookup).unwrap()).unwrap();
+    assert_eq!((config.monitoring.interval, config.monitoring.stuck_sec), (3, 60));
+}
+
+/// 测试从文件和命令读取 API key
+#[test]
+fn test_api_key_file_and_cmd() {
+    let dir = tempfile::tempdir().unwrap();
+    let key_file = dir.path().join("openai.key");
+    std::fs::write(&key_file, "sk-from-file\n").unwrap();
+    let mut config = Config::default();
+    config.llm.backend = "openai".to_string();
+
+    config.llm.openai = Some(OpenAiConfig { api_key_file: Some(key_file.to_string_lossy().into_owned()), ..openai("") });
+    assert!(config.resolve_secrets().is_empty());
+    assert_eq!(config.llm.openai.as_ref().unwrap().api_key, "sk-from-file");
+
+    config.llm.openai = Some(OpenAiConfig { api_key_cmd: Some("printf 'sk-from-cmd\\nmetadata\\n'".to_string()), ..openai("") });
+    assert!(config.resolve_secrets().is_empty());
+    assert_eq!(config.llm.openai.as_ref().unwrap().api_key, "sk-from-cmd");
+}
+
+/// 测试读取失败、同时设置多个来源，以及不解析未使用的后端
+#[test]
+fn test_secret_errors() {
+    let mut config = Config::default();
+    config.llm.backend = "openai".to_string();
+
+    config.llm.openai = Some(OpenAiConfig { api_key_cmd: Some("echo locked >&2; exit 3".to_string()), ..openai("") });
+    let diagnostics = config.resolve_secrets();
+    assert_eq!(diagnostics[0].path, "llm.openai.api_key_cmd");
+    assert!(diagnostics[0].message.contains("locked"), "{}", diagnostics[0].message);
+
+    config.llm.openai = Some(OpenAiConfig { api_key_file: Some("/nonexistent/key".to_string()), ..openai("sk-x") });
+    assert_eq!(config.resolve_secrets()[0].path, "llm.openai");
+
+    config.llm.openai = Some(OpenAiConfig { api_key_cmd: Some("exit 1".to_string()), ..openai("") });
+    config.llm.backend = "none".to_string();
+    assert!(config.resolve_secrets().is_empty());
+}
+
+/// 测试配置文件、命令行参数和密钥走同一条解析路径
+#[test]
+fn test_resolve_from_file_and_env() {
+    std::env::set_var("CLAUDE_WATCH_TEST_OPENAI_KEY", "sk-interpolated");
+    std::env::set_var("ANTHROPIC_API_KEY", "sk-ant-legacy");
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.yaml");
+    std::fs::write(
+        &path,
+        "llm:\n  backend: openai\n  openai:\n    api_key: \"${CLAUDE_WATCH_TEST_OPENAI_KEY}\"\n    api_base: \"https://api.openai.com/v1\"\n    model: \"gpt-4o-mini\"\n\
+         monitoring:\n  interval: 5\n  stuck_sec: 60\n  max_retry: 3\ntmux:\n  pane: \"%0\"\n",
+    )
+    .unwrap();
+
+    let args = Args::parse_from(["claude-watch", "--config", path.to_str().unwrap(), "--interval", "2"]);
+    let (config, diagnostics) = Config::resolve(&args).unwrap();
+    assert!(!has_errors(&diagnostics), "{:?}", diagnostics);
+    assert_eq!(config.llm.openai.unwrap().api_key, "sk-interpolated");
+    assert_eq!(config.monitoring.interval, 2);
+
+    // 没有配置文件时使用兼容的环境变量
+    let missing = dir.path().join("missing.yaml");
+    let args = Args::parse_from(["claude-watch", "--config", missing.to_str().unwrap()]);
+    assert!(Config::resolve(&args).is_err());
+    let args = Args::parse_from(["claude-watch", "--backend", "anthropic"]);
+    let (config, diagnostics) = Config::resolve(&args).unwrap();
+    assert!(!has_errors(&diagnostics), "{:?}", diagnostics);
+    assert_eq!(config.llm.anthropic.unwrap().api_key, "sk-ant-legacy");
+    assert_eq!(Config::from_args(&args).llm.anthropic.unwrap().api_key, "sk-ant-legacy");
+
+    std::fs::write(&path, "llm:\n  backend: ${CLAUDE_WATCH_TEST_UNSET}\n").unwrap();
+    let args = Args::parse_from(["claude-watch", "--config", path.to_str().unwrap()]);
+    assert_eq!(Config::resolve(&args).unwrap_err().location, Some((2, 12)));
+}
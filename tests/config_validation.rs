@@ -0,0 +1,188 @@
+use claude_watch::config::validate::{check_panes, has_errors, locate, parse};
+use claude_watch::config::{Config, OpenAiConfig, PaneConfig, Severity};
+use claude_watch::testing::ScriptedTerminal;
+use predicates::prelude::*;
+
+const BASE: &str = "llm:
+  backend: none
+monitoring:
+  interval: 5
+  stuck_sec: 60
+  max_retry: 3
+tmux:
+  pane: \"%0\"
+";
+
+fn paths(config: &Config) -> Vec<String> {
+    config.validate().into_iter().map(|d| d.path).collect()
+}
+
+/// 测试示例配置文件没有任何问题
+#[test]
+fn test_example_config_is_clean() {
+    let content = std::fs::read_to_string("config.example.yaml").unwrap();
+    let (config, diagnostics) = parse(&content).unwrap();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    assert!(config.validate().is_empty(), "{:?}", config.validate());
+}
+
+/// 测试报告未知配置项的位置和拼写建议
+#[test]
+fn test_unknown_keys() {
+    let content = BASE.replace("  max_retry: 3", "  max_retry: 3\n  stuck_secs: 30")
+        + "  panes:\n    - \"%1\"\n    - id: \"%2\"\n      intervall: 2\n  nothing:\n";
+
+    let (_, diagnostics) = parse(&content).unwrap();
+
+    assert_eq!(diagnostics.len(), 2, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].path, "monitoring.stuck_secs");
+    assert_eq!(diagnostics[0].location, Some((7, 3)));
+    assert!(diagnostics[0].message.contains("`stuck_sec`"), "{}", diagnostics[0].message);
+    assert_eq!(diagnostics[1].path, "tmux.panes[1].intervall");
+    assert_eq!(diagnostics[1].location, Some((13, 7)));
+    assert!(has_errors(&diagnostics));
+}
+
+/// 测试语法和类型错误带有行列号
+#[test]
+fn test_parse_errors_have_location() {
+    let error = parse(&BASE.replace("interval: 5", "interval: soon")).unwrap_err();
+    assert_eq!(error.severity, Severity::Error);
+    assert_eq!(error.location.map(|(line, _)| line), Some(4));
+    assert!(!error.message.contains(" at line "), "{}", error.message);
+
+    let error = parse("llm: [unclosed\n").unwrap_err();
+    assert!(error.location.is_some(), "{:?}", error);
+}
+
+/// 测试缺少后端配置段、空的 API key 和不支持的后端
+#[test]
+fn test_backend_checks() {
+    let mut config = Config::default();
+    config.llm.backend = "openai".to_string();
+    config.llm.openai = None;
+    assert_eq!(paths(&config), vec!["llm.backend"]);
+
+    config.llm.openai = Some(OpenAiConfig {
+        api_key: " ".to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_base: "https://api.openai.com/v1".to_string(),
+        model: "gpt-4o-mini".to_string(),
+    });
+    assert_eq!(paths(&config), vec!["llm.openai.api_key"]);
+    assert!(has_errors(&config.validate()));
+
+    // 没有使用的后端不检查 API key
+    config.llm.backend = "gemini".to_string();
+    let diagnostics = config.validate();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].path, "llm.backend");
+    assert!(diagnostics[0].message.contains("gemini"));
+}
+
+/// 测试检查间隔和卡住判定时间，包括窗格级覆盖
+#[test]
+fn test_timing_checks() {
+    let mut config = Config::default();
+    config.monitoring.interval = 120;
+    assert_eq!(paths(&config), vec!["monitoring.interval"]);
+
+    config.monitoring.interval = 5;
+    config.tmux.panes = vec![
+        PaneConfig::new("%1"),
+        PaneConfig { stuck_sec: Some(2), ..PaneConfig::new("%2") },
+        PaneConfig { interval: Some(0), ..PaneConfig::new("%3") },
+    ];
+    assert_eq!(paths(&config), vec!["tmux.panes[1].stuck_sec", "tmux.panes[2].interval"]);
+}
+
+/// 测试按路径定位到 YAML 中的行列
+#[test]
+fn test_locate() {
+    let content = "llm:\n  backend: none\n  backends:\n  - ollama\n  - name: openai\n    timeout: 5\ntmux:\n  pane: \"%0\"\n";
+    assert_eq!(locate(content, "llm.backend"), Some((2, 3)));
+    assert_eq!(locate(content, "llm.backends[1]"), Some((5, 3)));
+    assert_eq!(locate(content, "llm.backends[1].timeout"), Some((6, 5)));
+    assert_eq!(locate(content, "tmux.pane"), Some((8, 3)));
+    // 找不到时退回到最近的上级
+    assert_eq!(locate(content, "tmux.discover_interval"), Some((7, 1)));
+}
+
+/// 测试 tmux 中找不到的窗格
+#[tokio::test]
+async fn test_unreachable_panes() {
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%1", "");
+    let mut config = Config::default();
+    config.tmux.panes = vec![PaneConfig::new("%1"), PaneConfig::new("%7")];
+
+    let diagnostics = check_panes(&config, &terminal).await;
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].path, "tmux.panes[1]");
+
+    config.tmux.discover = true;
+    assert!(check_panes(&config, &terminal).await.is_empty());
+}
+
+/// 测试 Config::load 不再对缺失或有错的文件静默使用默认配置
+#[test]
+fn test_load_rejects_missing_and_invalid_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.yaml");
+    assert!(Config::load(path.to_str().unwrap()).is_err());
+
+    std::fs::write(&path, BASE.replace("backend: none", "backend: none\n  bakend: openai")).unwrap();
+    let error = Config::load(path.to_str().unwrap()).unwrap_err().to_string();
+    assert!(error.contains("config.yaml:3:3") && error.contains("llm.bakend"), "{}", error);
+
+    std::fs::write(&path, BASE).unwrap();
+    assert_eq!(Config::load(path.to_str().unwrap()).unwrap().llm.backend, "none");
+}
+
+/// 测试 config check 子命令的输出和退出码，以及启动时拒绝无效配置
+#[test]
+fn test_config_check_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.yaml");
+    std::fs::write(&path, BASE.replace("interval: 5", "interval: 90")).unwrap();
+    let config = path.to_str().unwrap();
+
+    assert_cmd::Command::cargo_bin("claude-watch")
+        .unwrap()
+        .args(["--config", config, "config", "check"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("config.yaml:4:3").and(predicate::str::contains("monitoring.interval")));
+
+    assert_cmd::Command::cargo_bin("claude-watch")
+        .unwrap()
+        .args(["--config", config, "--once"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("monitoring.interval"));
+}
+
+/// 测试 --config 指定的文件不存在时报错，而不是退回到其他配置层
+#[test]
+fn test_missing_config_file_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("xdg/claude-watch")).unwrap();
+    let user = BASE.replace("pane: \"%0\"", "discover: true");
+    std::fs::write(dir.path().join("xdg/claude-watch/config.yaml"), user).unwrap();
+    let run = |args: &[&str]| {
+        let mut command = assert_cmd::Command::cargo_bin("claude-watch").unwrap();
+        command.current_dir(dir.path()).env("XDG_CONFIG_HOME", dir.path().join("xdg")).args(args);
+        command.assert()
+    };
+
+    run(&["--config", "typo.yaml", "config", "check"])
+        .code(1)
+        .stdout(predicate::str::contains("typo.yaml: 错误: 配置文件不存在"));
+    run(&["--config", "typo.yaml", "status"]).code(1).stderr(predicate::str::contains("typo.yaml"));
+
+    // 没有指定时 config.yaml 可以不存在，但会给出警告
+    run(&["config", "check"])
+        .code(0)
+        .stdout(predicate::str::contains("config.yaml: 警告: 配置文件不存在"));
+}
@@ -0,0 +1,90 @@
+use claude_watch::config::Config;
+use claude_watch::discovery::{discover_claude_panes, is_claude_command, is_claude_pane, looks_like_claude_screen};
+use claude_watch::monitor::{run_monitoring_loop, PaneState};
+use claude_watch::terminal::PaneInfo;
+use claude_watch::testing::ScriptedTerminal;
+use claude_watch::tmux::parse_list_panes;
+
+/// 测试解析 tmux list-panes 输出
+#[test]
+fn test_parse_list_panes() {
+    let output = "%0\twork\tclaude\t1234\n%1\twork\tbash\t1240\n\n%2\tbroken\n";
+    let panes = parse_list_panes(output);
+
+    assert_eq!(panes.len(), 2, "格式不完整的行应该被跳过");
+    assert_eq!(
+        panes[0],
+        PaneInfo {
+            id: "%0".to_string(),
+            session: "work".to_string(),
+            current_command: "claude".to_string(),
+            pid: 1234,
+        }
+    );
+    assert_eq!(panes[1].current_command, "bash");
+}
+
+/// 测试进程名称识别
+#[test]
+fn test_is_claude_command() {
+    assert!(is_claude_command("claude"));
+    assert!(is_claude_command("Claude"));
+    assert!(!is_claude_command("node"));
+    assert!(!is_claude_command("bash"));
+    assert!(!is_claude_command("vim"));
+}
+
+/// 测试屏幕指纹识别
+#[test]
+fn test_looks_like_claude_screen() {
+    assert!(looks_like_claude_screen("╭──────╮\n│ >    │\n╰──────╯\n  ? for shortcuts"));
+    assert!(looks_like_claude_screen("* Herding… (169s · ↑ 8.7k tokens · esc to interrupt)"));
+    assert!(!looks_like_claude_screen("user@host:~$ ls\nCargo.toml  src"));
+}
+
+/// 测试 node 启动的 Claude Code 通过屏幕识别
+#[test]
+fn test_is_claude_pane_by_screen() {
+    let info = PaneInfo {
+        id: "%3".to_string(),
+        session: "dev".to_string(),
+        current_command: "node".to_string(),
+        pid: 42,
+    };
+
+    assert!(is_claude_pane(&info, "✻ Welcome to Claude Code!"));
+    assert!(!is_claude_pane(&info, "Server listening on :3000"));
+}
+
+/// 测试自动发现只返回 Claude Code 窗格
+#[tokio::test]
+async fn test_discover_claude_panes() {
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%0", "anything");
+    terminal.set_command("%0", "claude");
+    terminal.push_screen("%1", "user@host:~$ ");
+    terminal.push_screen("%2", "  ? for shortcuts");
+    terminal.set_command("%2", "node");
+
+    let ids: Vec<String> = discover_claude_panes(&terminal)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|info| info.id)
+        .collect();
+
+    assert_eq!(ids, vec!["%0".to_string(), "%2".to_string()]);
+}
+
+/// 测试窗格关闭后监控循环自行结束
+#[tokio::test]
+async fn test_monitoring_loop_stops_when_pane_gone() {
+    let mut config = Config::default();
+    config.tmux.pane = "%5".to_string();
+    let terminal = ScriptedTerminal::new();
+    let mut state = PaneState::new();
+
+    let result = run_monitoring_loop(&config, &terminal, &mut state).await;
+
+    assert!(result.is_ok());
+}
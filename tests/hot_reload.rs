@@ -0,0 +1,118 @@
+use claude_watch::config::{Config, PaneConfig, RecoveryAction, RecoveryStep, SuccessCheck};
+use claude_watch::monitor::{run_monitoring_loop, watch_panes, PaneState};
+use claude_watch::reload::{self, ConfigUpdates, FileWatcher};
+use claude_watch::shutdown;
+use claude_watch::testing::{ScriptedTerminal, SentInput};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 启发式检查会判定为出错的屏幕
+const ERROR_SCREEN: &str = "Error: compilation failed";
+
+/// 一小时也等不完的配置
+fn slow_config(pane: &str) -> Config {
+    let mut config = Config::default();
+    config.tmux.pane = pane.to_string();
+    config.llm.backend = "none".to_string();
+    config.monitoring.interval = 3600;
+    config.monitoring.stuck_sec = 3600;
+    config
+}
+
+/// 测试轮询修改时间检测文件的修改、创建和删除
+#[test]
+fn test_file_watcher_detects_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let existing = dir.path().join("config.yaml");
+    let missing = dir.path().join(".claude-watch.yaml");
+    std::fs::write(&existing, "llm:\n  backend: none\n").unwrap();
+    let mut watcher = FileWatcher::new([existing.clone(), missing.clone(), existing.clone()]);
+    assert_eq!(watcher.paths().count(), 2);
+    assert!(watcher.poll().is_empty());
+
+    std::fs::write(&existing, "llm:\n  backend: openai\n").unwrap();
+    assert_eq!(watcher.poll(), vec![existing.clone()]);
+    assert!(watcher.poll().is_empty());
+
+    std::fs::write(&missing, "monitoring:\n  interval: 2\n").unwrap();
+    std::fs::remove_file(&existing).unwrap();
+    assert_eq!(watcher.poll(), vec![existing, missing]);
+}
+
+/// 测试配置通知只交付一次最新的配置，固定配置永远不更新
+#[tokio::test]
+async fn test_config_updates() {
+    let (publisher, mut updates) = reload::channel(slow_config("%0"));
+    assert!(updates.take().is_none());
+
+    let mut first = slow_config("%0");
+    first.monitoring.interval = 1;
+    let mut second = slow_config("%0");
+    second.monitoring.interval = 2;
+    publisher.publish(first);
+    publisher.publish(second);
+    assert_eq!(updates.take().unwrap().monitoring.interval, 2);
+    assert!(updates.take().is_none());
+    assert_eq!(publisher.current().monitoring.interval, 2);
+
+    let mut fixed = ConfigUpdates::fixed(slow_config("%0"));
+    assert!(fixed.take().is_none());
+    assert!(fixed.changed().await.is_none());
+    assert_eq!(fixed.current().monitoring.stuck_sec, 3600);
+}
+
+/// 测试监控循环应用新的卡住判定时间和恢复步骤，且不重置已经累计的无活动时间
+#[tokio::test]
+async fn test_monitoring_loop_applies_new_config_without_resetting_timer() {
+    let config = slow_config("%0");
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%0", ERROR_SCREEN);
+    let (publisher, updates) = reload::channel(config.clone());
+    let (trigger, shutdown) = shutdown::channel();
+    let mut state = PaneState::with_updates(shutdown, updates);
+    state.content_changed(ERROR_SCREEN);
+    state.last_active = Instant::now() - Duration::from_secs(30);
+
+    let mut reloaded = config.clone();
+    reloaded.monitoring.stuck_sec = 10;
+    reloaded.recovery.steps = vec![RecoveryStep {
+        wait: Some(0),
+        check: SuccessCheck::None,
+        ..RecoveryStep::new(RecoveryAction::SendText { text: "go on".to_string() })
+    }];
+    publisher.publish(reloaded);
+
+    let stop = async {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        trigger.trigger();
+    };
+    let run = tokio::time::timeout(Duration::from_secs(5), run_monitoring_loop(&config, &terminal, &mut state));
+    let (result, _) = tokio::join!(run, stop);
+
+    assert!(result.expect("监控循环应该在退出通知后结束").is_ok());
+    assert_eq!(terminal.sent(), vec![SentInput::Text { pane: "%0".to_string(), text: "go on".to_string() }]);
+}
+
+/// 测试窗格列表变化时停止被移除的窗格、监控新加入的窗格
+#[tokio::test]
+async fn test_watch_panes_follows_pane_list() {
+    let mut config = slow_config("%0");
+    config.tmux.panes = vec![PaneConfig::new("%1")];
+    let terminal = Arc::new(ScriptedTerminal::new());
+    terminal.push_screen("%1", "> first");
+    let (publisher, updates) = reload::channel(config.clone());
+    let (_trigger, shutdown) = shutdown::channel();
+
+    let handle = tokio::spawn(watch_panes(updates, terminal, shutdown));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!handle.is_finished());
+
+    // %1 被移出列表后停止监控，新加入的 %9 不存在，监控任务立即结束
+    config.tmux.panes = vec![PaneConfig::new("%9")];
+    publisher.publish(config);
+
+    let result = tokio::time::timeout(Duration::from_secs(5), handle)
+        .await
+        .expect("被移除的窗格应该停止监控");
+    assert!(result.unwrap().is_ok());
+}
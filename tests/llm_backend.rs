@@ -0,0 +1,187 @@
+use claude_watch::config::{BackendConfig, Config, OllamaConfig, OpenAiConfig};
+use claude_watch::llm::create_backend;
+use claude_watch::prompt::PromptContext;
+use claude_watch::ClaudeState;
+use serde_json::json;
+use std::time::{Duration, Instant};
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn openai_config(server: &MockServer) -> Config {
+    let mut config = Config::default();
+    config.llm.backend = "openai".to_string();
+    config.llm.openai = Some(OpenAiConfig {
+        api_key: "sk-test".to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_base: format!("{}/v1", server.uri()),
+        model: "gpt-test".to_string(),
+    });
+    config
+}
+
+fn chat_response(content: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "choices": [{"message": {"role": "assistant", "content": content}}]
+    }))
+}
+
+/// 测试按名称选择后端，未知后端和缺少配置时报错
+#[test]
+fn test_create_backend_by_name() {
+    let mut config = Config::default();
+    config.llm.openai = None;
+
+    assert_eq!(create_backend("none", &config.llm).unwrap().name(), "none");
+    assert_eq!(create_backend("ollama", &config.llm).unwrap().name(), "ollama");
+    assert!(create_backend("openai", &config.llm).is_err());
+    assert!(create_backend("gemini", &config.llm).is_err());
+}
+
+/// 测试不带协议或端口的 Ollama 地址也能创建客户端，只有不在本机时才算远程后端
+#[test]
+fn test_ollama_url_without_scheme() {
+    let mut config = Config::default();
+    config.llm.ollama = Some(OllamaConfig { url: "localhost".to_string(), model: "qwen2.5:3b".to_string() });
+
+    assert!(!create_backend("ollama", &config.llm).unwrap().is_remote());
+    for (url, remote) in [("http://127.0.0.1:11434", false), ("https://gpu-box.lan:11434", true), ("10.0.0.5", true)] {
+        config.llm.ollama = Some(OllamaConfig { url: url.to_string(), model: "qwen2.5:3b".to_string() });
+        assert_eq!(create_backend("ollama", &config.llm).unwrap().is_remote(), remote, "{}", url);
+    }
+}
+
+/// 测试 none 后端使用启发式判断，且无法生成激活消息
+#[tokio::test]
+async fn test_heuristic_backend() {
+    let backend = create_backend("none", &Config::default().llm).unwrap();
+
+    assert_eq!(backend.classify("判断状态", "Error: compilation failed").await.map(|verdict| verdict.state), Ok(ClaudeState::Errored));
+    assert!(backend.generate("激活助手", "激活一下").await.is_err());
+}
+
+/// 测试 OpenAI 兼容后端判断状态
+#[tokio::test]
+async fn test_openai_classify() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("Authorization", "Bearer sk-test"))
+        .and(body_partial_json(json!({
+            "model": "gpt-test",
+            "max_tokens": 256,
+            "response_format": {"type": "json_object"}
+        })))
+        .respond_with(chat_response(r#"{"state": "DONE", "confidence": 0.9, "reason": "出现完成总结"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let config = openai_config(&server);
+
+    let backend = create_backend("openai", &config.llm).unwrap();
+    assert_eq!(
+        backend.classify("判断状态", "✅ Task completed").await.map(|verdict| verdict.state),
+        Ok(ClaudeState::Done)
+    );
+}
+
+/// 测试 content 为空时视为卡住，未知标签报错
+#[tokio::test]
+async fn test_openai_classify_empty_and_unknown() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(chat_response(""))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(chat_response("MAYBE"))
+        .mount(&server)
+        .await;
+    let backend = create_backend("openai", &openai_config(&server).llm).unwrap();
+
+    assert_eq!(backend.classify("判断状态", "screen").await.map(|verdict| verdict.state), Ok(ClaudeState::Stuck));
+    assert!(backend.classify("判断状态", "screen").await.is_err());
+}
+
+/// 测试激活消息和状态判断走同一个后端
+#[tokio::test]
+async fn test_openai_generate_activation() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({"max_tokens": 100})))
+        .respond_with(chat_response("  请继续你的工作\n"))
+        .mount(&server)
+        .await;
+    let config = openai_config(&server);
+
+    let message = claude_watch::ask_llm_for_activation(&PromptContext::new("%0", "等待中"), &config).await;
+    assert_eq!(message, Ok("请继续你的工作".to_string()));
+}
+
+/// 测试 HTTP 错误状态会带上响应内容
+#[tokio::test]
+async fn test_openai_http_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(401).set_body_string("invalid api key"))
+        .mount(&server)
+        .await;
+    let backend = create_backend("openai", &openai_config(&server).llm).unwrap();
+
+    let error = backend.classify("判断状态", "screen").await.unwrap_err();
+    assert!(error.contains("401") && error.contains("invalid api key"), "{}", error);
+}
+
+/// 测试 HTTP 请求在降级链的超时时间内结束
+#[tokio::test]
+async fn test_openai_request_uses_chain_timeout() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+    let mut config = openai_config(&server);
+    config.llm.backends = vec![BackendConfig { timeout: Some(1), ..BackendConfig::new("openai") }];
+    let backend = create_backend("openai", &config.llm).unwrap();
+
+    let started = Instant::now();
+    let error = backend.classify("判断状态", "screen").await.unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(3), "{:?}", started.elapsed());
+    assert!(error.contains("HTTP 请求失败"), "{}", error);
+}
+
+/// 测试 Ollama 后端的状态判断和激活消息
+#[tokio::test]
+async fn test_ollama_backend() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/generate"))
+        .and(body_partial_json(json!({"model": "qwen-test", "format": "json", "options": {"num_predict": 256}})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "qwen-test",
+            "created_at": "2025-01-01T00:00:00Z",
+            "response": "STUCK",
+            "done": true
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/generate"))
+        .and(body_partial_json(json!({"options": {"num_predict": 100}})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "qwen-test",
+            "created_at": "2025-01-01T00:00:00Z",
+            "response": "请继续处理任务",
+            "done": true
+        })))
+        .mount(&server)
+        .await;
+    let mut config = Config::default();
+    config.llm.ollama = Some(OllamaConfig { url: server.uri(), model: "qwen-test".to_string() });
+
+    let backend = create_backend("ollama", &config.llm).unwrap();
+    assert_eq!(backend.classify("判断状态", "screen").await.map(|verdict| verdict.state), Ok(ClaudeState::Stuck));
+    assert_eq!(backend.generate("激活助手", "激活一下").await, Ok("请继续处理任务".to_string()));
+}
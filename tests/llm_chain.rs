@@ -0,0 +1,202 @@
+use claude_watch::config::{AnthropicConfig, BackendConfig, Config, OllamaConfig, OpenAiConfig, PaneConfig};
+use claude_watch::llm::chain::retry_delay;
+use claude_watch::llm::LlmChain;
+use claude_watch::ClaudeState;
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// OpenAI 指向 `openai`，Anthropic 指向 `anthropic`，按 `chain` 的顺序降级
+fn chain_config(openai: &MockServer, anthropic: &MockServer, chain: &[&str]) -> Config {
+    let mut config = Config::default();
+    config.llm.openai = Some(OpenAiConfig {
+        api_key: "sk-test".to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_base: openai.uri(),
+        model: "gpt-test".to_string(),
+    });
+    config.llm.anthropic = Some(AnthropicConfig {
+        api_key: "sk-ant-test".to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        base_url: anthropic.uri(),
+        model: "claude-test".to_string(),
+        version: "2023-06-01".to_string(),
+    });
+    config.llm.backends = chain.iter().map(|name| BackendConfig::new(name)).collect();
+    config.llm.retries = 0;
+    config.llm.retry_backoff_ms = 10;
+    config
+}
+
+async fn mount_openai_error(server: &MockServer, expected_calls: u64) {
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("unavailable"))
+        .expect(expected_calls)
+        .mount(server)
+        .await;
+}
+
+async fn mount_anthropic_text(server: &MockServer, text: &str) {
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "type": "message",
+            "content": [{"type": "text", "text": text}]
+        })))
+        .mount(server)
+        .await;
+}
+
+/// 测试解析降级链，条目可以是名称或带覆盖项的映射
+#[test]
+fn test_parse_backend_chain() {
+    let yaml = r#"
+llm:
+  backend: ollama
+  backends:
+    - ollama
+    - name: openrouter
+      timeout: 5
+      retries: 0
+    - none
+  timeout: 20
+  retries: 2
+  circuit_breaker:
+    failures: 5
+monitoring:
+  interval: 5
+  stuck_sec: 60
+  max_retry: 3
+tmux:
+  pane: "%0"
+"#;
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+    let names: Vec<String> = config.llm.chain().into_iter().map(|b| b.name).collect();
+
+    assert_eq!(names, vec!["ollama", "openrouter", "none"]);
+    assert_eq!(config.llm.timeout_for("ollama"), Duration::from_secs(20));
+    assert_eq!(config.llm.timeout_for("openrouter"), Duration::from_secs(5));
+    assert_eq!((config.llm.retries_for("ollama"), config.llm.retries_for("openrouter")), (2, 0));
+    assert_eq!((config.llm.circuit_breaker.failures, config.llm.circuit_breaker.cooldown), (5, 300));
+}
+
+/// 测试没有配置降级链时只使用 backend，窗格覆盖后端时替换整个链
+#[test]
+fn test_chain_defaults_and_pane_override() {
+    let mut config = Config::default();
+    assert_eq!(config.llm.chain(), vec![BackendConfig::new("ollama")]);
+
+    config.llm.backends = vec![BackendConfig::new("openai"), BackendConfig::new("none")];
+    let pane = PaneConfig { backend: Some("anthropic".to_string()), ..PaneConfig::new("%1") };
+    assert_eq!(config.for_pane(&pane).llm.chain(), vec![BackendConfig::new("anthropic")]);
+}
+
+/// 测试第一个后端失败（含重试）后使用下一个后端
+#[tokio::test]
+async fn test_falls_through_to_next_backend() {
+    let (openai, anthropic) = (MockServer::start().await, MockServer::start().await);
+    mount_openai_error(&openai, 3).await;
+    mount_anthropic_text(&anthropic, "DONE").await;
+    let mut config = chain_config(&openai, &anthropic, &["openai", "anthropic"]);
+    config.llm.retries = 2;
+
+    let verdict = LlmChain::new().classify("%0", "判断状态", "screen", &config.llm).await;
+    assert_eq!(verdict.state, ClaudeState::Done);
+}
+
+/// 测试所有后端都失败时回落到启发式规则
+#[tokio::test]
+async fn test_ends_in_heuristic() {
+    let (openai, anthropic) = (MockServer::start().await, MockServer::start().await);
+    mount_openai_error(&openai, 1).await;
+    let config = chain_config(&openai, &anthropic, &["openai", "gemini"]);
+
+    let verdict = LlmChain::new().classify("%0", "判断状态", "Error: compilation failed", &config.llm).await;
+    assert_eq!(verdict.state, ClaudeState::Errored);
+}
+
+/// 测试超时的后端被放弃
+#[tokio::test]
+async fn test_timeout_falls_through() {
+    let (openai, anthropic) = (MockServer::start().await, MockServer::start().await);
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&openai)
+        .await;
+    mount_anthropic_text(&anthropic, "请继续").await;
+    let mut config = chain_config(&openai, &anthropic, &["openai", "anthropic"]);
+    config.llm.backends[0].timeout = Some(1);
+
+    let started = std::time::Instant::now();
+    let message = LlmChain::new().generate("%0", "激活助手", "激活一下", &config.llm).await;
+    assert_eq!(message, Ok("请继续".to_string()));
+    assert!(started.elapsed() < Duration::from_secs(4));
+}
+
+/// 测试本机的 Ollama 和远程后端一样重试和熔断，停掉的 Ollama 不会让每次判断都等待它
+#[tokio::test]
+async fn test_local_ollama_retries_and_trips_breaker() {
+    let (ollama, anthropic) = (MockServer::start().await, MockServer::start().await);
+    Mock::given(method("POST"))
+        .and(path("/api/generate"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("model is loading"))
+        .expect(2)
+        .mount(&ollama)
+        .await;
+    mount_anthropic_text(&anthropic, "STUCK").await;
+    let mut config = chain_config(&ollama, &anthropic, &["ollama", "anthropic"]);
+    config.llm.ollama = Some(OllamaConfig { url: ollama.uri(), model: "qwen-test".to_string() });
+    config.llm.retries = 1;
+    config.llm.circuit_breaker.failures = 1;
+    let chain = LlmChain::new();
+
+    for _ in 0..3 {
+        assert_eq!(chain.classify("%0", "判断状态", "screen", &config.llm).await.state, ClaudeState::Stuck);
+    }
+    assert!(chain.is_tripped("ollama"));
+}
+
+/// 测试连续失败的后端被熔断，之后直接跳过
+#[tokio::test]
+async fn test_circuit_breaker_skips_failing_backend() {
+    let (openai, anthropic) = (MockServer::start().await, MockServer::start().await);
+    mount_openai_error(&openai, 2).await;
+    mount_anthropic_text(&anthropic, "STUCK").await;
+    let mut config = chain_config(&openai, &anthropic, &["openai", "anthropic"]);
+    config.llm.circuit_breaker.failures = 2;
+    let chain = LlmChain::new();
+
+    for _ in 0..4 {
+        assert_eq!(chain.classify("%0", "判断状态", "screen", &config.llm).await.state, ClaudeState::Stuck);
+    }
+    assert!(chain.is_tripped("openai"));
+    assert!(!chain.is_tripped("anthropic"));
+}
+
+/// 测试 none 后端不能生成激活消息，也不会被熔断
+#[tokio::test]
+async fn test_generate_without_llm() {
+    let mut config = Config::default();
+    config.llm.backends = vec![BackendConfig::new("none")];
+    let chain = LlmChain::new();
+
+    for _ in 0..5 {
+        assert!(chain.generate("%0", "激活助手", "激活一下", &config.llm).await.is_err());
+    }
+    assert!(!chain.is_tripped("none"));
+}
+
+/// 测试重试间隔按指数增长并带抖动
+#[test]
+fn test_retry_delay_jitter() {
+    for _ in 0..20 {
+        let first = retry_delay(100, 0);
+        let third = retry_delay(100, 2);
+        assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(150), "{:?}", first);
+        assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(600), "{:?}", third);
+    }
+}
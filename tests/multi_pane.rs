@@ -0,0 +1,120 @@
+use claude_watch::config::{Config, PaneConfig};
+use claude_watch::monitor::{run_monitoring_loop, PaneState};
+use claude_watch::testing::ScriptedTerminal;
+use std::sync::Arc;
+use std::time::Duration;
+
+const MULTI_PANE_YAML: &str = r#"
+llm:
+  backend: "ollama"
+monitoring:
+  interval: 5
+  stuck_sec: 60
+  max_retry: 10
+tmux:
+  panes:
+    - "%1"
+    - id: "%2"
+      interval: 2
+      stuck_sec: 30
+      backend: "none"
+"#;
+
+/// 测试 panes 同时支持纯字符串和带覆盖项的写法
+#[test]
+fn test_parse_pane_list() {
+    let config: Config = serde_yaml::from_str(MULTI_PANE_YAML).unwrap();
+    let panes = config.tmux.pane_list();
+
+    assert_eq!(panes.len(), 2);
+    assert_eq!(panes[0], PaneConfig::new("%1"));
+    assert_eq!(panes[1].id, "%2");
+    assert_eq!(panes[1].interval, Some(2));
+    assert_eq!(panes[1].stuck_sec, Some(30));
+    assert_eq!(panes[1].backend.as_deref(), Some("none"));
+}
+
+/// 测试未配置 panes 时回退到单个 pane
+#[test]
+fn test_pane_list_falls_back_to_single_pane() {
+    let mut config = Config::default();
+    config.tmux.pane = "%7".to_string();
+
+    assert_eq!(config.tmux.pane_list(), vec![PaneConfig::new("%7")]);
+}
+
+/// 测试窗格级覆盖项只影响该窗格的有效配置
+#[test]
+fn test_for_pane_applies_overrides() {
+    let config: Config = serde_yaml::from_str(MULTI_PANE_YAML).unwrap();
+    let panes = config.tmux.pane_list();
+
+    let plain = config.for_pane(&panes[0]);
+    assert_eq!(plain.tmux.pane, "%1");
+    assert_eq!(plain.monitoring.interval, 5);
+    assert_eq!(plain.monitoring.stuck_sec, 60);
+    assert_eq!(plain.llm.backend, "ollama");
+
+    let overridden = config.for_pane(&panes[1]);
+    assert_eq!(overridden.tmux.pane, "%2");
+    assert_eq!(overridden.monitoring.interval, 2);
+    assert_eq!(overridden.monitoring.stuck_sec, 30);
+    assert_eq!(overridden.llm.backend, "none");
+    assert_eq!(overridden.monitoring.max_retry, 10);
+}
+
+/// 测试两个窗格的内容变化状态互不影响
+#[test]
+fn test_pane_state_content_is_independent() {
+    let mut first = PaneState::new();
+    let mut second = PaneState::new();
+
+    assert!(first.content_changed("Building project"));
+    assert!(!first.content_changed("Building project"));
+
+    // 第二个窗格第一次看到相同内容，仍然认为有变化
+    assert!(second.content_changed("Building project"));
+    assert!(second.content_changed("Running tests"));
+    assert!(!first.content_changed("Building project"));
+}
+
+/// 测试两个窗格的时间递增状态互不影响
+#[test]
+fn test_pane_state_time_is_independent() {
+    let mut first = PaneState::new();
+    let mut second = PaneState::new();
+
+    assert!(first.time_increasing("* Herding… (100s · esc to interrupt)"));
+    assert!(first.time_increasing("* Herding… (101s · esc to interrupt)"));
+    assert!(!first.time_increasing("* Herding… (101s · esc to interrupt)"));
+
+    // 第二个窗格的时间比第一个小，也应当被视为首次记录
+    assert!(second.time_increasing("* Thinking… (5s · esc to interrupt)"));
+    assert!(!second.time_increasing("* Thinking… (5s · esc to interrupt)"));
+}
+
+/// 测试监控循环在等待期间让出运行时线程
+///
+/// 单线程运行时中，只有监控循环使用异步等待，关闭窗格的任务才有机会执行
+#[tokio::test]
+async fn test_monitoring_loop_yields_to_other_tasks() {
+    let mut config = Config::default();
+    config.tmux.pane = "%1".to_string();
+    config.monitoring.interval = 1;
+    config.monitoring.stuck_sec = 86400;
+    let terminal = Arc::new(ScriptedTerminal::new());
+    terminal.push_screen("%1", "> waiting");
+
+    let closer = {
+        let terminal = Arc::clone(&terminal);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            terminal.remove_pane("%1");
+        })
+    };
+    let mut state = PaneState::new();
+    let result = run_monitoring_loop(&config, terminal.as_ref(), &mut state).await;
+
+    assert!(result.is_ok());
+    closer.await.unwrap();
+}
@@ -0,0 +1,130 @@
+use claude_watch::config::{Config, Language, PromptConfig};
+use claude_watch::prompt::{activation_prompt, judge_prompt, prepare_screen, render, PromptContext};
+use claude_watch::ClaudeState;
+use std::io::Write;
+
+fn context() -> PromptContext {
+    PromptContext {
+        idle_seconds: 90,
+        last_action: Some("发送文本 \"continue\"".to_string()),
+        task_goal: Some("迁移到 v2 API".to_string()),
+        ..PromptContext::new("%3", "cargo build\n   Compiling foo")
+    }
+}
+
+/// 测试替换所有变量，两侧允许空格，未知变量保持原样
+#[test]
+fn test_render_variables() {
+    let template = "{{pane}} 空闲 {{ idle_seconds }} 秒，上一步 {{last_action}}，目标 {{task_goal}}\n{{screen}}\n{{unknown}}";
+    let rendered = render(template, &context(), Language::Zh);
+
+    assert_eq!(
+        rendered,
+        "%3 空闲 90 秒，上一步 发送文本 \"continue\"，目标 迁移到 v2 API\ncargo build\n   Compiling foo\n{{unknown}}"
+    );
+}
+
+/// 测试缺少的变量按语言使用占位文字
+#[test]
+fn test_render_missing_values() {
+    let context = PromptContext::new("%0", "");
+
+    assert_eq!(render("{{last_action}}/{{task_goal}}", &context, Language::Zh), "无/未指定");
+    assert_eq!(render("{{last_action}}/{{task_goal}}", &context, Language::En), "none/not specified");
+}
+
+/// 测试屏幕末尾、检测到的状态和之前无效的尝试
+#[test]
+fn test_render_activation_context() {
+    let screen: String = (1..=50).map(|i| format!("line {}\n", i)).collect::<String>() + "\n\n";
+    assert_eq!(prepare_screen(&screen, 3, 1000), "line 48\nline 49\nline 50");
+    assert_eq!(prepare_screen("only\n", 3, 1000), "only");
+
+    let context = PromptContext {
+        state: Some(ClaudeState::Errored),
+        failed_attempts: vec!["send_text \"Retry\"".to_string(), "llm_message \"请继续\"".to_string()],
+        ..PromptContext::new("%0", &screen)
+    };
+    let rendered = render("{{state}}\n{{failed_attempts}}\n{{screen_tail}}", &context, Language::Zh);
+    assert!(rendered.starts_with("ERRORED\n- send_text \"Retry\"\n- llm_message \"请继续\"\nline 21\n"), "{}", rendered);
+    assert!(rendered.ends_with("line 50"));
+
+    let empty = PromptContext::new("%0", "");
+    assert_eq!(render("{{state}}/{{failed_attempts}}", &empty, Language::Zh), "未知/无");
+    assert_eq!(render("{{state}}/{{failed_attempts}}", &empty, Language::En), "unknown/none");
+}
+
+/// 测试按 language 选择内置模板
+#[test]
+fn test_builtin_templates_by_language() {
+    let zh = PromptConfig::default();
+    let en = PromptConfig { language: Language::En, ..PromptConfig::default() };
+
+    let judge_zh = judge_prompt(&zh, &context());
+    let judge_en = judge_prompt(&en, &context());
+    assert!(judge_zh.contains("迁移到 v2 API") && judge_zh.contains("90 秒"), "{}", judge_zh);
+    assert!(judge_en.contains("90 seconds") && judge_en.contains("%3"), "{}", judge_en);
+    assert!(!judge_zh.contains("{{") && !judge_en.contains("{{"));
+
+    assert!(activation_prompt(&zh, &context()).contains("   Compiling foo"));
+    assert!(activation_prompt(&en, &context()).contains("Reply with the message only"));
+}
+
+/// 测试读取自定义模板文件，文件修改后立即生效，读取失败时使用内置模板
+#[test]
+fn test_custom_template_file() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    write!(file, "pane={{{{pane}}}} goal={{{{task_goal}}}}").unwrap();
+    let mut config = PromptConfig {
+        activation: Some(file.path().to_string_lossy().into_owned()),
+        ..PromptConfig::default()
+    };
+
+    assert_eq!(activation_prompt(&config, &context()), "pane=%3 goal=迁移到 v2 API");
+
+    std::fs::write(file.path(), "idle={{idle_seconds}}").unwrap();
+    assert_eq!(activation_prompt(&config, &context()), "idle=90");
+
+    config.activation = Some("/nonexistent/activation.md".to_string());
+    assert_eq!(activation_prompt(&config, &context()), activation_prompt(&PromptConfig::default(), &context()));
+}
+
+/// 测试自定义判断模板中的 {{screen}} 不重复屏幕内容，只指向用户消息
+#[test]
+fn test_judge_template_screen_points_to_user_message() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    write!(file, "screen:\n{{{{screen}}}}").unwrap();
+    let zh = PromptConfig { judge: Some(file.path().to_string_lossy().into_owned()), ..PromptConfig::default() };
+    let en = PromptConfig { language: Language::En, ..zh.clone() };
+
+    assert_eq!(judge_prompt(&zh, &context()), "screen:\n（屏幕内容见用户消息）");
+    assert_eq!(judge_prompt(&en, &context()), "screen:\n(the screen is in the user message)");
+}
+
+/// 测试解析 prompts 配置，从配置中取得窗格和任务目标
+#[test]
+fn test_parse_prompt_config() {
+    let yaml = r#"
+llm:
+  backend: none
+monitoring:
+  interval: 5
+  stuck_sec: 60
+  max_retry: 3
+tmux:
+  pane: "%2"
+prompts:
+  language: en
+  judge: ./judge.md
+  task_goal: "ship the release"
+"#;
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+
+    assert_eq!(config.prompts.language, Language::En);
+    assert_eq!(config.prompts.judge.as_deref(), Some("./judge.md"));
+    assert_eq!(config.prompts.activation, None);
+
+    let context = PromptContext::from_config(&config, "screen");
+    assert_eq!((context.pane.as_str(), context.task_goal.as_deref()), ("%2", Some("ship the release")));
+    assert_eq!(Config::default().prompts.language, Language::Zh);
+}
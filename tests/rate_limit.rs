@@ -0,0 +1,113 @@
+use chrono::{Local, TimeZone};
+use claude_watch::activity::parse_usage_limit;
+use claude_watch::config::Config;
+use claude_watch::monitor::{run_monitoring_loop, PaneState};
+use claude_watch::testing::{ScriptedTerminal, SentInput};
+use claude_watch::ClaudeState;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 测试解析带 Unix 时间戳的用量限制提示
+#[test]
+fn test_parse_epoch_reset() {
+    let now = Local.with_ymd_and_hms(2025, 1, 10, 13, 0, 0).unwrap();
+    let limit = parse_usage_limit("> fix it\nClaude AI usage limit reached|1760000000", now).unwrap();
+
+    assert_eq!(limit.message, "Claude AI usage limit reached|1760000000");
+    assert_eq!(limit.resets_at, Local.timestamp_opt(1_760_000_000, 0).single());
+}
+
+/// 测试解析钟点形式的重置时间
+#[test]
+fn test_parse_clock_reset() {
+    let now = Local.with_ymd_and_hms(2025, 1, 10, 13, 0, 0).unwrap();
+    let at = |h, m| Local.with_ymd_and_hms(2025, 1, 10, h, m, 0).unwrap();
+
+    let resets = |screen: &str| parse_usage_limit(screen, now).unwrap().resets_at;
+    assert_eq!(resets("5-hour limit reached ∙ resets 3pm (Asia/Shanghai)"), Some(at(15, 0)));
+    assert_eq!(resets("Claude usage limit reached. Your limit will reset at 3:30pm"), Some(at(15, 30)));
+    assert_eq!(resets("Usage limit reached, resets at 12:45 pm"), Some(at(12, 45)));
+    assert_eq!(resets("usage limit reached ∙ reset at 17:00"), Some(at(17, 0)));
+    assert_eq!(resets("usage limit reached, please try again later"), None);
+}
+
+/// 测试已经过去很久的钟点被解释为第二天
+#[test]
+fn test_parse_clock_reset_rolls_over() {
+    let now = Local.with_ymd_and_hms(2025, 1, 10, 23, 0, 0).unwrap();
+    let limit = parse_usage_limit("5-hour limit reached ∙ resets 2am", now).unwrap();
+
+    assert_eq!(limit.resets_at, Some(Local.with_ymd_and_hms(2025, 1, 11, 2, 0, 0).unwrap()));
+}
+
+/// 测试没有用量限制时返回 None
+#[test]
+fn test_parse_no_usage_limit() {
+    let now = Local::now();
+    assert!(parse_usage_limit("● All tests passed.\n╭────╮\n│ >  │\n╰────╯", now).is_none());
+}
+
+fn rate_limited_config() -> Config {
+    let mut config = Config::default();
+    config.tmux.pane = "%0".to_string();
+    config.monitoring.interval = 0;
+    config.monitoring.stuck_sec = 0;
+    config.llm.backend = "none".to_string();
+    config.rate_limit.resume_delay = 0;
+    config
+}
+
+/// 在后台线程中稍后关闭窗格，让监控循环结束
+fn close_pane_later(terminal: &Arc<ScriptedTerminal>) -> std::thread::JoinHandle<()> {
+    let terminal = Arc::clone(terminal);
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        terminal.remove_pane("%0");
+    })
+}
+
+/// 测试重置时间未到时不发送任何内容
+#[tokio::test]
+async fn test_rate_limit_suppresses_recovery() {
+    let config = rate_limited_config();
+    let terminal = Arc::new(ScriptedTerminal::new());
+    terminal.push_screen("%0", "> fix it\nClaude AI usage limit reached|4102444800");
+    let mut state = PaneState::new();
+
+    let closer = close_pane_later(&terminal);
+    run_monitoring_loop(&config, terminal.as_ref(), &mut state).await.unwrap();
+    closer.join().unwrap();
+
+    assert!(terminal.sent().is_empty(), "用量限制期间不应发送恢复命令");
+    assert_eq!(state.last_state, Some(ClaudeState::RateLimited));
+    assert_eq!(state.rate_limited_until, Local.timestamp_opt(4_102_444_800, 0).single());
+}
+
+/// 测试重置时间过后只发送一次继续消息
+#[tokio::test]
+async fn test_rate_limit_resumes_once_after_reset() {
+    let mut config = rate_limited_config();
+    config.rate_limit.resume_message = "please continue".to_string();
+    let terminal = Arc::new(ScriptedTerminal::new());
+    terminal.push_screen("%0", "> fix it\nClaude AI usage limit reached|1000000000\n  ⎿  Done");
+    let mut state = PaneState::new();
+
+    let closer = close_pane_later(&terminal);
+    run_monitoring_loop(&config, terminal.as_ref(), &mut state).await.unwrap();
+    closer.join().unwrap();
+
+    let texts: Vec<SentInput> = terminal
+        .sent()
+        .into_iter()
+        .filter(|input| matches!(input, SentInput::Text { .. }))
+        .collect();
+    assert_eq!(
+        texts.first(),
+        Some(&SentInput::Text { pane: "%0".to_string(), text: "please continue".to_string() })
+    );
+    assert_eq!(
+        texts.iter().filter(|input| matches!(input, SentInput::Text { text, .. } if text == "please continue")).count(),
+        1,
+        "仍留在屏幕上的旧提示不应再次触发恢复"
+    );
+}
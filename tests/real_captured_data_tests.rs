@@ -1,3 +1,5 @@
+#![allow(clippy::expect_fun_call)]
+
 //! 基于真实捕获的Claude Code界面数据的单元测试
 //! 
 //! 这些测试使用从真实tmux会话中捕获的Claude Code界面数据
@@ -179,9 +181,7 @@ fn test_current_state_with_input() {
 /// 测试真实数据的时间递增检测
 #[test]
 fn test_real_data_time_increasing() {
-    setup::reset_global_state();
-    
-    let pane_id = "real_test_pane";
+    let mut previous = None;
     
     // 加载不同时间点的界面数据
     let state_1s = load_interface_data("hello_world_final.txt");    // 1s
@@ -190,24 +190,24 @@ fn test_real_data_time_increasing() {
     let state_43s = load_interface_data("hello_world_completed.txt"); // 43s
     
     // 测试时间递增逻辑
-    let detection_1s = is_time_increasing(&state_1s, pane_id);
+    let detection_1s = is_time_increasing(&state_1s, &mut previous);
     println!("1s状态时间递增检测: {}", detection_1s);
     assert!(detection_1s, "1s状态应该返回true（第一次检测）");
     
-    let detection_14s = is_time_increasing(&state_14s, pane_id);
+    let detection_14s = is_time_increasing(&state_14s, &mut previous);
     println!("14s状态时间递增检测: {}", detection_14s);
     assert!(detection_14s, "14s状态应该返回true（时间递增）");
     
-    let detection_28s = is_time_increasing(&state_28s, pane_id);
+    let detection_28s = is_time_increasing(&state_28s, &mut previous);
     println!("28s状态时间递增检测: {}", detection_28s);
     assert!(detection_28s, "28s状态应该返回true（时间递增）");
     
-    let detection_43s = is_time_increasing(&state_43s, pane_id);
+    let detection_43s = is_time_increasing(&state_43s, &mut previous);
     println!("43s状态时间递增检测: {}", detection_43s);
     assert!(detection_43s, "43s状态应该返回true（时间递增）");
     
     // 再次检测相同时间应该返回false
-    let detection_43s_again = is_time_increasing(&state_43s, pane_id);
+    let detection_43s_again = is_time_increasing(&state_43s, &mut previous);
     println!("43s状态再次检测: {}", detection_43s_again);
     assert!(!detection_43s_again, "相同时间再次检测应该返回false");
     
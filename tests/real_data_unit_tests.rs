@@ -1,3 +1,5 @@
+#![allow(clippy::expect_fun_call)]
+
 //! 基于真实Claude Code界面数据的单元测试
 //! 
 //! 使用从真实tmux会话中捕获的Claude Code界面数据进行测试
@@ -143,26 +145,24 @@ fn test_philosophising_tail_interface() {
 /// 测试时间递增检测功能
 #[test]
 fn test_time_increasing_with_real_data() {
-    setup::reset_global_state();
-    
-    let pane_id = "real_test_pane";
+    let mut previous = None;
     
     // 加载两个不同时间点的界面数据
     let interface1 = load_interface_data("philosophising_state.txt");
     let interface2 = load_interface_data("user_input_state.txt");
     
     // 第一次检测
-    let first_detection = is_time_increasing(&interface1, pane_id);
+    let first_detection = is_time_increasing(&interface1, &mut previous);
     println!("第一次时间递增检测: {}", first_detection);
     assert!(first_detection, "第一次时间检测应该返回true");
     
     // 第二次检测（相同时间）
-    let second_detection = is_time_increasing(&interface1, pane_id);
+    let second_detection = is_time_increasing(&interface1, &mut previous);
     println!("第二次时间递增检测: {}", second_detection);
     assert!(!second_detection, "相同时间应该返回false");
     
     // 第三次检测（不同时间）
-    let third_detection = is_time_increasing(&interface2, pane_id);
+    let third_detection = is_time_increasing(&interface2, &mut previous);
     println!("第三次时间递增检测: {}", third_detection);
     // 注意：这里可能返回true或false，取决于实际的时间值
 }
@@ -105,9 +105,7 @@ fn test_real_claude_interfaces() {
 /// 测试真实界面的时间递增检测
 #[test]
 fn test_real_interface_time_increasing() {
-    setup::reset_global_state();
-    
-    let pane_id = "real_pane_test";
+    let mut previous = None;
     
     // 使用真实界面数据测试时间递增检测
     let interfaces_with_time = REAL_CLAUDE_INTERFACES.iter()
@@ -118,7 +116,7 @@ fn test_real_interface_time_increasing() {
     
     // 测试时间递增逻辑
     for (i, &interface) in interfaces_with_time.iter().enumerate() {
-        let is_increasing = is_time_increasing(interface, pane_id);
+        let is_increasing = is_time_increasing(interface, &mut previous);
         println!("界面 {} 时间递增检测结果: {}", i + 1, is_increasing);
         
         // 第一次检测应该返回true
@@ -0,0 +1,278 @@
+use claude_watch::config::{Config, OpenAiConfig, RecoveryAction, RecoveryStep, SuccessCheck};
+use claude_watch::monitor::PaneState;
+use claude_watch::recovery::{run_recovery_step, step_succeeded};
+use claude_watch::testing::{ScriptedTerminal, SentInput};
+use claude_watch::Key;
+use serde_json::json;
+use std::time::{Duration, Instant};
+use wiremock::matchers::{body_string_contains, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// 没有任何进展迹象的屏幕（提示符和长行都会被视为进展）
+const STUCK_SCREEN: &str = "\n  ...\n";
+
+fn config_with_steps(steps: Vec<RecoveryStep>) -> Config {
+    let mut config = Config::default();
+    config.tmux.pane = "%0".to_string();
+    config.llm.backend = "none".to_string();
+    config.recovery.steps = steps
+        .into_iter()
+        .map(|step| RecoveryStep { wait: Some(0), ..step })
+        .collect();
+    config
+}
+
+/// 测试从 YAML 解析恢复步骤
+#[test]
+fn test_parse_recovery_steps() {
+    let yaml = r#"
+llm:
+  backend: none
+monitoring:
+  interval: 5
+  stuck_sec: 60
+  max_retry: 3
+tmux:
+  pane: "%0"
+recovery:
+  steps:
+    - action: llm_message
+      fallback: Retry
+    - action: send_text
+      text: "continue"
+      attempts: 3
+      wait: 30
+    - action: send_key
+      key: Escape
+      check: changed
+    - action: shell_hook
+      command: ./scripts/restart.sh
+      check: active
+    - action: notify
+      message: "{pane} needs a human"
+      check: none
+"#;
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+    let steps = config.recovery_steps();
+
+    assert_eq!(steps.len(), 5);
+    assert_eq!(steps[0].action, RecoveryAction::LlmMessage { fallback: Some("Retry".to_string()) });
+    assert_eq!(steps[0].attempts, 1);
+    assert_eq!(steps[0].check, SuccessCheck::Progress);
+    assert_eq!(steps[1].action, RecoveryAction::SendText { text: "continue".to_string() });
+    assert_eq!((steps[1].attempts, steps[1].wait), (3, Some(30)));
+    assert_eq!(steps[2].action, RecoveryAction::SendKey { key: Key::Escape });
+    assert_eq!(steps[2].check, SuccessCheck::Changed);
+    assert_eq!(steps[3].action, RecoveryAction::ShellHook { command: "./scripts/restart.sh".to_string(), timeout: None });
+    assert_eq!(steps[4].check, SuccessCheck::None);
+}
+
+/// 测试未配置时使用内置阶梯，Retry 次数来自 max_retry
+#[test]
+fn test_default_recovery_ladder() {
+    let mut config = Config::default();
+    config.monitoring.max_retry = 4;
+    let steps = config.recovery_steps();
+
+    assert!(matches!(steps[0].action, RecoveryAction::LlmMessage { .. }));
+    assert_eq!(steps[1].action, RecoveryAction::SendText { text: "Retry".to_string() });
+    assert_eq!(steps[1].attempts, 4);
+    assert!(matches!(steps.last().unwrap().action, RecoveryAction::Notify { .. }));
+
+    // 中断和退出使用真正的控制键，而不是输入 "Ctrl+C" 文本
+    let keys: Vec<Key> = steps
+        .iter()
+        .filter_map(|step| match step.action {
+            RecoveryAction::SendKey { key } => Some(key),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(keys, vec![Key::Escape, Key::CtrlC]);
+    assert!(!steps.iter().any(|step| matches!(
+        &step.action,
+        RecoveryAction::SendText { text } if text == "Ctrl+C" || text == "Escaping"
+    )));
+}
+
+/// 测试未知的按键名称在加载配置时报错
+#[test]
+fn test_unknown_key_rejected() {
+    let yaml = "action: send_key\nkey: Escaping\n";
+    assert!(serde_yaml::from_str::<RecoveryStep>(yaml).is_err());
+}
+
+/// 测试失败的步骤按 attempts 升级，全部失败后回到第一步
+#[tokio::test]
+async fn test_ladder_escalates_and_wraps() {
+    let config = config_with_steps(vec![
+        RecoveryStep {
+            attempts: 2,
+            ..RecoveryStep::new(RecoveryAction::SendKey { key: Key::Escape })
+        },
+        RecoveryStep::new(RecoveryAction::SendText { text: "go on".to_string() }),
+    ]);
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%0", STUCK_SCREEN);
+    let mut state = PaneState::new();
+
+    for _ in 0..4 {
+        assert!(!run_recovery_step(&config, &terminal, &mut state).await);
+    }
+
+    let key = |key: Key| SentInput::Key { pane: "%0".to_string(), key };
+    assert_eq!(
+        terminal.sent(),
+        vec![
+            key(Key::Escape),
+            key(Key::Escape),
+            SentInput::Text { pane: "%0".to_string(), text: "go on".to_string() },
+            key(Key::Escape),
+        ]
+    );
+    assert_eq!((state.recovery_step, state.retry_count), (0, 1));
+}
+
+/// 测试步骤成功后重置阶梯
+#[tokio::test]
+async fn test_ladder_resets_on_success() {
+    let config = config_with_steps(vec![
+        RecoveryStep {
+            check: SuccessCheck::Active,
+            ..RecoveryStep::new(RecoveryAction::SendText { text: "Retry".to_string() })
+        },
+    ]);
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%0", STUCK_SCREEN);
+    terminal.push_screen("%0", "* Processing… (3s · esc to interrupt)");
+    let mut state = PaneState::new();
+    state.retry_count = 5;
+
+    assert!(run_recovery_step(&config, &terminal, &mut state).await);
+    assert_eq!((state.recovery_step, state.retry_count), (0, 0));
+}
+
+/// 测试 LLM 不可用时发送备用文本
+#[tokio::test]
+async fn test_llm_message_falls_back() {
+    let config = config_with_steps(vec![RecoveryStep::new(RecoveryAction::LlmMessage {
+        fallback: Some("Retry".to_string()),
+    })]);
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%0", STUCK_SCREEN);
+    let mut state = PaneState::new();
+
+    run_recovery_step(&config, &terminal, &mut state).await;
+
+    assert_eq!(
+        terminal.sent(),
+        vec![SentInput::Text { pane: "%0".to_string(), text: "Retry".to_string() }]
+    );
+}
+
+/// 测试激活消息的提示词包含屏幕末尾和之前无效的尝试，成功后清空无效尝试
+#[tokio::test]
+async fn test_llm_message_sees_screen_and_failed_attempts() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_string_contains("test_parse ... FAILED"))
+        .and(body_string_contains("send_text \\\"go on\\\""))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"role": "assistant", "content": "test_parse 失败了，请修复后继续"}}]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut config = config_with_steps(vec![
+        RecoveryStep {
+            check: SuccessCheck::Changed,
+            ..RecoveryStep::new(RecoveryAction::SendText { text: "go on".to_string() })
+        },
+        RecoveryStep {
+            check: SuccessCheck::Active,
+            ..RecoveryStep::new(RecoveryAction::LlmMessage { fallback: None })
+        },
+    ]);
+    config.llm.backend = "openai".to_string();
+    config.llm.openai = Some(OpenAiConfig {
+        api_key: "sk-test".to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_base: server.uri(),
+        model: "gpt-test".to_string(),
+    });
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%0", "running 3 tests\ntest test_parse ... FAILED\n");
+    let mut state = PaneState::new();
+
+    assert!(!run_recovery_step(&config, &terminal, &mut state).await);
+    assert_eq!(state.failed_attempts, vec!["send_text \"go on\"".to_string()]);
+
+    terminal.push_screen("%0", "* Processing… (3s · esc to interrupt)");
+    assert!(run_recovery_step(&config, &terminal, &mut state).await);
+    assert_eq!(
+        terminal.sent().last(),
+        Some(&SentInput::Text { pane: "%0".to_string(), text: "test_parse 失败了，请修复后继续".to_string() })
+    );
+    assert_eq!(state.last_action.as_deref(), Some("llm_message \"test_parse 失败了，请修复后继续\""));
+    assert!(state.failed_attempts.is_empty());
+}
+
+/// 测试钩子命令能拿到窗格 ID，失败的钩子不会中断阶梯
+#[tokio::test]
+async fn test_shell_hook_step() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("pane.txt");
+    let config = config_with_steps(vec![
+        RecoveryStep::new(RecoveryAction::ShellHook {
+            command: format!("printf %s \"$CLAUDE_WATCH_PANE\" > '{}'", out.display()),
+            timeout: None,
+        }),
+        RecoveryStep::new(RecoveryAction::ShellHook { command: "exit 3".to_string(), timeout: None }),
+    ]);
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%0", STUCK_SCREEN);
+    let mut state = PaneState::new();
+
+    assert!(!run_recovery_step(&config, &terminal, &mut state).await);
+    assert!(!run_recovery_step(&config, &terminal, &mut state).await);
+
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), "%0");
+    assert_eq!(state.recovery_step, 0);
+}
+
+/// 测试超时的钩子连同它启动的子进程一起被终止，不会拖住恢复阶梯
+#[tokio::test]
+async fn test_shell_hook_timeout_kills_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("done.txt");
+    let child = dir.path().join("child.txt");
+    let config = config_with_steps(vec![RecoveryStep::new(RecoveryAction::ShellHook {
+        command: format!("(sleep 2; touch '{}') & sleep 2 | cat; touch '{}'", child.display(), out.display()),
+        timeout: Some(1),
+    })]);
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%0", STUCK_SCREEN);
+    let mut state = PaneState::new();
+
+    let started = Instant::now();
+    assert!(!run_recovery_step(&config, &terminal, &mut state).await);
+    assert!(started.elapsed() < Duration::from_secs(2), "{:?}", started.elapsed());
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert!(!out.exists());
+    assert!(!child.exists(), "钩子启动的子进程在超时后仍在运行");
+}
+
+/// 测试各种检查方式
+#[test]
+fn test_step_success_checks() {
+    let active = "* Processing… (3s · esc to interrupt)";
+    let changed = "● Found the bug in src/main.rs, fixing it now.";
+
+    assert!(step_succeeded(SuccessCheck::Active, STUCK_SCREEN, active));
+    assert!(!step_succeeded(SuccessCheck::Active, STUCK_SCREEN, STUCK_SCREEN));
+    assert!(step_succeeded(SuccessCheck::Changed, STUCK_SCREEN, changed));
+    assert!(!step_succeeded(SuccessCheck::Changed, STUCK_SCREEN, STUCK_SCREEN));
+    assert!(step_succeeded(SuccessCheck::Progress, STUCK_SCREEN, active));
+    assert!(!step_succeeded(SuccessCheck::None, STUCK_SCREEN, active));
+}
@@ -0,0 +1,104 @@
+use claude_watch::config::{BackendConfig, Config, OpenAiConfig};
+use claude_watch::llm::LlmChain;
+use claude_watch::monitor::strip_ui_chrome;
+use claude_watch::prompt::prepare_screen;
+use serde_json::json;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const SCREEN: &str = "╭───────────────────────────────────────────────────╮
+│ ✻ Welcome to Claude Code!                         │
+│                                                   │
+│   /help for help, /status for your current setup  │
+│                                                   │
+│   cwd: /home/user/project                         │
+╰───────────────────────────────────────────────────╯
+
+
+● Bash(cargo test)
+  ⎿  test result: FAILED. 3 passed; 1 failed
+
+
+● 测试 test_parse 失败了，需要修复解析逻辑。
+
+╭───────────────────────────────────────────────────╮
+│ Do you want to make this edit to parser.rs?       │
+│ ❯ 1. Yes                                          │
+│   2. No, and tell Claude what to do differently   │
+╰───────────────────────────────────────────────────╯
+───────────────────────────────────────────────────────
+  ? for shortcuts                          Bypassing Permissions
+";
+
+/// 测试去掉横幅、框线和快捷键提示，保留框内的对话框文字
+#[test]
+fn test_strip_ui_chrome() {
+    assert_eq!(
+        strip_ui_chrome(SCREEN),
+        "● Bash(cargo test)
+  ⎿  test result: FAILED. 3 passed; 1 failed
+
+● 测试 test_parse 失败了，需要修复解析逻辑。
+
+ Do you want to make this edit to parser.rs?
+ ❯ 1. Yes
+   2. No, and tell Claude what to do differently"
+    );
+}
+
+/// 测试输入框保留提示符，没有闭合的框保留内容
+#[test]
+fn test_strip_input_box_and_unclosed_box() {
+    let screen = "✶ Combobulating… (131s · esc to interrupt)\n\n╭────╮\n│ >  │\n╰────╯\n  ? for shortcuts\n";
+    assert_eq!(strip_ui_chrome(screen), "✶ Combobulating… (131s · esc to interrupt)\n\n >");
+
+    let cut = "╭────────╮\n│ 1. Yes │\n│ 2. No";
+    assert_eq!(strip_ui_chrome(cut), " 1. Yes\n│ 2. No");
+}
+
+/// 测试按非空行数和字符数截取末尾
+#[test]
+fn test_prepare_screen_budget() {
+    let screen: String = (1..=100).map(|i| format!("line {:03}\n\n", i)).collect();
+
+    let by_lines = prepare_screen(&screen, 3, 10_000);
+    assert_eq!(by_lines, "line 098\n\nline 099\n\nline 100");
+
+    let by_chars = prepare_screen(&screen, 80, 20);
+    assert!(by_chars.chars().count() <= 20, "{:?}", by_chars);
+    assert!(by_chars.ends_with("line 100"));
+
+    let long_line = "x".repeat(50) + "尾部";
+    assert_eq!(prepare_screen(&long_line, 10, 6), "xxxx尾部");
+}
+
+/// 测试降级链按后端的字符预算发送屏幕内容
+#[tokio::test]
+async fn test_chain_applies_budget_per_backend() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"role": "assistant", "content": "STUCK"}}]
+        })))
+        .mount(&server)
+        .await;
+    let mut config = Config::default();
+    config.llm.openai = Some(OpenAiConfig {
+        api_key: "sk-test".to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_base: server.uri(),
+        model: "gpt-test".to_string(),
+    });
+    config.llm.backends = vec![BackendConfig { max_chars: Some(120), ..BackendConfig::new("openai") }];
+    let screen: String = (1..=200).map(|i| format!("output line {}\n", i)).collect();
+
+    LlmChain::new().classify("%0", "判断状态", &screen, &config.llm).await;
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let sent = body["messages"][1]["content"].as_str().unwrap();
+    assert!(sent.chars().count() <= 120, "{}", sent);
+    assert!(sent.ends_with("output line 200") && !sent.contains("output line 1\n"), "{}", sent);
+    assert_eq!(config.llm.max_chars_for("ollama"), 8000);
+}
@@ -0,0 +1,149 @@
+use claude_watch::config::{Config, PaneConfig};
+use claude_watch::monitor::{classify_once, classify_screen, monitor_completion_state, run_monitoring_loop, watch_panes, PaneState};
+use claude_watch::reload::ConfigUpdates;
+use claude_watch::shutdown::{self, Shutdown};
+use claude_watch::testing::ScriptedTerminal;
+use claude_watch::ClaudeState;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 一小时也等不完的配置，只有退出通知能让循环及时结束
+fn slow_config(pane: &str) -> Config {
+    let mut config = Config::default();
+    config.tmux.pane = pane.to_string();
+    config.llm.backend = "none".to_string();
+    config.monitoring.interval = 3600;
+    config.monitoring.stuck_sec = 3600;
+    config
+}
+
+/// 测试退出通知打断等待
+#[tokio::test]
+async fn test_shutdown_interrupts_sleep() {
+    let (trigger, shutdown) = shutdown::channel();
+    assert!(!shutdown.is_triggered());
+
+    trigger.trigger();
+
+    assert!(shutdown.is_triggered());
+    assert!(!shutdown.sleep(Duration::from_secs(3600)).await);
+}
+
+/// 测试永不触发的退出通知会完整等待
+#[tokio::test]
+async fn test_never_shutdown_sleeps_fully() {
+    let shutdown = Shutdown::never();
+    assert!(shutdown.sleep(Duration::from_millis(10)).await);
+    assert!(!shutdown.is_triggered());
+}
+
+/// 测试监控循环收到退出通知后立即结束
+#[tokio::test]
+async fn test_monitoring_loop_stops_on_shutdown() {
+    let config = slow_config("%0");
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%0", "* Processing… (3s · esc to interrupt)");
+    let (trigger, shutdown) = shutdown::channel();
+    let mut state = PaneState::with_shutdown(shutdown);
+
+    let stop = async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        trigger.trigger();
+    };
+    let run = tokio::time::timeout(
+        Duration::from_secs(5),
+        run_monitoring_loop(&config, &terminal, &mut state),
+    );
+    let (result, _) = tokio::join!(run, stop);
+
+    assert!(result.expect("监控循环应该在退出通知后结束").is_ok());
+}
+
+/// 测试所有窗格的监控任务都会响应退出通知
+#[tokio::test]
+async fn test_watch_panes_stops_on_shutdown() {
+    let mut config = slow_config("%0");
+    config.tmux.panes = vec![PaneConfig::new("%1"), PaneConfig::new("%2")];
+    let terminal = Arc::new(ScriptedTerminal::new());
+    terminal.push_screen("%1", "> first");
+    terminal.push_screen("%2", "> second");
+    let (trigger, shutdown) = shutdown::channel();
+
+    let handle = tokio::spawn(async move { watch_panes(ConfigUpdates::fixed(config), terminal, shutdown).await });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    trigger.trigger();
+
+    let result = tokio::time::timeout(Duration::from_secs(5), handle)
+        .await
+        .expect("所有监控任务应该在退出通知后结束");
+    assert!(result.unwrap().is_ok());
+}
+
+/// 测试完成状态监控收到退出通知后返回
+#[tokio::test]
+async fn test_completion_state_stops_on_shutdown() {
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%0", "✅ Task completed");
+    let (trigger, shutdown) = shutdown::channel();
+    trigger.trigger();
+
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        monitor_completion_state(&terminal, "%0", Duration::from_secs(3600), &shutdown),
+    )
+    .await;
+    assert_eq!(result, Ok(Ok(())));
+}
+
+/// 测试单次判断覆盖主要状态
+#[tokio::test]
+async fn test_classify_screen() {
+    let config = slow_config("%0");
+
+    assert_eq!(
+        classify_screen(&config, "✻ Cogitating… (169s · ↑ 8.7k tokens · esc to interrupt)").await,
+        ClaudeState::Thinking
+    );
+    assert_eq!(
+        classify_screen(&config, "5-hour limit reached ∙ resets 3pm").await,
+        ClaudeState::RateLimited
+    );
+    assert_eq!(
+        classify_screen(&config, "│ Bash command │\n│ ls │\n│ Do you want to proceed? │\n│ ❯ 1. Yes │\n│   2. No │").await,
+        ClaudeState::WaitingForPermission
+    );
+    assert_eq!(classify_screen(&config, "✅ All checks passed").await, ClaudeState::Done);
+    assert_eq!(classify_screen(&config, "nothing here").await, ClaudeState::Stuck);
+}
+
+/// 测试 --once 使用的退出码
+#[tokio::test]
+async fn test_classify_once_exit_codes() {
+    let config = slow_config("%0");
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%0", "Error: compilation failed");
+
+    let state = classify_once(&config, &terminal).await.unwrap();
+    assert_eq!(state, ClaudeState::Errored);
+    assert_eq!(state.exit_code(), 3);
+    assert!(classify_once(&slow_config("%9"), &terminal).await.is_err());
+
+    assert_eq!(ClaudeState::Working.exit_code(), 0);
+    assert_eq!(ClaudeState::Done.exit_code(), 0);
+    assert_eq!(ClaudeState::WaitingForPermission.exit_code(), 2);
+    assert_eq!(ClaudeState::Stuck.exit_code(), 3);
+}
+
+/// 测试 --once 在无法捕获窗格时以 1 退出
+#[test]
+fn test_once_cli_exits_with_error_code() {
+    assert_cmd::Command::cargo_bin("claude-watch")
+        .unwrap()
+        .args(["--once", "--pane", "%99999", "--backend", "none"])
+        .current_dir(std::env::temp_dir())
+        .env("XDG_CONFIG_HOME", std::env::temp_dir().join("claude-watch-no-config"))
+        .env("TMUX_TMPDIR", std::env::temp_dir().join("claude-watch-no-server"))
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("无法捕获窗格"));
+}
@@ -0,0 +1,98 @@
+use claude_watch::activity::detect_screen_state;
+use claude_watch::llm::simple_heuristic_check;
+use claude_watch::ClaudeState;
+
+/// 测试状态标签的往返解析
+#[test]
+fn test_state_labels_round_trip() {
+    for state in ClaudeState::ALL {
+        assert_eq!(ClaudeState::from_label(state.label()), Some(state));
+    }
+    assert_eq!(ClaudeState::from_label(" done \n"), Some(ClaudeState::Done));
+    assert_eq!(ClaudeState::from_label("waiting_for_permission"), Some(ClaudeState::WaitingForPermission));
+    assert_eq!(ClaudeState::from_label("MAYBE"), None);
+}
+
+/// 测试只有工作和思考状态被视为活动
+#[test]
+fn test_state_activity_flags() {
+    assert!(ClaudeState::Working.is_active());
+    assert!(ClaudeState::Thinking.is_active());
+    assert!(!ClaudeState::Stuck.is_active());
+
+    assert!(ClaudeState::Stuck.needs_recovery());
+    assert!(ClaudeState::Errored.needs_recovery());
+    assert!(!ClaudeState::WaitingForPermission.needs_recovery());
+    assert!(!ClaudeState::RateLimited.needs_recovery());
+}
+
+/// 测试思考状态和工作状态的区分
+#[test]
+fn test_detect_thinking_and_working() {
+    assert_eq!(
+        detect_screen_state("✻ Cogitating… (169s · ↑ 8.7k tokens · esc to interrupt)"),
+        Some(ClaudeState::Thinking)
+    );
+    assert_eq!(
+        detect_screen_state("* Processing… (56s · esc to interrupt)"),
+        Some(ClaudeState::Working)
+    );
+}
+
+/// 测试权限确认对话框识别
+#[test]
+fn test_detect_permission_prompt() {
+    let screen = "╭──────────────────────────────────────╮\n\
+                  │ Bash command                         │\n\
+                  │   cargo test --workspace             │\n\
+                  │ Do you want to proceed?              │\n\
+                  │ ❯ 1. Yes                             │\n\
+                  │   2. No, and tell Claude what to do  │\n\
+                  ╰──────────────────────────────────────╯";
+    assert_eq!(detect_screen_state(screen), Some(ClaudeState::WaitingForPermission));
+}
+
+/// 测试用量限制和上下文已满识别
+#[test]
+fn test_detect_rate_limit_and_context_full() {
+    assert_eq!(
+        detect_screen_state("Claude AI usage limit reached|1760000000"),
+        Some(ClaudeState::RateLimited)
+    );
+    assert_eq!(
+        detect_screen_state("  ⎿  Prompt is too long"),
+        Some(ClaudeState::ContextFull)
+    );
+}
+
+/// 测试 Claude 提问识别
+#[test]
+fn test_detect_question() {
+    let screen = "● I found two config files. Which one should I update?\n\
+                  ╭──────────────╮\n\
+                  │ >            │\n\
+                  ╰──────────────╯";
+    assert_eq!(detect_screen_state(screen), Some(ClaudeState::AskingUserQuestion));
+
+    let statement = "● I updated the config file.\n╭──────╮\n│ >    │\n╰──────╯";
+    assert_eq!(detect_screen_state(statement), None);
+}
+
+/// 测试中断识别
+#[test]
+fn test_detect_interrupted() {
+    assert_eq!(
+        detect_screen_state("> fix the tests\n  ⎿  Interrupted by user"),
+        Some(ClaudeState::Interrupted)
+    );
+}
+
+/// 测试启发式检查产出丰富状态，而不是统一返回 Stuck
+#[test]
+fn test_heuristic_produces_rich_states() {
+    assert_eq!(simple_heuristic_check("✅ All checks passed"), ClaudeState::Done);
+    assert_eq!(simple_heuristic_check("Error: compilation failed"), ClaudeState::Errored);
+    assert_eq!(simple_heuristic_check("Tool use: Reading file"), ClaudeState::Working);
+    assert_eq!(simple_heuristic_check("Interrupted by user"), ClaudeState::Interrupted);
+    assert_eq!(simple_heuristic_check("nothing happening here"), ClaudeState::Stuck);
+}
@@ -0,0 +1,190 @@
+use claude_watch::config::{Config, OpenAiConfig, RecoveryAction, RecoveryStep};
+use claude_watch::monitor::{monitor_completion_state, run_monitoring_loop, PaneState, MAX_COMPACT_ATTEMPTS};
+use claude_watch::recovery::run_recovery_step;
+use claude_watch::shutdown::{channel, Shutdown};
+use claude_watch::testing::{ScriptedTerminal, SentInput};
+use claude_watch::{Key, TerminalBackend};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// 测试脚本化终端按顺序返回屏幕，最后一屏保持不变
+#[tokio::test]
+async fn test_scripted_terminal_screen_queue() {
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%1", "first");
+    terminal.push_screen("%1", "second");
+
+    assert_eq!(terminal.capture("%1").await.unwrap(), "first");
+    assert_eq!(terminal.capture("%1").await.unwrap(), "second");
+    assert_eq!(terminal.capture("%1").await.unwrap(), "second", "最后一屏应该重复返回");
+}
+
+/// 测试未配置的窗格视为不存在
+#[tokio::test]
+async fn test_scripted_terminal_missing_pane() {
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%1", "screen");
+
+    assert!(terminal.pane_exists("%1").await);
+    assert!(!terminal.pane_exists("%2").await);
+    assert!(terminal.capture("%2").await.is_err());
+    assert!(terminal.send_text("hello", "%2").await.is_err());
+
+    terminal.remove_pane("%1");
+    assert!(!terminal.pane_exists("%1").await);
+}
+
+/// 测试发送的文本和按键会按顺序记录
+#[tokio::test]
+async fn test_scripted_terminal_records_input() {
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%1", "screen");
+
+    terminal.send_text("Retry", "%1").await.unwrap();
+    terminal.send_key(Key::CtrlC, "%1").await.unwrap();
+
+    assert_eq!(
+        terminal.sent(),
+        vec![
+            SentInput::Text { pane: "%1".to_string(), text: "Retry".to_string() },
+            SentInput::Key { pane: "%1".to_string(), key: Key::CtrlC },
+        ]
+    );
+}
+
+/// 测试完成状态监控一直等到画面变化才返回
+#[tokio::test]
+async fn test_completion_state_waits_for_screen_change() {
+    let interval = Duration::from_millis(10);
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%1", "✅ Task completed");
+
+    let unchanged = tokio::time::timeout(
+        Duration::from_millis(200),
+        monitor_completion_state(&terminal, "%1", interval, &Shutdown::never()),
+    )
+    .await;
+    assert!(unchanged.is_err(), "画面没有变化时不应该返回");
+
+    terminal.push_screen("%1", "✅ Task completed");
+    terminal.push_screen("%1", "✅ Task completed");
+    terminal.push_screen("%1", "> 开始新任务");
+    let changed = tokio::time::timeout(
+        Duration::from_secs(5),
+        monitor_completion_state(&terminal, "%1", interval, &Shutdown::never()),
+    )
+    .await;
+    assert_eq!(changed, Ok(Ok(())));
+}
+
+/// 测试窗格消失时完成状态监控返回错误而不是 panic
+#[tokio::test]
+async fn test_completion_state_errors_when_pane_gone() {
+    let terminal = ScriptedTerminal::new();
+
+    assert!(monitor_completion_state(&terminal, "%9", Duration::ZERO, &Shutdown::never()).await.is_err());
+}
+
+/// 测试 LLM 不可用且没有备用文本时，LLM 激活步骤不会向终端发送任何内容
+#[tokio::test]
+async fn test_activation_without_llm_sends_nothing() {
+    let mut config = Config::default();
+    config.tmux.pane = "%1".to_string();
+    config.llm.backend = "none".to_string();
+    config.recovery.steps = vec![RecoveryStep { wait: Some(0), ..RecoveryStep::new(RecoveryAction::LlmMessage { fallback: None }) }];
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%1", "Some output");
+
+    run_recovery_step(&config, &terminal, &mut PaneState::new()).await;
+    assert!(terminal.sent().is_empty());
+}
+
+/// 测试等待激活效果时收到退出通知立即返回
+#[tokio::test]
+async fn test_activation_wait_stops_on_shutdown() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"role": "assistant", "content": "请继续"}}]
+        })))
+        .mount(&server)
+        .await;
+    let mut config = Config::default();
+    config.tmux.pane = "%1".to_string();
+    config.llm.backend = "openai".to_string();
+    config.llm.openai = Some(OpenAiConfig {
+        api_key: "sk-test".to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_base: server.uri(),
+        model: "gpt-test".to_string(),
+    });
+    config.monitoring.stuck_sec = 3600;
+    config.recovery.steps = vec![RecoveryStep::new(RecoveryAction::LlmMessage { fallback: None })];
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%1", "Some output");
+    let (trigger, shutdown) = channel();
+    let mut state = PaneState::with_shutdown(shutdown);
+    trigger.trigger();
+
+    let recovered = tokio::time::timeout(Duration::from_secs(10), run_recovery_step(&config, &terminal, &mut state))
+        .await
+        .expect("激活等待没有被退出通知打断");
+
+    assert!(!recovered);
+    assert_eq!(terminal.sent(), vec![SentInput::Text { pane: "%1".to_string(), text: "请继续".to_string() }]);
+}
+
+/// 测试按键名称解析和 tmux 键名映射
+#[test]
+fn test_key_names() {
+    assert_eq!("C-c".parse::<Key>(), Ok(Key::CtrlC));
+    assert_eq!("ctrl+c".parse::<Key>(), Ok(Key::CtrlC));
+    assert_eq!("Esc".parse::<Key>(), Ok(Key::Escape));
+    assert_eq!("shift+tab".parse::<Key>(), Ok(Key::BackTab));
+    assert_eq!("2".parse::<Key>(), Ok(Key::Char('2')));
+    assert!("Escaping".parse::<Key>().is_err());
+    assert!("Ctrl+C+V".parse::<Key>().is_err());
+
+    assert_eq!(Key::CtrlC.tmux_name(), "C-c");
+    assert_eq!(Key::Enter.tmux_name(), "Enter");
+    assert_eq!(Key::BackTab.tmux_name(), "BTab");
+    assert_eq!(Key::Char(';').tmux_name(), ";");
+}
+
+/// 测试上下文一直已满时最多发送 MAX_COMPACT_ATTEMPTS 次 /compact
+#[tokio::test]
+async fn test_context_full_caps_compact_attempts() {
+    let mut config = Config::default();
+    config.tmux.pane = "%0".to_string();
+    config.monitoring.interval = 0;
+    config.monitoring.stuck_sec = 0;
+    config.llm.backend = "none".to_string();
+
+    let terminal = ScriptedTerminal::new();
+    terminal.push_screen("%0", "  ⎿  Prompt is too long\n\n> ");
+    let mut state = PaneState::new();
+
+    // 屏幕一直保持上下文已满，稍后移除窗格让循环结束
+    let terminal = Arc::new(terminal);
+    let remover = {
+        let terminal = Arc::clone(&terminal);
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            terminal.remove_pane("%0");
+        })
+    };
+    let result = run_monitoring_loop(&config, terminal.as_ref(), &mut state).await;
+    remover.join().unwrap();
+
+    assert!(result.is_ok());
+    let compacts = terminal
+        .sent()
+        .into_iter()
+        .filter(|input| matches!(input, SentInput::Text { text, .. } if text == "/compact"))
+        .count();
+    assert_eq!(compacts, MAX_COMPACT_ATTEMPTS);
+}
@@ -117,15 +117,12 @@ fn test_progress_detection() {
 
 #[test]
 fn test_time_increasing_logic() {
-    // 注意：这个测试可能失败，因为static mut的全局状态在测试之间可能不会重置
-    // 这里主要测试函数能正常工作，不测试具体的状态变化
+    let mut previous = None;
     
-    let pane_id = "test_pane";
-    
-    // 测试函数能正常调用（不假设全局状态）
-    let result = is_time_increasing("* Herding… (100s · ↑ 8.7k tokens · esc to interrupt)", pane_id);
-    // 结果可能是true或false，取决于全局状态，我们只确保函数不崩溃
-    println!("时间递增检测结果: {}", result);
+    // 第一次看到时间认为是活动的，并记录下来
+    assert!(is_time_increasing("* Herding… (100s · ↑ 8.7k tokens · esc to interrupt)", &mut previous));
+    assert_eq!(previous, Some(100));
+    assert!(!is_time_increasing("* Herding… (100s · ↑ 8.7k tokens · esc to interrupt)", &mut previous));
 }
 
 #[test]
@@ -214,14 +211,12 @@ fn test_performance_sensitive_operations() {
 
 #[test]
 fn test_concurrent_access() {
-    // 测试并发访问时间追踪器 - 注意static mut的状态问题
-    let pane1 = "test_pane_1";
-    let pane2 = "test_pane_2";
-    
-    // 不同pane的时间应该独立追踪
-    let result1 = is_time_increasing("* Herding… (100s · ↑ 8.7k tokens · esc to interrupt)", pane1);
-    let result2 = is_time_increasing("* Cogitating… (200s · ↓ 5.3k tokens · esc to interrupt)", pane2);
-    
-    // 只检查函数能正常调用，不假设具体结果（因为全局状态可能被其他测试影响）
-    println!("并发访问测试 - Pane1: {}, Pane2: {}", result1, result2);
+    // 每个窗格保存自己的上一次时间，互不影响
+    let mut pane1 = None;
+    let mut pane2 = None;
+    
+    let result1 = is_time_increasing("* Herding… (100s · ↑ 8.7k tokens · esc to interrupt)", &mut pane1);
+    let result2 = is_time_increasing("* Cogitating… (200s · ↓ 5.3k tokens · esc to interrupt)", &mut pane2);
+    assert!(result1 && result2);
+    assert!(is_time_increasing("* Herding… (150s · ↑ 8.7k tokens · esc to interrupt)", &mut pane1));
 }
\ No newline at end of file
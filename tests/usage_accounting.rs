@@ -0,0 +1,193 @@
+use chrono::{Duration, Local};
+use claude_watch::config::{BackendConfig, Config, ModelPrice, OllamaConfig, OpenAiConfig};
+use claude_watch::llm::usage::{load_records, summarize};
+use claude_watch::llm::{LlmChain, Usage, UsageLedger, UsageRecord};
+use claude_watch::ClaudeState;
+use predicates::prelude::*;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// 指向 mock 服务的 OpenAI 配置，用量日志写到临时目录
+async fn metered_config(server: &MockServer, dir: &tempfile::TempDir) -> Config {
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"role": "assistant", "content": "STUCK"}}],
+            "usage": {"prompt_tokens": 1200, "completion_tokens": 10, "total_tokens": 1210}
+        })))
+        .mount(server)
+        .await;
+    let mut config = Config::default();
+    config.llm.backend = "openai".to_string();
+    config.llm.openai = Some(OpenAiConfig {
+        api_key: "sk-test".to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_base: server.uri(),
+        model: "gpt-test".to_string(),
+    });
+    config.llm.usage.file = Some(dir.path().join("usage.jsonl").to_string_lossy().into_owned());
+    config.llm.usage.prices.insert("gpt-test".to_string(), ModelPrice { input: 2.5, output: 10.0 });
+    config
+}
+
+fn record(days_ago: i64, pane: &str, model: &str, cost: f64) -> UsageRecord {
+    UsageRecord {
+        timestamp: Local::now() - Duration::days(days_ago),
+        pane: pane.to_string(),
+        provider: "openai".to_string(),
+        model: model.to_string(),
+        prompt_tokens: 1000,
+        completion_tokens: 100,
+        cost,
+    }
+}
+
+/// 测试按每百万 token 的价格估算费用
+#[test]
+fn test_usage_cost() {
+    let price = ModelPrice { input: 3.0, output: 15.0 };
+    let cost = Usage::new(2_000_000, 100_000).cost(&price);
+    assert!((cost - 7.5).abs() < 1e-9, "{}", cost);
+}
+
+/// 测试调用记录写入用量日志，并按窗格累计当天用量
+#[tokio::test]
+async fn test_chain_records_usage() {
+    let server = MockServer::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let config = metered_config(&server, &dir).await;
+    let chain = LlmChain::new();
+
+    chain.classify("%1", "判断状态", "screen", &config.llm).await;
+    chain.classify("%2", "判断状态", "screen", &config.llm).await;
+
+    let records = load_records(&config.llm.usage.path()).unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!((records[0].pane.as_str(), records[0].provider.as_str()), ("%1", "openai"));
+    assert_eq!((records[0].model.as_str(), records[0].prompt_tokens, records[0].completion_tokens), ("gpt-test", 1200, 10));
+    assert!((records[0].cost - 0.0031).abs() < 1e-9, "{}", records[0].cost);
+
+    let pane = chain.ledger().pane_today("%1", &config.llm.usage).await;
+    assert_eq!((pane.calls, pane.prompt_tokens), (1, 1200));
+    assert_eq!(chain.ledger().today(&config.llm.usage).await.calls, 2);
+}
+
+/// 测试超出每日预算后跳过远程后端，判断降级为启发式规则，激活消息返回错误
+#[tokio::test]
+async fn test_daily_budget_skips_remote_backends() {
+    let server = MockServer::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = metered_config(&server, &dir).await;
+    config.llm.usage.daily_budget = Some(0.005);
+    let chain = LlmChain::new();
+
+    assert_eq!(chain.classify("%1", "判断状态", "screen", &config.llm).await.state, ClaudeState::Stuck);
+    assert_eq!(chain.classify("%1", "判断状态", "screen", &config.llm).await.state, ClaudeState::Stuck);
+    let verdict = chain.classify("%1", "判断状态", "Error: compilation failed", &config.llm).await;
+    assert_eq!(verdict.state, ClaudeState::Errored);
+    let error = chain.generate("%1", "激活助手", "激活一下", &config.llm).await.unwrap_err();
+    assert!(error.contains("超出每日预算"), "{}", error);
+
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
+
+/// 测试超出每日预算后仍然使用本机的 Ollama
+#[tokio::test]
+async fn test_daily_budget_keeps_local_ollama() {
+    let server = MockServer::start().await;
+    let ollama = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/generate"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "qwen-test",
+            "created_at": "2025-01-01T00:00:00Z",
+            "response": "WORKING",
+            "done": true
+        })))
+        .mount(&ollama)
+        .await;
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = metered_config(&server, &dir).await;
+    config.llm.usage.daily_budget = Some(0.001);
+    config.llm.ollama = Some(OllamaConfig { url: ollama.uri(), model: "qwen-test".to_string() });
+    config.llm.backends = vec![BackendConfig::new("openai"), BackendConfig::new("ollama")];
+    let chain = LlmChain::new();
+
+    assert_eq!(chain.classify("%1", "判断状态", "screen", &config.llm).await.state, ClaudeState::Stuck);
+    let verdict = chain.classify("%1", "判断状态", "Error: compilation failed", &config.llm).await;
+    assert_eq!(verdict.state, ClaudeState::Working);
+    assert_eq!(chain.generate("%1", "激活助手", "激活一下", &config.llm).await, Ok("WORKING".to_string()));
+
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    assert_eq!(ollama.received_requests().await.unwrap().len(), 2);
+}
+
+/// 测试重启后从用量日志恢复当天的累计费用
+#[tokio::test]
+async fn test_ledger_loads_today_from_log() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("usage.jsonl");
+    let lines: Vec<String> = [record(0, "%1", "gpt-test", 0.5), record(1, "%1", "gpt-test", 9.0)]
+        .iter()
+        .map(|record| serde_json::to_string(record).unwrap())
+        .collect();
+    std::fs::write(&path, lines.join("\n") + "\nnot json\n").unwrap();
+    let mut config = Config::default();
+    config.llm.usage.file = Some(path.to_string_lossy().into_owned());
+    config.llm.usage.daily_budget = Some(1.0);
+    config.llm.usage.prices.insert("gpt-test".to_string(), ModelPrice { input: 2.5, output: 10.0 });
+
+    let ledger = UsageLedger::new();
+    assert!((ledger.today(&config.llm.usage).await.cost - 0.5).abs() < 1e-9);
+    assert!(!ledger.budget_exceeded(&config.llm.usage).await);
+
+    ledger.record("%2", "openai", "gpt-test", Usage::new(200_000, 0), &config.llm.usage).await;
+    assert!(ledger.budget_exceeded(&config.llm.usage).await);
+    assert_eq!(load_records(&path).unwrap().len(), 3);
+}
+
+/// 测试按日期、窗格和模型汇总
+#[test]
+fn test_summarize() {
+    let records = vec![
+        record(0, "%1", "gpt-test", 0.25),
+        record(0, "%2", "gpt-test", 0.25),
+        record(2, "%1", "claude-test", 1.0),
+        record(30, "%1", "gpt-test", 100.0),
+    ];
+
+    let summary = summarize(&records, Local::now().date_naive() - Duration::days(6));
+
+    assert_eq!(summary.total.calls, 3);
+    assert!((summary.total.cost - 1.5).abs() < 1e-9);
+    assert_eq!(summary.by_day.len(), 2);
+    assert_eq!(summary.by_pane["%1"].calls, 2);
+    assert_eq!(summary.by_model["openai/gpt-test"].prompt_tokens, 2000);
+    let text = summary.to_string();
+    assert!(text.contains("openai/claude-test") && text.contains("$1.5000"), "{}", text);
+}
+
+/// 测试 usage 子命令输出汇总
+#[test]
+fn test_usage_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("usage.jsonl");
+    std::fs::write(&path, serde_json::to_string(&record(0, "%3", "gpt-test", 0.125)).unwrap() + "\n").unwrap();
+    let config = dir.path().join("config.yaml");
+    std::fs::write(
+        &config,
+        format!(
+            "llm:\n  backend: none\n  usage:\n    file: {}\nmonitoring:\n  interval: 5\n  stuck_sec: 60\n  max_retry: 3\ntmux:\n  pane: \"%0\"\n",
+            path.display()
+        ),
+    )
+    .unwrap();
+
+    assert_cmd::Command::cargo_bin("claude-watch")
+        .unwrap()
+        .args(["--config", config.to_str().unwrap(), "usage", "--days", "1"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("%3").and(predicates::str::contains("$0.1250")));
+}
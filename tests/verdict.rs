@@ -0,0 +1,69 @@
+use claude_watch::llm::parse_verdict;
+use claude_watch::{ClaudeState, Verdict};
+
+/// 测试解析标准 JSON 判断结果
+#[test]
+fn test_parse_json_verdict() {
+    let verdict = parse_verdict(
+        r#"{"state": "STUCK", "confidence": 0.8, "reason": "停在 cargo build 输出", "suggested_action": "发送 继续"}"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        verdict,
+        Verdict {
+            state: ClaudeState::Stuck,
+            confidence: Some(0.8),
+            reason: "停在 cargo build 输出".to_string(),
+            suggested_action: Some("发送 继续".to_string()),
+        }
+    );
+}
+
+/// 测试代码块、前后说明文字、字段别名和不规范的标签
+#[test]
+fn test_parse_messy_json_verdict() {
+    let verdict = parse_verdict(
+        "Here is my answer:\n```json\n{\"status\": \"waiting for permission\", \"confidence\": \"85%\", \"explanation\": \"对话框\"}\n```",
+    )
+    .unwrap();
+    assert_eq!(verdict.state, ClaudeState::WaitingForPermission);
+    assert_eq!(verdict.confidence, Some(0.85));
+    assert_eq!(verdict.reason, "对话框");
+
+    let verdict = parse_verdict(r#"{"state": "done.", "confidence": 90, "reason": "", "suggested_action": " "}"#).unwrap();
+    assert_eq!(verdict.state, ClaudeState::Done);
+    assert_eq!(verdict.confidence, Some(0.9));
+    assert_eq!(verdict.suggested_action, None);
+}
+
+/// 测试只有状态标签的纯文本回答
+#[test]
+fn test_parse_plain_label() {
+    assert_eq!(parse_verdict("Done.").unwrap(), Verdict::new(ClaudeState::Done, ""));
+    assert_eq!(parse_verdict("STUCK\n").unwrap().state, ClaudeState::Stuck);
+    assert_eq!(parse_verdict("context-full").unwrap().state, ClaudeState::ContextFull);
+
+    let verdict = parse_verdict("The screen shows a compile error, so ERRORED is not right: it is STUCK").unwrap();
+    assert_eq!(verdict.state, ClaudeState::Errored, "优先匹配第一个全大写标签");
+    assert!(!verdict.reason.is_empty());
+}
+
+/// 测试无法识别的回答报错
+#[test]
+fn test_parse_unknown_verdict() {
+    assert!(parse_verdict("MAYBE").is_err());
+    assert!(parse_verdict(r#"{"state": "MAYBE", "reason": "?"}"#).is_err());
+    assert!(parse_verdict("").is_err());
+}
+
+/// 测试判断结果的日志格式
+#[test]
+fn test_verdict_display() {
+    let verdict = Verdict {
+        confidence: Some(0.8),
+        ..Verdict::new(ClaudeState::Stuck, "停在 cargo build 输出")
+    };
+    assert_eq!(verdict.to_string(), "STUCK (置信度 0.80): 停在 cargo build 输出");
+    assert_eq!(Verdict::new(ClaudeState::Done, "").to_string(), "DONE");
+}
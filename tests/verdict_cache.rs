@@ -0,0 +1,133 @@
+use claude_watch::config::{BackendConfig, CacheConfig, Config, OpenAiConfig};
+use claude_watch::llm::VerdictCache;
+use claude_watch::prompt::PromptContext;
+use claude_watch::{ask_llm_final_status, ClaudeState, Verdict};
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn cache_config(ttl: u64, max_entries: usize) -> CacheConfig {
+    CacheConfig { ttl, max_entries }
+}
+
+/// 测试缓存键忽略计时和 token 计数的变化
+#[test]
+fn test_key_uses_core_content() {
+    let first = "✻ Compiling… (12s · ↑ 340 tokens · esc to interrupt)\n> cargo build";
+    let later = "✻ Compiling… (57s · ↑ 1200 tokens · esc to interrupt)\n> cargo build";
+
+    assert_eq!(VerdictCache::key(first), VerdictCache::key(later));
+    assert_ne!(VerdictCache::key(first), VerdictCache::key("> cargo test"));
+}
+
+/// 测试命中、未命中和统计
+#[test]
+fn test_get_and_stats() {
+    let cache = VerdictCache::new();
+    let config = cache_config(600, 16);
+    let verdict = Verdict::new(ClaudeState::Stuck, "停在构建输出");
+
+    assert_eq!(cache.get("screen a", &config), None);
+    cache.insert("screen a", &verdict, &config);
+    assert_eq!(cache.get("screen a", &config), Some(verdict.clone()));
+    assert_eq!(cache.get("screen a", &config), Some(verdict));
+    assert_eq!(cache.get("screen b", &config), None);
+
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (2, 2, 1));
+    assert_eq!(stats.to_string(), "命中 2 次，未命中 2 次，缓存 1 条");
+}
+
+/// 测试过期的条目不再命中，ttl 为 0 时不缓存
+#[test]
+fn test_ttl() {
+    let cache = VerdictCache::new();
+    let verdict = Verdict::new(ClaudeState::Stuck, "");
+
+    cache.insert("screen", &verdict, &cache_config(1, 16));
+    std::thread::sleep(Duration::from_millis(1100));
+    assert_eq!(cache.get("screen", &cache_config(1, 16)), None);
+    assert_eq!(cache.stats().entries, 0);
+
+    cache.insert("screen", &verdict, &cache_config(0, 16));
+    assert_eq!(cache.get("screen", &cache_config(600, 16)), None);
+}
+
+/// 测试超过 max_entries 时淘汰最早保存的条目
+#[test]
+fn test_evicts_oldest() {
+    let cache = VerdictCache::new();
+    let config = cache_config(600, 2);
+    for screen in ["first", "second", "third"] {
+        cache.insert(screen, &Verdict::new(ClaudeState::Stuck, screen), &config);
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    assert_eq!(cache.stats().entries, 2);
+    assert_eq!(cache.get("first", &config), None);
+    assert!(cache.get("third", &config).is_some());
+}
+
+/// 测试相同画面只调用一次 LLM，后端失败时的启发式结果不缓存
+#[tokio::test]
+async fn test_identical_screen_calls_llm_once() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"role": "assistant", "content": "{\"state\": \"STUCK\", \"reason\": \"画面没有变化\"}"}}]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut config = Config::default();
+    config.llm.backend = "openai".to_string();
+    config.llm.openai = Some(OpenAiConfig {
+        api_key: "sk-test".to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_base: server.uri(),
+        model: "gpt-test".to_string(),
+    });
+
+    let first = PromptContext::new("%0", "verdict_cache 测试画面 (3s)\n>");
+    let again = PromptContext::new("%0", "verdict_cache 测试画面 (45s)\n>");
+    assert_eq!(ask_llm_final_status(&first, &config).await.reason, "画面没有变化");
+    assert_eq!(ask_llm_final_status(&again, &config).await.reason, "画面没有变化");
+
+    config.llm.openai = None;
+    let failed = PromptContext::new("%0", "verdict_cache 后端不可用的画面");
+    assert_eq!(ask_llm_final_status(&failed, &config).await.reason, "LLM 不可用，启发式规则判断");
+    assert_eq!(VerdictCache::global().get(&failed.screen, &config.llm.cache), None);
+}
+
+/// 测试降级链中的 none 给出的启发式结果不缓存，LLM 恢复后重新判断
+#[tokio::test]
+async fn test_heuristic_backend_in_chain_not_cached() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"role": "assistant", "content": "{\"state\": \"WORKING\", \"reason\": \"LLM 判断\"}"}}]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut config = Config::default();
+    config.llm.backends = vec![BackendConfig::new("openai"), BackendConfig::new("none")];
+    config.llm.retries = 0;
+    config.llm.openai = Some(OpenAiConfig {
+        api_key: String::new(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_base: server.uri(),
+        model: "gpt-test".to_string(),
+    });
+    let context = PromptContext::new("%0", "verdict_cache 降级到 none 的画面\n>");
+
+    assert_eq!(ask_llm_final_status(&context, &config).await.reason, "启发式规则判断");
+    assert_eq!(VerdictCache::global().get(&context.screen, &config.llm.cache), None);
+
+    config.llm.openai.as_mut().unwrap().api_key = "sk-test".to_string();
+    assert_eq!(ask_llm_final_status(&context, &config).await.reason, "LLM 判断");
+    assert_eq!(ask_llm_final_status(&context, &config).await.reason, "LLM 判断");
+}